@@ -0,0 +1,80 @@
+//! # Throughput of the emulator on a compute-bound loop
+//!
+//! Runs a loop of arithmetic instructions (including division) without any
+//! system calls in between and reports the achieved instruction rate. The
+//! assertions on the hot path of the interpreter are `debug_assert!`s, to
+//! compare against a build keeping them run this benchmark once as is and
+//! once with `CARGO_PROFILE_BENCH_DEBUG_ASSERTIONS=true`:
+//!
+//!   cargo bench --bench compute_loop
+
+use riscu::{Instruction, Program, ProgramSegment, Register};
+use std::io;
+use std::time::{Duration, Instant};
+use unicorn::emulate::EmulatorState;
+
+const CODE_START: u64 = 0x10000;
+const DATA_START: u64 = 0x20000;
+const MEMORY_SIZE: usize = 1024 * 1024;
+const ITERATIONS: i32 = 256; // in units of 4096, loaded via `lui`
+const RUNS: usize = 5;
+
+fn compute_loop() -> Program {
+    let code = [
+        Instruction::new_lui(Register::S1, ITERATIONS),
+        Instruction::new_addi(Register::A1, Register::Zero, 1234),
+        Instruction::new_addi(Register::A2, Register::Zero, 7),
+        // loop:
+        Instruction::new_mul(Register::A3, Register::A1, Register::A2),
+        Instruction::new_add(Register::A1, Register::A1, Register::A3),
+        Instruction::new_divu(Register::A4, Register::A1, Register::A2),
+        Instruction::new_remu(Register::A5, Register::A1, Register::A2),
+        Instruction::new_addi(Register::S1, Register::S1, -1),
+        Instruction::new_beq(Register::S1, Register::Zero, 8),
+        Instruction::new_jal(Register::Zero, -24),
+        // exit(0)
+        Instruction::new_addi(Register::A0, Register::Zero, 0),
+        Instruction::new_addi(Register::A7, Register::Zero, 93),
+        Instruction::new_ecall(),
+    ];
+    let content: Vec<u8> = code
+        .iter()
+        .flat_map(|instr| u32::from(*instr).to_le_bytes())
+        .collect();
+    let code_end = CODE_START + content.len() as u64;
+    Program {
+        code: ProgramSegment {
+            address: CODE_START,
+            content,
+        },
+        data: ProgramSegment {
+            address: DATA_START,
+            content: vec![],
+        },
+        instruction_range: CODE_START..code_end,
+        is64: true,
+    }
+}
+
+fn main() {
+    let program = compute_loop();
+    let argv = [String::from("compute_loop")];
+    let mut best = Duration::MAX;
+    let mut instructions = 0;
+    for _ in 0..RUNS {
+        let mut emulator = EmulatorState::new(MEMORY_SIZE);
+        emulator.bootstrap(&program, &argv).expect("can bootstrap");
+        emulator.set_stdio(io::empty(), io::sink(), io::sink());
+        let start = Instant::now();
+        let report = emulator.run().expect("can run to completion");
+        best = best.min(start.elapsed());
+        instructions = report.instructions_retired;
+    }
+    println!(
+        "compute loop: {} instructions in {:?} ({:.1} MIPS, best of {})",
+        instructions,
+        best,
+        instructions as f64 / best.as_secs_f64() / 1e6,
+        RUNS
+    );
+}
@@ -77,6 +77,32 @@ pub fn args() -> Command {
                         .default_value(DEFAULT_MEMORY_SIZE)
                         .value_parser(value_parser_memory_size()),
                 )
+                .arg(
+                    Arg::new("argv0")
+                        .help("Program name passed as first argument [default: FILE]")
+                        .long("argv0")
+                        .num_args(1)
+                        .value_name("NAME"),
+                )
+                .arg(
+                    Arg::new("protect-rodata")
+                        .help("Fail on writes to read-only data (e.g. .rodata) if known")
+                        .long("protect-rodata")
+                        .num_args(0),
+                )
+                .arg(
+                    Arg::new("output-log")
+                        .help("Mirror all output of the emulated program to a log file")
+                        .long("output-log")
+                        .num_args(1)
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("summary")
+                        .help("Print a one-line summary of the run to stderr")
+                        .long("summary")
+                        .num_args(0),
+                )
                 .arg(
                     Arg::new("extras")
                         .help("Arguments passed to emulated program")
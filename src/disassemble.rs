@@ -66,6 +66,16 @@ impl fmt::Display for Disassembly {
     }
 }
 
+// Textual representation of a single instruction in assembler syntax.
+#[derive(Clone, Copy, Debug)]
+pub struct InstructionText(pub Instruction);
+
+impl fmt::Display for InstructionText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_instruction(f, self.0)
+    }
+}
+
 fn enumerate_with<T>(
     iter: impl Iterator<Item = T>,
     start: u64,
@@ -76,80 +86,83 @@ fn enumerate_with<T>(
 }
 
 fn print_instruction(f: &mut fmt::Formatter<'_>, pc: u64, i: Instruction) -> fmt::Result {
+    writeln!(f, "{:#x}: {}", pc, InstructionText(i))
+}
+
+fn write_instruction(f: &mut fmt::Formatter<'_>, i: Instruction) -> fmt::Result {
     match i {
-        Instruction::Lui(t) => print_utype(f, pc, "lui", t),
-        Instruction::Auipc(t) => print_utype(f, pc, "auipc", t),
-        Instruction::Jal(t) => print_jtype(f, pc, "jal", t),
-        Instruction::Jalr(t) => print_itype(f, pc, "jalr", t),
-        Instruction::Beq(t) => print_btype(f, pc, "beq", t),
-        Instruction::Bne(t) => print_btype(f, pc, "bne", t),
-        Instruction::Blt(t) => print_btype(f, pc, "blt", t),
-        Instruction::Bge(t) => print_btype(f, pc, "bge", t),
-        Instruction::Bltu(t) => print_btype(f, pc, "bltu", t),
-        Instruction::Bgeu(t) => print_btype(f, pc, "bgeu", t),
-        Instruction::Lb(t) => print_itype(f, pc, "lb", t),
-        Instruction::Lh(t) => print_itype(f, pc, "lh", t),
-        Instruction::Lw(t) => print_itype(f, pc, "lw", t),
-        Instruction::Ld(t) => print_itype(f, pc, "ld", t),
-        Instruction::Lbu(t) => print_itype(f, pc, "lbu", t),
-        Instruction::Lhu(t) => print_itype(f, pc, "lhu", t),
-        Instruction::Lwu(t) => print_itype(f, pc, "lwu", t),
-        Instruction::Sb(t) => print_stype(f, pc, "sb", t),
-        Instruction::Sh(t) => print_stype(f, pc, "sh", t),
-        Instruction::Sw(t) => print_stype(f, pc, "sw", t),
-        Instruction::Sd(t) => print_stype(f, pc, "sd", t),
-        Instruction::Fence(t) => print_itype(f, pc, "fence", t),
-        Instruction::Addi(t) => print_itype(f, pc, "addi", t),
-        Instruction::Slti(t) => print_itype(f, pc, "slti", t),
-        Instruction::Sltiu(t) => print_itype(f, pc, "sltiu", t),
-        Instruction::Xori(t) => print_itype(f, pc, "xori", t),
-        Instruction::Ori(t) => print_itype(f, pc, "ori", t),
-        Instruction::Andi(t) => print_itype(f, pc, "andi", t),
-        Instruction::Slli(t) => print_shift(f, pc, "slli", t),
-        Instruction::Srli(t) => print_shift(f, pc, "srli", t),
-        Instruction::Srai(t) => print_shift(f, pc, "srai", t),
-        Instruction::Addiw(t) => print_itype(f, pc, "addiw", t),
-        Instruction::Slliw(t) => print_shift(f, pc, "slliw", t),
-        Instruction::Srliw(t) => print_shift(f, pc, "srliw", t),
-        Instruction::Sraiw(t) => print_shift(f, pc, "sraiw", t),
-        Instruction::Add(t) => print_rtype(f, pc, "add", t),
-        Instruction::Sub(t) => print_rtype(f, pc, "sub", t),
-        Instruction::Sll(t) => print_rtype(f, pc, "sll", t),
-        Instruction::Slt(t) => print_rtype(f, pc, "slt", t),
-        Instruction::Sltu(t) => print_rtype(f, pc, "sltu", t),
-        Instruction::Xor(t) => print_rtype(f, pc, "xor", t),
-        Instruction::Srl(t) => print_rtype(f, pc, "srl", t),
-        Instruction::Sra(t) => print_rtype(f, pc, "sra", t),
-        Instruction::Or(t) => print_rtype(f, pc, "or", t),
-        Instruction::And(t) => print_rtype(f, pc, "and", t),
-        Instruction::Mul(t) => print_rtype(f, pc, "mul", t),
-        Instruction::Mulh(t) => print_rtype(f, pc, "mulh", t),
-        Instruction::Mulhsu(t) => print_rtype(f, pc, "mulhsu", t),
-        Instruction::Mulhu(t) => print_rtype(f, pc, "mulhu", t),
-        Instruction::Div(t) => print_rtype(f, pc, "div", t),
-        Instruction::Divu(t) => print_rtype(f, pc, "divu", t),
-        Instruction::Rem(t) => print_rtype(f, pc, "rem", t),
-        Instruction::Remu(t) => print_rtype(f, pc, "remu", t),
-        Instruction::Addw(t) => print_rtype(f, pc, "addw", t),
-        Instruction::Subw(t) => print_rtype(f, pc, "subw", t),
-        Instruction::Sllw(t) => print_rtype(f, pc, "sllw", t),
-        Instruction::Srlw(t) => print_rtype(f, pc, "srlw", t),
-        Instruction::Sraw(t) => print_rtype(f, pc, "sraw", t),
-        Instruction::Mulw(t) => print_rtype(f, pc, "mulw", t),
-        Instruction::Divw(t) => print_rtype(f, pc, "divw", t),
-        Instruction::Divuw(t) => print_rtype(f, pc, "divuw", t),
-        Instruction::Remw(t) => print_rtype(f, pc, "remw", t),
-        Instruction::Remuw(t) => print_rtype(f, pc, "remuw", t),
-        Instruction::Ecall(_) => writeln!(f, "{:#x}: ecall", pc),
+        Instruction::Lui(t) => print_utype(f, "lui", t),
+        Instruction::Auipc(t) => print_utype(f, "auipc", t),
+        Instruction::Jal(t) => print_jtype(f, "jal", t),
+        Instruction::Jalr(t) => print_itype(f, "jalr", t),
+        Instruction::Beq(t) => print_btype(f, "beq", t),
+        Instruction::Bne(t) => print_btype(f, "bne", t),
+        Instruction::Blt(t) => print_btype(f, "blt", t),
+        Instruction::Bge(t) => print_btype(f, "bge", t),
+        Instruction::Bltu(t) => print_btype(f, "bltu", t),
+        Instruction::Bgeu(t) => print_btype(f, "bgeu", t),
+        Instruction::Lb(t) => print_itype(f, "lb", t),
+        Instruction::Lh(t) => print_itype(f, "lh", t),
+        Instruction::Lw(t) => print_itype(f, "lw", t),
+        Instruction::Ld(t) => print_itype(f, "ld", t),
+        Instruction::Lbu(t) => print_itype(f, "lbu", t),
+        Instruction::Lhu(t) => print_itype(f, "lhu", t),
+        Instruction::Lwu(t) => print_itype(f, "lwu", t),
+        Instruction::Sb(t) => print_stype(f, "sb", t),
+        Instruction::Sh(t) => print_stype(f, "sh", t),
+        Instruction::Sw(t) => print_stype(f, "sw", t),
+        Instruction::Sd(t) => print_stype(f, "sd", t),
+        Instruction::Fence(t) => print_itype(f, "fence", t),
+        Instruction::Addi(t) => print_itype(f, "addi", t),
+        Instruction::Slti(t) => print_itype(f, "slti", t),
+        Instruction::Sltiu(t) => print_itype(f, "sltiu", t),
+        Instruction::Xori(t) => print_itype(f, "xori", t),
+        Instruction::Ori(t) => print_itype(f, "ori", t),
+        Instruction::Andi(t) => print_itype(f, "andi", t),
+        Instruction::Slli(t) => print_shift(f, "slli", t),
+        Instruction::Srli(t) => print_shift(f, "srli", t),
+        Instruction::Srai(t) => print_shift(f, "srai", t),
+        Instruction::Addiw(t) => print_itype(f, "addiw", t),
+        Instruction::Slliw(t) => print_shift(f, "slliw", t),
+        Instruction::Srliw(t) => print_shift(f, "srliw", t),
+        Instruction::Sraiw(t) => print_shift(f, "sraiw", t),
+        Instruction::Add(t) => print_rtype(f, "add", t),
+        Instruction::Sub(t) => print_rtype(f, "sub", t),
+        Instruction::Sll(t) => print_rtype(f, "sll", t),
+        Instruction::Slt(t) => print_rtype(f, "slt", t),
+        Instruction::Sltu(t) => print_rtype(f, "sltu", t),
+        Instruction::Xor(t) => print_rtype(f, "xor", t),
+        Instruction::Srl(t) => print_rtype(f, "srl", t),
+        Instruction::Sra(t) => print_rtype(f, "sra", t),
+        Instruction::Or(t) => print_rtype(f, "or", t),
+        Instruction::And(t) => print_rtype(f, "and", t),
+        Instruction::Mul(t) => print_rtype(f, "mul", t),
+        Instruction::Mulh(t) => print_rtype(f, "mulh", t),
+        Instruction::Mulhsu(t) => print_rtype(f, "mulhsu", t),
+        Instruction::Mulhu(t) => print_rtype(f, "mulhu", t),
+        Instruction::Div(t) => print_rtype(f, "div", t),
+        Instruction::Divu(t) => print_rtype(f, "divu", t),
+        Instruction::Rem(t) => print_rtype(f, "rem", t),
+        Instruction::Remu(t) => print_rtype(f, "remu", t),
+        Instruction::Addw(t) => print_rtype(f, "addw", t),
+        Instruction::Subw(t) => print_rtype(f, "subw", t),
+        Instruction::Sllw(t) => print_rtype(f, "sllw", t),
+        Instruction::Srlw(t) => print_rtype(f, "srlw", t),
+        Instruction::Sraw(t) => print_rtype(f, "sraw", t),
+        Instruction::Mulw(t) => print_rtype(f, "mulw", t),
+        Instruction::Divw(t) => print_rtype(f, "divw", t),
+        Instruction::Divuw(t) => print_rtype(f, "divuw", t),
+        Instruction::Remw(t) => print_rtype(f, "remw", t),
+        Instruction::Remuw(t) => print_rtype(f, "remuw", t),
+        Instruction::Ecall(_) => write!(f, "ecall"),
         _ => todo!("{:?}", i),
     }
 }
 
-fn print_rtype(f: &mut fmt::Formatter<'_>, pc: u64, op: &str, rtype: RType) -> fmt::Result {
-    writeln!(
+fn print_rtype(f: &mut fmt::Formatter<'_>, op: &str, rtype: RType) -> fmt::Result {
+    write!(
         f,
-        "{:#x}: {} {:?},{:?},{:?}",
-        pc,
+        "{} {:?},{:?},{:?}",
         op,
         rtype.rd(),
         rtype.rs1(),
@@ -157,11 +170,10 @@ fn print_rtype(f: &mut fmt::Formatter<'_>, pc: u64, op: &str, rtype: RType) -> f
     )
 }
 
-fn print_itype(f: &mut fmt::Formatter<'_>, pc: u64, op: &str, itype: IType) -> fmt::Result {
-    writeln!(
+fn print_itype(f: &mut fmt::Formatter<'_>, op: &str, itype: IType) -> fmt::Result {
+    write!(
         f,
-        "{:#x}: {} {:?},{:?},{}",
-        pc,
+        "{} {:?},{:?},{}",
         op,
         itype.rd(),
         itype.rs1(),
@@ -169,11 +181,10 @@ fn print_itype(f: &mut fmt::Formatter<'_>, pc: u64, op: &str, itype: IType) -> f
     )
 }
 
-fn print_stype(f: &mut fmt::Formatter<'_>, pc: u64, op: &str, stype: SType) -> fmt::Result {
-    writeln!(
+fn print_stype(f: &mut fmt::Formatter<'_>, op: &str, stype: SType) -> fmt::Result {
+    write!(
         f,
-        "{:#x}: {} {:?},{}({:?})",
-        pc,
+        "{} {:?},{}({:?})",
         op,
         stype.rs2(),
         stype.imm(),
@@ -181,15 +192,14 @@ fn print_stype(f: &mut fmt::Formatter<'_>, pc: u64, op: &str, stype: SType) -> f
     )
 }
 
-fn print_jtype(f: &mut fmt::Formatter<'_>, pc: u64, op: &str, jtype: JType) -> fmt::Result {
-    writeln!(f, "{:#x}: {} {:?},{}", pc, op, jtype.rd(), jtype.imm())
+fn print_jtype(f: &mut fmt::Formatter<'_>, op: &str, jtype: JType) -> fmt::Result {
+    write!(f, "{} {:?},{}", op, jtype.rd(), jtype.imm())
 }
 
-fn print_btype(f: &mut fmt::Formatter<'_>, pc: u64, op: &str, btype: BType) -> fmt::Result {
-    writeln!(
+fn print_btype(f: &mut fmt::Formatter<'_>, op: &str, btype: BType) -> fmt::Result {
+    write!(
         f,
-        "{:#x}: {} {:?},{:?},{}",
-        pc,
+        "{} {:?},{:?},{}",
         op,
         btype.rs1(),
         btype.rs2(),
@@ -197,15 +207,14 @@ fn print_btype(f: &mut fmt::Formatter<'_>, pc: u64, op: &str, btype: BType) -> f
     )
 }
 
-fn print_utype(f: &mut fmt::Formatter<'_>, pc: u64, op: &str, utype: UType) -> fmt::Result {
-    writeln!(f, "{:#x}: {} {:?},{:#x}", pc, op, utype.rd(), utype.imm())
+fn print_utype(f: &mut fmt::Formatter<'_>, op: &str, utype: UType) -> fmt::Result {
+    write!(f, "{} {:?},{:#x}", op, utype.rd(), utype.imm())
 }
 
-fn print_shift(f: &mut fmt::Formatter<'_>, pc: u64, op: &str, itype: IType) -> fmt::Result {
-    writeln!(
+fn print_shift(f: &mut fmt::Formatter<'_>, op: &str, itype: IType) -> fmt::Result {
+    write!(
         f,
-        "{:#x}: {} {:?},{:?},{}",
-        pc,
+        "{} {:?},{:?},{}",
         op,
         itype.rd(),
         itype.rs1(),
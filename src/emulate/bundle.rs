@@ -0,0 +1,92 @@
+use super::{EmulatorConfig, EmulatorError, ScriptedInput};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+// Bundle of all sources of nondeterminism modeled by the emulator, which
+// together with the program and its arguments reproduces a run exactly.
+// Applying a bundle fixes the seed of `getrandom`, the rate of the clock
+// backing `times` and `getrusage`, and replaces standard input with the
+// given script. Not captured are the contents of host files opened by
+// the guest and the configuration unrelated to these sources (e.g. the
+// memory size), both have to be provided alongside the bundle.
+//
+// Bundles are saved as text, one setting per line:
+//   - `random-seed <seed>`
+//   - `instructions-per-tick <count>`
+//   - `stdin <delay> <hex bytes>`, one line per entry of the script
+#[derive(Clone, Debug)]
+pub struct DeterminismConfig {
+    pub random_seed: u64,
+    pub instructions_per_tick: u64,
+    pub stdin: ScriptedInput,
+}
+
+impl Default for DeterminismConfig {
+    fn default() -> Self {
+        let config = EmulatorConfig::default();
+        Self {
+            random_seed: config.random_seed,
+            instructions_per_tick: config.instructions_per_tick,
+            stdin: ScriptedInput::new(),
+        }
+    }
+}
+
+impl DeterminismConfig {
+    pub fn save<P>(&self, path: P) -> Result<(), EmulatorError>
+    where
+        P: AsRef<Path>,
+    {
+        let mut file = File::create(path)?;
+        writeln!(file, "random-seed {}", self.random_seed)?;
+        writeln!(file, "instructions-per-tick {}", self.instructions_per_tick)?;
+        for (delay, bytes) in self.stdin.delayed_entries() {
+            let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            writeln!(file, "stdin {} {}", delay, hex)?;
+        }
+        Ok(())
+    }
+
+    pub fn load<P>(path: P) -> Result<Self, EmulatorError>
+    where
+        P: AsRef<Path>,
+    {
+        let contents = std::fs::read_to_string(path)?;
+        let mut bundle = Self::default();
+        for (index, line) in contents.lines().enumerate() {
+            let malformed = || EmulatorError::MalformedBundle { line: index + 1 };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [] => {}
+                ["random-seed", seed] => {
+                    bundle.random_seed = seed.parse().map_err(|_| malformed())?;
+                }
+                ["instructions-per-tick", count] => {
+                    bundle.instructions_per_tick = count.parse().map_err(|_| malformed())?;
+                }
+                ["stdin", delay, hex @ ..] if hex.len() <= 1 => {
+                    let delay = delay.parse().map_err(|_| malformed())?;
+                    let hex = hex.first().unwrap_or(&"");
+                    let bytes = parse_hex(hex).ok_or_else(malformed)?;
+                    bundle.stdin = bundle.stdin.then(delay, &bytes);
+                }
+                _ => return Err(malformed()),
+            }
+        }
+        Ok(bundle)
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+        })
+        .collect()
+}
@@ -0,0 +1,96 @@
+use super::MemoryAccess;
+use std::collections::VecDeque;
+
+// Observational model of a data cache, fed with the effective addresses
+// of all data accesses performed by load and store instructions (see
+// `attach_cache`). It has no influence on emulation whatsoever.
+pub trait CacheModel: Send {
+    fn access(&mut self, access: &MemoryAccess);
+    fn stats(&self) -> CacheStats;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    pub fn miss_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.misses as f64 / total as f64,
+        }
+    }
+}
+
+// Set-associative cache with least-recently-used replacement, where one
+// way makes it direct-mapped. Loads and stores are treated alike (i.e.
+// write-allocate), an access spanning two lines counts as one access to
+// each of them. The default geometry is a 32 KiB cache with 64-byte lines
+// and four ways (i.e. 128 sets).
+#[derive(Clone, Debug)]
+pub struct SetAssociativeCache {
+    line_size: u64,
+    ways: usize,
+    sets: Vec<VecDeque<u64>>,
+    stats: CacheStats,
+}
+
+impl SetAssociativeCache {
+    pub fn new(size: u64, line_size: u64, ways: usize) -> Self {
+        assert!(line_size.is_power_of_two(), "line size is a power of two");
+        assert!(ways > 0, "at least one way");
+        let set_size = line_size * ways as u64;
+        assert!(
+            size >= set_size && size % set_size == 0,
+            "size is a multiple of line size times ways"
+        );
+        Self {
+            line_size,
+            ways,
+            sets: vec![VecDeque::with_capacity(ways); (size / set_size) as usize],
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub fn direct_mapped(size: u64, line_size: u64) -> Self {
+        Self::new(size, line_size, 1)
+    }
+
+    fn access_line(&mut self, line: u64) {
+        let index = (line % self.sets.len() as u64) as usize;
+        let set = &mut self.sets[index];
+        match set.iter().position(|l| *l == line) {
+            Some(way) => {
+                set.remove(way);
+                self.stats.hits += 1;
+            }
+            None => {
+                set.truncate(self.ways - 1);
+                self.stats.misses += 1;
+            }
+        }
+        set.push_front(line); // most recently used first
+    }
+}
+
+impl Default for SetAssociativeCache {
+    fn default() -> Self {
+        Self::new(32 * 1024, 64, 4)
+    }
+}
+
+impl CacheModel for SetAssociativeCache {
+    fn access(&mut self, access: &MemoryAccess) {
+        let first = access.effective_address / self.line_size;
+        let last = (access.effective_address + access.size - 1) / self.line_size;
+        for line in first..=last {
+            self.access_line(line);
+        }
+    }
+
+    fn stats(&self) -> CacheStats {
+        self.stats
+    }
+}
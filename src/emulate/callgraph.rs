@@ -0,0 +1,80 @@
+use super::{is_call, EmulatorValue};
+use riscu::Instruction;
+use std::collections::BTreeMap;
+
+// Dynamic call graph with edges between function entry points, counting
+// how often each caller called each callee. Calls and returns are not
+// explicit in RISC-V, the following heuristics are used to identify them:
+//   - A `jal` or `jalr` writing the link register `ra` (or the alternate
+//     link register `t0`) is a call, its target is the entry point of the
+//     callee. The address after the call is remembered as return address.
+//   - A `jal` or `jalr` jumping to a remembered return address is a return,
+//     which also returns from all calls made after that (e.g. `longjmp`).
+//   - Any other jump (e.g. a tail call through `jr` or `j`) is treated as
+//     a jump within the current function, hence calls performed by a tail
+//     called function are attributed to the function performing the tail
+//     call.
+#[derive(Clone, Debug)]
+pub struct CallGraph {
+    root: EmulatorValue,
+    edges: BTreeMap<(EmulatorValue, EmulatorValue), u64>,
+    frames: Vec<(EmulatorValue, EmulatorValue)>,
+}
+
+impl CallGraph {
+    pub(super) fn new(root: EmulatorValue) -> Self {
+        Self {
+            root,
+            edges: BTreeMap::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    // Number of calls per `(caller, callee)` pair of entry points.
+    pub fn edges(&self) -> &BTreeMap<(EmulatorValue, EmulatorValue), u64> {
+        &self.edges
+    }
+
+    // Number of calls that have not returned yet.
+    pub(super) fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    // Renders the graph in the DOT format of Graphviz.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph calls {\n");
+        for ((caller, callee), count) in &self.edges {
+            dot.push_str(&format!(
+                "  \"{:#x}\" -> \"{:#x}\" [label=\"{}\"];\n",
+                caller, callee, count
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub(super) fn observe_jump(
+        &mut self,
+        instr: Instruction,
+        return_address: EmulatorValue,
+        target: EmulatorValue,
+    ) -> JumpKind {
+        if is_call(instr) {
+            let caller = self.frames.last().map_or(self.root, |(entry, _)| *entry);
+            *self.edges.entry((caller, target)).or_insert(0) += 1;
+            self.frames.push((target, return_address));
+            JumpKind::Call
+        } else if let Some(depth) = self.frames.iter().rposition(|(_, ra)| *ra == target) {
+            self.frames.truncate(depth);
+            JumpKind::Return
+        } else {
+            JumpKind::Jump
+        }
+    }
+}
+
+pub(super) enum JumpKind {
+    Call,
+    Return,
+    Jump,
+}
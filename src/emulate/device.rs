@@ -0,0 +1,51 @@
+use super::{splitmix64, EmulatorState};
+use std::io::{self, Read, Write};
+use std::mem::size_of;
+
+// Device files emulated without touching the host: `/dev/null` reads as
+// empty, `/dev/zero` reads as zeros and `/dev/urandom` reads as bytes of
+// a generator seeded from the emulator's deterministic random source at
+// the time of opening. Writes to any of them are discarded.
+#[derive(Clone, Debug)]
+pub(super) enum VirtualDevice {
+    Null,
+    Zero,
+    Urandom(u64),
+}
+
+impl VirtualDevice {
+    pub(super) fn open(state: &mut EmulatorState, path: &str) -> Option<VirtualDevice> {
+        match path {
+            "/dev/null" => Some(VirtualDevice::Null),
+            "/dev/zero" => Some(VirtualDevice::Zero),
+            "/dev/urandom" => Some(VirtualDevice::Urandom(state.next_random())),
+            _ => None,
+        }
+    }
+}
+
+impl Read for VirtualDevice {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            VirtualDevice::Null => return Ok(0),
+            VirtualDevice::Zero => buf.iter_mut().for_each(|b| *b = 0),
+            VirtualDevice::Urandom(seed) => {
+                for chunk in buf.chunks_mut(size_of::<u64>()) {
+                    let word = splitmix64(seed).to_le_bytes();
+                    chunk.copy_from_slice(&word[..chunk.len()]);
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+}
+
+impl Write for VirtualDevice {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,9465 @@
+mod bundle;
+mod cache;
+mod callgraph;
+mod device;
+mod pipe;
+
+pub use self::bundle::DeterminismConfig;
+pub use self::cache::{CacheModel, CacheStats, SetAssociativeCache};
+pub use self::callgraph::CallGraph;
+
+use self::callgraph::JumpKind;
+use self::device::VirtualDevice;
+use self::pipe::PipeEnd;
+use crate::disassemble::InstructionText;
+use crate::engine::system::{
+    prepare_unix_stack, unix_stack_size, Errno, SyscallId, NUMBER_OF_REGISTERS, PAGE_SIZE,
+};
+use crate::util::next_multiple_of;
+use byteorder::{ByteOrder, LittleEndian};
+use log::{debug, info, trace, warn};
+use riscu::{
+    instruction_length, types::*, DecodedProgram, Instruction, Program, ProgramSegment, Register,
+};
+use std::cmp::min;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Empty, ErrorKind, Read, Seek, SeekFrom, Sink, Write};
+use std::mem::size_of;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+//
+// Public Interface
+//
+
+pub type EmulatorValue = u64;
+
+#[derive(Clone, Debug)]
+pub struct EmulatorConfig {
+    // Maintain the `ResourceUsage` counters while running. Counting is
+    // cheap, but can be disabled to squeeze out the last bit of speed.
+    pub resource_accounting: bool,
+
+    // Check after every instruction that the program counter still lies
+    // within the code segment, catching wild jumps at their origin.
+    pub check_pc_in_text: bool,
+
+    // Dry execution without observable I/O, e.g. to estimate the cost of
+    // a run. The `write` system call reports all bytes as written (they
+    // are accounted for) without emitting them, the `read` system call
+    // deterministically reports end-of-file on all readable descriptors.
+    // All other system calls are unaffected by this mode.
+    pub dry_run: bool,
+
+    // Number of most recently executed instructions that can be undone by
+    // `step_back`, zero disables recording. Each recorded instruction
+    // costs one allocation holding the previous program counter and break
+    // together with the old value of every register and memory location
+    // it wrote, which roughly doubles the time spent per instruction.
+    pub reverse_depth: usize,
+
+    // Number of retired instructions that make up one clock tick (of
+    // which there are 100 per second) of synthetic user time, as reported
+    // by `times` and `getrusage`. Time is derived from the instruction
+    // count of resource accounting, hence stays zero with it disabled.
+    // System time is always reported as zero.
+    pub instructions_per_tick: u64,
+
+    // Limit on the number of bytes the guest can write in total, across
+    // all descriptors. Once reached, writes are cut short and further
+    // writes fail with `ENOSPC`. Unlimited in case of `None`.
+    pub max_output_bytes: Option<u64>,
+
+    // Size of the guard region at the bottom of memory which is treated as
+    // unmapped, loads and stores accessing it fail with a null pointer
+    // access. Disabled in case of `None`, the usual size is `PAGE_SIZE`.
+    pub null_guard_size: Option<u64>,
+
+    // Behavior when the guest invokes a system call that is not known.
+    pub unknown_syscall: UnknownSyscallPolicy,
+
+    // Budget of gas available to a run, where each instruction consumes
+    // gas according to the cost model. Emulation stops once the budget is
+    // exhausted, the instruction exhausting it is completed. Unlimited in
+    // case of `None`.
+    pub gas_budget: Option<u64>,
+    pub cost_model: CostModel,
+
+    // Extra gas consumed by system calls, keyed by system call number, on
+    // top of the `syscall` cost of the `ecall` itself. This models the
+    // latency of I/O (e.g. a `write` costing 1000). Only charged once the
+    // call completes (i.e. not while it blocks) and free unless listed.
+    pub syscall_costs: BTreeMap<EmulatorValue, u64>,
+
+    // Whether signed division overflowing (i.e. dividing the most negative
+    // value by minus one, which is undefined behavior in C) fails emulation
+    // with `SignedDivisionOverflow`. The instruction still completes with
+    // the result defined by the ISA before emulation stops.
+    pub trap_signed_division_overflow: bool,
+
+    // Whether loads and stores not naturally aligned to their width fail
+    // emulation with `UnalignedAccess`, as they would trap on some RISC-V
+    // implementations. Otherwise they are performed byte-wise, emulating
+    // hardware that handles them transparently.
+    pub trap_unaligned_access: bool,
+
+    // Seed of the pseudo-random generator backing the `getrandom` system
+    // call, the same seed always yields the same sequence of bytes.
+    pub random_seed: u64,
+
+    // Working directory reported to the guest by the `getcwd` system
+    // call. Note that this is mostly cosmetic, relative paths opened by
+    // the guest are still resolved against the working directory of the
+    // host (only `faccessat` resolves them against this one).
+    pub working_directory: String,
+
+    // System identity reported to the guest by the `uname` system call.
+    pub system_identity: SystemIdentity,
+
+    // Seed for randomizing the address-space layout, shaking out guest
+    // bugs that depend on it. The initial stack pointer is moved down from
+    // the top of memory by a pseudo-random offset (a multiple of 16 bytes
+    // below `MAX_STACK_OFFSET`, but never more than half the space between
+    // program break and top of memory) derived from the seed alone, hence
+    // the same seed always reproduces the same layout. The layout is fixed
+    // with the stack starting at the top of memory in case of `None`.
+    pub layout_seed: Option<u64>,
+
+    // Heuristic check of the calling convention, see `CallingConventionCheck`.
+    pub calling_convention: CallingConventionCheck,
+
+    // Path of a core file written whenever `run` fails with an error, for
+    // post-mortem inspection with `load_snapshot`. It holds the complete
+    // machine state (see `save_snapshot` for the format) together with
+    // the error and its instruction context. Note that all of memory is
+    // written uncompressed, the file is hence as large as guest memory.
+    pub core_dump: Option<PathBuf>,
+
+    // Whether every fetched instruction is re-encoded (see `encode`) and
+    // compared against the word it was decoded from, failing emulation
+    // with `DecodeMismatch` on any difference. This is a debugging aid
+    // for suspected decoder bugs and costs an encoding per instruction.
+    pub verify_decode: bool,
+
+    // Check every write to `sp` while running, failing emulation with
+    // `InvalidStackPointer` unless the new value lies between the program
+    // break and the top of memory. This pinpoints the instruction that
+    // corrupted the stack pointer instead of a later stack access.
+    pub check_stack_pointer: bool,
+
+    // Limit on the call depth (see `call_depth`), a call exceeding it
+    // fails emulation with `RecursionLimitExceeded`. This diagnoses
+    // runaway recursion more clearly than the stack eventually running
+    // into the heap. Unbounded in case of `None`.
+    pub max_call_depth: Option<u64>,
+
+    // Write a one-line summary of the run (see `RunReport::summary`) to
+    // the standard error stream once emulation stopped, as immediate
+    // feedback for interactive use without enabling tracing. Counters are
+    // only maintained with `resource_accounting` enabled.
+    pub summary_line: bool,
+
+    // Detector for guests stuck issuing system calls at a pathological
+    // rate (e.g. writing in a tight loop), see `SyscallRateLimit`. This
+    // catches runaway behavior that an instruction limit would only stop
+    // much later. Unlimited in case of `None`.
+    pub syscall_rate_limit: Option<SyscallRateLimit>,
+}
+
+// Rate of system calls considered excessive: more than `max_syscalls`
+// system calls within one window of `window` retired instructions. The
+// windows are consecutive and fixed (i.e. the first one covers the first
+// `window` instructions of a run), a burst straddling two windows might
+// go unnoticed. Exceeding the rate is logged as a warning once per
+// window, emulation additionally stops with `SyscallStorm` in case
+// `stop` is set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyscallRateLimit {
+    pub max_syscalls: u64,
+    pub window: u64,
+    pub stop: bool,
+}
+
+// Strings filling `struct utsname` as reported by `uname`, each field is
+// cut short to 64 bytes (plus the terminating zero byte) if necessary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SystemIdentity {
+    pub sysname: String,
+    pub nodename: String,
+    pub release: String,
+    pub version: String,
+    pub machine: String,
+    pub domainname: String,
+}
+
+impl Default for SystemIdentity {
+    fn default() -> Self {
+        Self {
+            sysname: String::from("Linux"),
+            nodename: String::from("unicorn"),
+            release: String::from("6.1.0"),
+            version: String::from("#1 SMP"),
+            machine: String::from("riscv64"),
+            domainname: String::from("(none)"),
+        }
+    }
+}
+
+// Gas cost per class of instructions, each costs one by default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CostModel {
+    pub alu: u64,
+    pub multiply: u64,
+    pub divide: u64,
+    pub load: u64,
+    pub store: u64,
+    pub branch: u64,
+    pub jump: u64,
+    pub syscall: u64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            alu: 1,
+            multiply: 1,
+            divide: 1,
+            load: 1,
+            store: 1,
+            branch: 1,
+            jump: 1,
+            syscall: 1,
+        }
+    }
+}
+
+impl CostModel {
+    pub fn cost(&self, instr: Instruction) -> u64 {
+        match instruction_class(instr) {
+            InstructionClass::Alu => self.alu,
+            InstructionClass::Multiply => self.multiply,
+            InstructionClass::Divide => self.divide,
+            InstructionClass::Load => self.load,
+            InstructionClass::Store => self.store,
+            InstructionClass::Branch => self.branch,
+            InstructionClass::Jump => self.jump,
+            InstructionClass::Syscall => self.syscall,
+        }
+    }
+}
+
+// Coarse classification of instructions, as used by the cost model and
+// trace filters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InstructionClass {
+    Alu,
+    Multiply,
+    Divide,
+    Load,
+    Store,
+    Branch,
+    Jump,
+    Syscall,
+}
+
+pub fn instruction_class(instr: Instruction) -> InstructionClass {
+    match instr {
+        Instruction::Mul(_) | Instruction::Mulw(_) => InstructionClass::Multiply,
+        Instruction::Div(_)
+        | Instruction::Divu(_)
+        | Instruction::Rem(_)
+        | Instruction::Remu(_)
+        | Instruction::Divw(_)
+        | Instruction::Remw(_) => InstructionClass::Divide,
+        Instruction::Lb(_)
+        | Instruction::Lh(_)
+        | Instruction::Lw(_)
+        | Instruction::Ld(_)
+        | Instruction::Lbu(_)
+        | Instruction::Lhu(_) => InstructionClass::Load,
+        Instruction::Sb(_) | Instruction::Sh(_) | Instruction::Sw(_) | Instruction::Sd(_) => {
+            InstructionClass::Store
+        }
+        Instruction::Beq(_)
+        | Instruction::Bne(_)
+        | Instruction::Blt(_)
+        | Instruction::Bge(_)
+        | Instruction::Bltu(_)
+        | Instruction::Bgeu(_) => InstructionClass::Branch,
+        Instruction::Jal(_) | Instruction::Jalr(_) => InstructionClass::Jump,
+        Instruction::Ecall(_) => InstructionClass::Syscall,
+        _ => InstructionClass::Alu,
+    }
+}
+
+// Pure semantics of an arithmetic or logic instruction, computing the
+// value written to `rd` from the value of `rs1` and either the value of
+// `rs2` or the sign-extended immediate. Decoupled from the machine state,
+// these can be compared against reference implementations in tests.
+#[derive(Clone, Copy, Debug)]
+pub enum AluOperation {
+    Register(fn(u64, u64) -> u64),
+    Immediate(fn(u64, i64) -> u64),
+}
+
+// Looks up the semantics of the arithmetic or logic instruction with the
+// given mnemonic (e.g. "addi" or "mulw"), which is `None` for all other
+// instructions.
+pub fn alu_operation(mnemonic: &str) -> Option<AluOperation> {
+    ALU_OPERATIONS
+        .iter()
+        .find(|(name, _)| *name == mnemonic)
+        .map(|(_, operation)| *operation)
+}
+
+// Ways of handling system calls not known to the emulator, each distinct
+// unknown system call is logged once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnknownSyscallPolicy {
+    // Return `-1` and continue execution.
+    Ignore,
+
+    // Return `-ENOSYS` and continue execution, which allows well-behaved
+    // guests to fall back to other code paths.
+    Enosys,
+
+    // Stop execution with an `UnknownSyscall` error.
+    Abort,
+}
+
+impl Default for EmulatorConfig {
+    fn default() -> Self {
+        Self {
+            resource_accounting: true,
+            check_pc_in_text: false,
+            dry_run: false,
+            reverse_depth: 0,
+            instructions_per_tick: 10_000,
+            max_output_bytes: None,
+            null_guard_size: None,
+            unknown_syscall: UnknownSyscallPolicy::Ignore,
+            gas_budget: None,
+            syscall_costs: BTreeMap::new(),
+            cost_model: CostModel::default(),
+            trap_signed_division_overflow: false,
+            trap_unaligned_access: false,
+            random_seed: 0,
+            working_directory: String::from("/"),
+            system_identity: SystemIdentity::default(),
+            layout_seed: None,
+            calling_convention: CallingConventionCheck::Off,
+            core_dump: None,
+            verify_decode: false,
+            check_stack_pointer: false,
+            max_call_depth: None,
+            summary_line: false,
+            syscall_rate_limit: None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum EmulatorError {
+    #[error(
+        "program counter {pc:#x} outside of code segment after {instruction:?} at {origin:#x}"
+    )]
+    PcOutOfText {
+        pc: EmulatorValue,
+        origin: EmulatorValue,
+        instruction: Instruction,
+    },
+
+    #[error("program has an empty code segment")]
+    EmptyProgram,
+
+    #[error("entry point {pc:#x} outside of code segment {code:#x?}")]
+    EntryOutOfText {
+        pc: EmulatorValue,
+        code: Range<EmulatorValue>,
+    },
+
+    #[error("I/O error")]
+    IoError(#[from] io::Error),
+
+    #[error("arguments require {required} bytes of stack, only {available} bytes available")]
+    StackSetupOverflow {
+        required: EmulatorValue,
+        available: EmulatorValue,
+    },
+
+    #[error("memory image of {size} bytes at {address:#x} does not fit into memory")]
+    ImageOutOfBounds {
+        address: EmulatorValue,
+        size: EmulatorValue,
+    },
+
+    #[error("code segment {code:#x?} overlaps data segment {data:#x?}")]
+    SegmentOverlap {
+        code: Range<EmulatorValue>,
+        data: Range<EmulatorValue>,
+    },
+
+    #[error("data segment {first:#x?} overlaps data segment {second:#x?}")]
+    DataSegmentOverlap {
+        first: Range<EmulatorValue>,
+        second: Range<EmulatorValue>,
+    },
+
+    #[error("segment of {size} bytes at {address:#x} does not fit into memory")]
+    SegmentOutOfBounds {
+        address: EmulatorValue,
+        size: EmulatorValue,
+    },
+
+    #[error("read from pipe {fd} blocks forever, the guest holds all write ends ({context})")]
+    PipeDeadlock {
+        fd: EmulatorValue,
+        context: InstructionContext,
+    },
+
+    #[error(
+        "program requires threads ({syscall:?} system call), which are not modeled ({context})"
+    )]
+    ThreadingUnsupported {
+        syscall: SyscallId,
+        context: InstructionContext,
+    },
+
+    #[error("unknown system call {number} ({context})")]
+    UnknownSyscall {
+        number: EmulatorValue,
+        context: InstructionContext,
+    },
+
+    #[error("null pointer access at address {address:#x} ({context})")]
+    NullPointerAccess {
+        address: EmulatorValue,
+        context: InstructionContext,
+    },
+
+    #[error("write to read-only memory at address {address:#x} ({context})")]
+    ReadOnlyWrite {
+        address: EmulatorValue,
+        context: InstructionContext,
+    },
+
+    #[error("unaligned {width}-byte access at address {address:#x} ({context})")]
+    UnalignedAccess {
+        address: EmulatorValue,
+        width: u64,
+        context: InstructionContext,
+    },
+
+    #[error("injected fault on {site:?} number {ordinal} at address {address:#x} ({context})")]
+    InjectedFault {
+        site: FaultSite,
+        ordinal: u64,
+        address: EmulatorValue,
+        context: InstructionContext,
+    },
+
+    #[error("stack pointer set to {value:#x} outside of stack ({context})")]
+    InvalidStackPointer {
+        value: EmulatorValue,
+        context: InstructionContext,
+    },
+
+    #[error("call at {pc:#x} exceeds call depth limit of {depth}")]
+    RecursionLimitExceeded { depth: u64, pc: EmulatorValue },
+
+    #[error("guest function calls nested deeper than {depth} ({context})")]
+    CallbackNestingExceeded {
+        depth: usize,
+        context: InstructionContext,
+    },
+
+    #[error("signed division overflow ({context})")]
+    SignedDivisionOverflow { context: InstructionContext },
+
+    #[error("calling convention violated: {violation}")]
+    CallingConventionViolation { violation: ConventionViolation },
+
+    #[error("malformed determinism bundle in line {line}")]
+    MalformedBundle { line: usize },
+
+    #[error("instruction word {word:#010x} at {pc:#x} re-encodes to {reencoded:#010x}")]
+    DecodeMismatch {
+        pc: EmulatorValue,
+        word: u32,
+        reencoded: u32,
+    },
+
+    #[error("unsupported instruction word {word:#010x} at {address:#x}")]
+    UnsupportedInstruction { address: EmulatorValue, word: u32 },
+
+    #[error("patch address {address:#x} outside of code segment")]
+    PatchOutOfCode { address: EmulatorValue },
+
+    #[error("malformed argument file")]
+    MalformedArgvFile,
+
+    #[error("data segment at {address:#x} is not word-aligned")]
+    MisalignedDataSegment { address: EmulatorValue },
+
+    #[error("string at {address:#x} starts outside of memory")]
+    StringOutOfBounds { address: EmulatorValue },
+
+    #[error("string at {address:#x} is not terminated within memory")]
+    UnterminatedString { address: EmulatorValue },
+
+    #[error("string at {address:#x} is longer than {limit} bytes")]
+    StringTooLong {
+        address: EmulatorValue,
+        limit: usize,
+    },
+
+    #[error("malformed snapshot")]
+    MalformedSnapshot,
+}
+
+// Reason a raw instruction word could not be decoded into an instruction
+// implemented by the emulator (see `disassemble_bytes`).
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("invalid instruction word {word:#010x}")]
+    Invalid { word: u32 },
+
+    #[error("unsupported instruction word {word:#010x}")]
+    Unsupported { word: u32 },
+}
+
+// Instruction during which an error was raised, together with the values
+// of all registers it reads as operands (for `ecall` the system call
+// number and the first three arguments) at the time it was raised. It is
+// only assembled once an error is raised, hence costs nothing otherwise.
+#[derive(Clone, Debug)]
+pub struct InstructionContext {
+    pub pc: EmulatorValue,
+    pub instruction: Instruction,
+    pub operands: Vec<(Register, EmulatorValue)>,
+}
+
+impl fmt::Display for InstructionContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pc={:#x} in `{}`",
+            self.pc,
+            InstructionText(self.instruction)
+        )?;
+        for (i, (reg, val)) in self.operands.iter().enumerate() {
+            let separator = if i == 0 { " with" } else { "," };
+            write!(f, "{} {:?}={:#x}", separator, reg, val)?;
+        }
+        Ok(())
+    }
+}
+
+// Summary of the work performed by the guest during one `run`. Memory
+// reads and writes only count data accesses of load and store
+// instructions, bytes read and written only count data successfully
+// transferred through system calls. The stack peak is the largest
+// distance of the stack pointer below the initial stack pointer (i.e.
+// including the arguments placed on the stack), which allows to size the
+// stack of a target with limited memory. Likewise, the heap peak is the
+// largest distance of the program break above the initial program break
+// (i.e. including mappings). System calls count every completed `ecall`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourceUsage {
+    pub instructions: u64,
+    pub memory_reads: u64,
+    pub memory_writes: u64,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    pub stack_peak: u64,
+    pub heap_peak: u64,
+    pub syscalls: u64,
+}
+
+// Instruction that completed execution, together with its address.
+#[derive(Clone, Copy, Debug)]
+pub struct RetiredInstruction {
+    pub pc: EmulatorValue,
+    pub instruction: Instruction,
+}
+
+// Word at `address` differing between two memories, as reported by
+// `diff_memory`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryDiff {
+    pub address: EmulatorValue,
+    pub old_word: EmulatorValue,
+    pub new_word: EmulatorValue,
+}
+
+// Events describing the single path taken by one concrete execution, in
+// the order in which they occurred. Together with the program and its
+// arguments these allow the path to be reconstructed (e.g. as a witness
+// for the model-checking side):
+//   - `Input` is emitted for every byte of guest input consumed through
+//     the `read` system call, `address` being where it was stored.
+//   - `Branch` is emitted for every conditional branch (i.e. `beq`,
+//     `bne`, `blt`, `bge`, `bltu`, `bgeu`), `taken` being its outcome.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WitnessEvent {
+    Input {
+        fd: EmulatorValue,
+        address: EmulatorValue,
+        byte: u8,
+    },
+    Branch {
+        pc: EmulatorValue,
+        taken: bool,
+    },
+}
+
+// Script of standard input for deterministically testing interactive
+// programs. Each entry becomes available once the given delay (counted
+// in instructions executed by the current run) has passed, delays are
+// relative to the previous entry becoming available. While no data is
+// available, reading blocks (i.e. the `read` system call is executed
+// over and over again). Once exhausted, reading reports end of input.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedInput {
+    entries: VecDeque<(u64, Vec<u8>)>,
+    last: u64,
+    now: u64,
+}
+
+impl ScriptedInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn then(mut self, delay: u64, bytes: &[u8]) -> Self {
+        self.last += delay;
+        self.entries.push_back((self.last, bytes.to_vec()));
+        self
+    }
+
+    // Entries of the script together with their delay relative to the
+    // previous entry, just as they were passed to `then`.
+    fn delayed_entries(&self) -> impl Iterator<Item = (u64, &[u8])> {
+        let starts = std::iter::once(0).chain(self.entries.iter().map(|(at, _)| *at));
+        self.entries
+            .iter()
+            .zip(starts)
+            .map(|((at, bytes), previous)| (at - previous, bytes.as_slice()))
+    }
+
+    // Moves the clock to the given instruction count, returns whether
+    // reading would not block (i.e. data is available or input ended).
+    fn advance(&mut self, now: u64) -> bool {
+        self.now = now;
+        match self.entries.front() {
+            Some((at, _)) => *at <= now,
+            None => true,
+        }
+    }
+}
+
+impl Read for ScriptedInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.entries.front_mut() {
+            Some((at, bytes)) if *at <= self.now => {
+                let len = min(buf.len(), bytes.len());
+                buf[..len].copy_from_slice(&bytes[..len]);
+                bytes.drain(..len);
+                if bytes.is_empty() {
+                    self.entries.pop_front();
+                }
+                Ok(len)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+// Crossing of the boundary of a region registered with `register_region`
+// at `pc`, which is the first instruction inside (respectively outside)
+// of the region, before it is executed. The number of instructions
+// retired by the current run so far allows timing a region.
+#[derive(Clone, Copy, Debug)]
+pub struct RegionEvent<'a> {
+    pub name: &'a str,
+    pub pc: EmulatorValue,
+    pub instructions_retired: u64,
+}
+
+type RegionCallback = Box<dyn FnMut(&RegionEvent) + Send>;
+
+// Callbacks invoked whenever execution enters or leaves a region.
+pub struct RegionCallbacks {
+    on_enter: RegionCallback,
+    on_leave: RegionCallback,
+}
+
+impl RegionCallbacks {
+    pub fn new<E, L>(on_enter: E, on_leave: L) -> Self
+    where
+        E: FnMut(&RegionEvent) + Send + 'static,
+        L: FnMut(&RegionEvent) + Send + 'static,
+    {
+        Self {
+            on_enter: Box::new(on_enter),
+            on_leave: Box::new(on_leave),
+        }
+    }
+}
+
+impl fmt::Debug for RegionCallbacks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RegionCallbacks")
+    }
+}
+
+// Structured trace of one retired instruction at `pc`, together with the
+// register it wrote (if any) and the value written.
+#[derive(Clone, Copy, Debug)]
+pub struct TraceEvent {
+    pub pc: EmulatorValue,
+    pub instruction: Instruction,
+    pub result: Option<(Register, EmulatorValue)>,
+}
+
+// Filter restricting tracing to a subset of instructions, both the trace
+// hook and the trace log only see matching instructions. An instruction
+// matches in case its address lies within `pc_range` and its class is
+// one of `classes`, `None` matching everything.
+#[derive(Clone, Debug, Default)]
+pub struct TraceFilter {
+    pub pc_range: Option<Range<EmulatorValue>>,
+    pub classes: Option<Vec<InstructionClass>>,
+}
+
+impl TraceFilter {
+    pub fn matches(&self, pc: EmulatorValue, instr: Instruction) -> bool {
+        let in_range = match &self.pc_range {
+            Some(range) => range.contains(&pc),
+            None => true,
+        };
+        let of_class = match &self.classes {
+            Some(classes) => classes.contains(&instruction_class(instr)),
+            None => true,
+        };
+        in_range && of_class
+    }
+}
+
+// Flat record of one trace event as exported by `write_json_trace`, one
+// JSON object per line. The schema is stable, fields are emitted in this
+// order: `pc` (number), `mnemonic` (string), `operands` (string, as in
+// the disassembly), `rd` (string naming the register written, or null)
+// and `result` (number written to `rd`, or null). For example:
+//   {"pc":65536,"mnemonic":"addi","operands":"A0,Zero,7","rd":"A0","result":7}
+// Records can also be serialized into other formats through `serde`.
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct TraceRecord {
+    pub pc: EmulatorValue,
+    pub mnemonic: String,
+    pub operands: String,
+    pub rd: Option<String>,
+    pub result: Option<EmulatorValue>,
+}
+
+#[cfg(feature = "serde")]
+impl TraceRecord {
+    pub fn new(event: &TraceEvent) -> Self {
+        let text = InstructionText(event.instruction).to_string();
+        let (mnemonic, operands) = match text.split_once(' ') {
+            Some((mnemonic, operands)) => (mnemonic.to_string(), operands.to_string()),
+            None => (text, String::new()),
+        };
+        Self {
+            pc: event.pc,
+            mnemonic,
+            operands,
+            rd: event.result.map(|(reg, _)| format!("{:?}", reg)),
+            result: event.result.map(|(_, value)| value),
+        }
+    }
+
+    // Renders the record as a single line of JSON, without line break.
+    pub fn to_json(&self) -> String {
+        fn string_or_null(value: &Option<String>) -> String {
+            value.as_deref().map_or(String::from("null"), json_string)
+        }
+        format!(
+            "{{\"pc\":{},\"mnemonic\":{},\"operands\":{},\"rd\":{},\"result\":{}}}",
+            self.pc,
+            json_string(&self.mnemonic),
+            json_string(&self.operands),
+            string_or_null(&self.rd),
+            self.result.map_or(String::from("null"), |v| v.to_string()),
+        )
+    }
+}
+
+// Data access performed by a load or store instruction at `pc`, the
+// `effective_address` being the computed `rs1 + imm` of the instruction.
+// The `region` is the classification of the effective address at the
+// time of the access (see `MemoryRegion`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub pc: EmulatorValue,
+    pub kind: AccessKind,
+    pub effective_address: EmulatorValue,
+    pub size: u64,
+    pub region: MemoryRegion,
+}
+
+// Operations that faults can be injected into (see `inject_fault`), each
+// counted separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FaultSite {
+    // Data access by a load instruction, failing emulation.
+    Load,
+    // Data access by a store instruction, failing emulation.
+    Store,
+    // Invocation of the `brk` system call, which fails the way the kernel
+    // fails it: The break is left unchanged and returned as is.
+    Brk,
+    // Invocation of the `mmap` system call, failing with `ENOMEM`.
+    Mmap,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessKind {
+    Load,
+    Store,
+}
+
+// Region of the address space an address falls into, derived from the
+// segment boundaries captured at load time and the current program break:
+//   - `Code` and `Global` are the code and data segment respectively.
+//   - `Heap` ranges from the end of the data segment up to the program
+//     break, including padding after the data segment and any mappings
+//     (which are carved out of the heap).
+//   - `Stack` ranges from the program break up to the top of memory. The
+//     gap between program break and stack pointer is attributed to the
+//     stack, as that is where the stack grows into on demand.
+//   - `Unmapped` is everything else, i.e. gaps below or between the code
+//     and data segments and addresses beyond the end of memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MemoryRegion {
+    Code,
+    Global,
+    Heap,
+    Stack,
+    Unmapped,
+}
+
+// Ways of handling violations of the calling convention, as detected by
+// a heuristic check on top of the call and return events identified for
+// the call graph (see `CallGraph`). The following is checked:
+//   - A return (i.e. a `jalr` to a remembered return address) has to jump
+//     through the link register it was called with (`ra` or `t0`).
+//   - A function performing a call has to have saved its own `ra` before,
+//     as the call clobbers it. Any instruction (except for `jalr` and
+//     branches) reading `ra` counts as saving it, e.g. storing it to the
+//     stack or moving it into another register.
+// False positives occur for functions performing a call that does not
+// return (e.g. to `exit`) without having saved `ra`, and for functions
+// entered through a call that never return themselves (e.g. `_start`
+// called by hand-written startup code). Each function activation raises
+// at most one violation of the second kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallingConventionCheck {
+    // No checking is performed.
+    Off,
+
+    // Violations are logged as warnings and recorded, execution continues.
+    Warn,
+
+    // Stop execution with a `CallingConventionViolation` error.
+    Abort,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConventionViolation {
+    // Return at `pc` jumping through a register other than the link register.
+    ReturnThroughNonLink {
+        pc: EmulatorValue,
+        register: Register,
+    },
+
+    // Call at `pc` clobbering `ra` before the calling function saved it.
+    LinkClobbered {
+        pc: EmulatorValue,
+    },
+}
+
+impl fmt::Display for ConventionViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReturnThroughNonLink { pc, register } => {
+                write!(
+                    f,
+                    "return at {:#x} through {:?} instead of ra",
+                    pc, register
+                )
+            }
+            Self::LinkClobbered { pc } => {
+                write!(f, "call at {:#x} clobbers ra without it being saved", pc)
+            }
+        }
+    }
+}
+
+// Tracks calls and returns like the call graph does, together with the
+// information whether each active function saved its `ra` already.
+#[derive(Clone, Debug)]
+struct ConventionChecker {
+    calls: CallGraph,
+    saved: Vec<bool>,
+    violations: Vec<ConventionViolation>,
+}
+
+impl ConventionChecker {
+    fn new(root: EmulatorValue) -> Self {
+        Self {
+            calls: CallGraph::new(root),
+            saved: Vec::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    fn observe_jump(
+        &mut self,
+        origin: EmulatorValue,
+        instr: Instruction,
+        return_address: EmulatorValue,
+        target: EmulatorValue,
+    ) -> Option<ConventionViolation> {
+        let violation = match self.calls.observe_jump(instr, return_address, target) {
+            JumpKind::Call => {
+                let depth = self.saved.len();
+                let clobbered = depth > 0 && !self.saved[depth - 1];
+                if let Some(saved) = self.saved.last_mut() {
+                    *saved = true; // report once per activation
+                }
+                self.saved.push(false);
+                clobbered.then_some(ConventionViolation::LinkClobbered { pc: origin })
+            }
+            JumpKind::Return => {
+                self.saved.truncate(self.calls.depth());
+                match instr {
+                    Instruction::Jalr(itype) if !is_link_register(itype.rs1()) => {
+                        Some(ConventionViolation::ReturnThroughNonLink {
+                            pc: origin,
+                            register: itype.rs1(),
+                        })
+                    }
+                    _ => None,
+                }
+            }
+            JumpKind::Jump => None,
+        };
+        if let Some(violation) = violation {
+            self.violations.push(violation);
+        }
+        violation
+    }
+
+    fn observe_instruction(&mut self, instr: Instruction) {
+        let reads_ra =
+            !is_control_transfer(instr) && source_registers(instr).contains(&Register::Ra);
+        if let (true, Some(saved)) = (reads_ra, self.saved.last_mut()) {
+            *saved = true;
+        }
+    }
+}
+
+// Edge coverage in the style of AFL, counting transitions between basic
+// blocks in a fixed-size bitmap. Every control transfer instruction
+// (i.e. branches whether taken or not, `jal` and `jalr`) ends a block,
+// the address it continues at identifies the next block. Blocks are
+// hashed into a location `cur` by multiplicative hashing of the address,
+// the edge from the previous block is counted in bucket `cur ^ prev`
+// (modulo the bitmap size) and `prev` becomes `cur >> 1` (keeping
+// `A->B` and `B->A` as well as tight loops `A->A` apart). Counts wrap
+// around on overflow, just as they do with AFL.
+#[derive(Clone, Debug)]
+struct CoverageBitmap {
+    map: Vec<u8>,
+    previous: u64,
+}
+
+impl CoverageBitmap {
+    fn new(size: usize) -> Self {
+        Self {
+            map: vec![0; size],
+            previous: 0,
+        }
+    }
+
+    fn observe_block(&mut self, address: EmulatorValue) {
+        let location = address.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 48;
+        let bucket = (location ^ self.previous) % self.map.len() as u64;
+        self.map[bucket as usize] = self.map[bucket as usize].wrapping_add(1);
+        self.previous = location >> 1;
+    }
+}
+
+// Outcome of a `run`, explaining why emulation stopped. The number of
+// retired instructions is counted independent of resource accounting.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RunReport {
+    pub stop_reason: StopReason,
+    pub exit_code: Option<EmulatorValue>,
+    pub instructions_retired: u64,
+    pub final_pc: EmulatorValue,
+    pub usage: ResourceUsage,
+}
+
+impl RunReport {
+    // Exit code to hand on to the host process, `None` in case the guest
+    // did not exit. Only the low eight bits of the guest's exit code are
+    // retained, just like a parent process on UNIX only observes those
+    // (e.g. `exit(257)` becomes 1 and `exit(-1)` becomes 255). A thin CLI
+    // front end can hence `std::process::exit` with it, or convert it via
+    // `std::process::ExitCode::from`.
+    pub fn host_exit_code(&self) -> Option<u8> {
+        self.exit_code.map(|exit_code| exit_code as u8)
+    }
+
+    // Human-readable one-line summary of the run, for example
+    // "42.1M insns, 3 syscalls, heap 8KiB, stack 2KiB, exit 0".
+    pub fn summary(&self) -> String {
+        let outcome = match self.stop_reason {
+            StopReason::Exit { exit_code } => format!("exit {}", exit_code as i64),
+            reason => format!("stopped {:?}", reason),
+        };
+        format!(
+            "{} insns, {} syscalls, heap {}, stack {}, {}",
+            compact_count(self.instructions_retired),
+            self.usage.syscalls,
+            compact_size(self.usage.heap_peak),
+            compact_size(self.usage.stack_peak),
+            outcome
+        )
+    }
+}
+
+// Transcript of a run performed by `run_with_io`, holding everything the
+// guest wrote to its standard output and error streams.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IoResult {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub exit_code: Option<EmulatorValue>,
+    pub instructions: u64,
+}
+
+// Input of a single run performed by `run_batch`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchInput {
+    pub argv: Vec<String>,
+    pub stdin: Vec<u8>,
+}
+
+// Outcome of a single run performed by `run_batch`. A crash (i.e. the run
+// failing with an error) is reported as `Err` in `result`. The coverage
+// delta counts the coverage bitmap entries hit by this run but by none of
+// the runs before it in the batch, zero unless coverage is recorded.
+#[derive(Debug)]
+pub struct BatchOutcome {
+    pub result: Result<RunReport, EmulatorError>,
+    pub new_coverage: usize,
+}
+
+impl BatchOutcome {
+    pub fn exit_code(&self) -> Option<EmulatorValue> {
+        self.result
+            .as_ref()
+            .ok()
+            .and_then(|report| report.exit_code)
+    }
+
+    pub fn crashed(&self) -> bool {
+        self.result.is_err()
+    }
+}
+
+// Cloneable handle to asynchronously stop a running emulator.
+#[derive(Clone, Debug)]
+pub struct StopHandle(Arc<AtomicBool>);
+
+impl StopHandle {
+    pub fn stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+// Reason why the emulator stopped executing instructions. This is the
+// central place to add new conditions stopping emulation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StopReason {
+    // The guest invoked the `exit` system call.
+    Exit {
+        exit_code: EmulatorValue,
+    },
+
+    // A watched register was written with a value matching the watch,
+    // `pc` points to the instruction performing the write.
+    RegisterWatch {
+        register: Register,
+        value: EmulatorValue,
+        pc: EmulatorValue,
+    },
+
+    // A store hit a watched memory word, leaving it with a value matching
+    // the watchpoint, `pc` points to the instruction performing the store.
+    MemoryWatch {
+        address: EmulatorValue,
+        value: EmulatorValue,
+        pc: EmulatorValue,
+    },
+
+    // A stop was requested through a `StopHandle`.
+    Interrupted,
+
+    // The number of instructions to run was reached.
+    InstructionLimit {
+        instructions_retired: u64,
+    },
+
+    // The gas budget of the run was exhausted.
+    OutOfGas {
+        gas_used: u64,
+    },
+
+    // System calls exceeded the configured `syscall_rate_limit`, `pc`
+    // points to the `ecall` exceeding it.
+    SyscallStorm {
+        syscalls: u64,
+        window: u64,
+        pc: EmulatorValue,
+    },
+}
+
+// The emulator owns all of its state (memory, registers, descriptors) and
+// requires all installed hooks to be `Send`, hence `EmulatorState` is
+// `Send` and independent emulations can be distributed across threads.
+// It is not `Sync`, a single emulation is not meant to be shared between
+// threads while it is running.
+#[derive(Debug)]
+pub struct EmulatorState {
+    registers: Vec<EmulatorValue>,
+    memory: Vec<u8>,
+    program_counter: EmulatorValue,
+    program_break: EmulatorValue,
+    heap_start: EmulatorValue,
+    read_only: Vec<Range<EmulatorValue>>,
+    fault_schedule: BTreeSet<(FaultSite, u64)>,
+    fault_counts: BTreeMap<FaultSite, u64>,
+    descriptors: Vec<Option<Descriptor>>,
+    running: bool,
+    stop_reason: Option<StopReason>,
+    retired: u64,
+    gas_used: u64,
+    fault: Option<EmulatorError>,
+    register_watches: Vec<RegisterWatch>,
+    memory_watches: Vec<MemoryWatch>,
+    symbols: Vec<(EmulatorValue, String)>,
+    witness: Option<WitnessHook>,
+    break_hook: Option<BreakHook>,
+    access_hook: Option<AccessHook>,
+    cache: Option<AttachedCache>,
+    trace_hook: Option<TraceHook>,
+    regions: Vec<Region>,
+    trace_filter: TraceFilter,
+    tracing: bool,
+    script: Option<ScriptedInput>,
+    syscall_blocked: bool,
+    random_state: u64,
+    slept_nanos: u64,
+    output_bytes: u64,
+    output_capped: bool,
+    unknown_syscalls: BTreeSet<EmulatorValue>,
+    call_graph: Option<CallGraph>,
+    call_frames: Vec<EmulatorValue>,
+    signal_handlers: BTreeMap<EmulatorValue, EmulatorValue>,
+    syscall_overrides: BTreeMap<EmulatorValue, VecDeque<EmulatorValue>>,
+    syscall_hooks: BTreeMap<EmulatorValue, SyscallHook>,
+    callback_depth: usize,
+    storm_window: u64,
+    storm_syscalls: u64,
+    convention: Option<ConventionChecker>,
+    coverage: Option<CoverageBitmap>,
+    interrupt: Arc<AtomicBool>,
+    stdio: HostStdio,
+    dry_input: Empty,
+    dry_output: Sink,
+    config: EmulatorConfig,
+    usage: ResourceUsage,
+    stack_base: EmulatorValue,
+    break_base: EmulatorValue,
+    code_range: Range<EmulatorValue>,
+    data_range: Range<EmulatorValue>,
+    recording: Option<StateDelta>,
+    history: VecDeque<StateDelta>,
+}
+
+impl EmulatorState {
+    pub fn new(memory_size: usize) -> Self {
+        Self::with_config(memory_size, EmulatorConfig::default())
+    }
+
+    pub fn with_config(memory_size: usize, config: EmulatorConfig) -> Self {
+        Self {
+            registers: vec![0; NUMBER_OF_REGISTERS],
+            memory: vec![0; memory_size],
+            program_counter: 0,
+            program_break: 0,
+            heap_start: 0,
+            read_only: Vec::new(),
+            fault_schedule: BTreeSet::new(),
+            fault_counts: BTreeMap::new(),
+            descriptors: vec![
+                Some(Descriptor::Stdin),
+                Some(Descriptor::Stdout),
+                Some(Descriptor::Stderr),
+            ],
+            running: false,
+            stop_reason: None,
+            retired: 0,
+            gas_used: 0,
+            fault: None,
+            register_watches: Vec::new(),
+            memory_watches: Vec::new(),
+            symbols: Vec::new(),
+            witness: None,
+            break_hook: None,
+            access_hook: None,
+            cache: None,
+            trace_hook: None,
+            regions: Vec::new(),
+            trace_filter: TraceFilter::default(),
+            tracing: false,
+            script: None,
+            syscall_blocked: false,
+            random_state: config.random_seed,
+            slept_nanos: 0,
+            output_bytes: 0,
+            output_capped: false,
+            unknown_syscalls: BTreeSet::new(),
+            call_graph: None,
+            call_frames: Vec::new(),
+            signal_handlers: BTreeMap::new(),
+            syscall_overrides: BTreeMap::new(),
+            syscall_hooks: BTreeMap::new(),
+            callback_depth: 0,
+            storm_window: 0,
+            storm_syscalls: 0,
+            convention: None,
+            coverage: None,
+            interrupt: Arc::new(AtomicBool::new(false)),
+            stdio: HostStdio {
+                stdin: Box::new(io::stdin()),
+                stdout: Box::new(io::stdout()),
+                stderr: Box::new(io::stderr()),
+            },
+            dry_input: io::empty(),
+            dry_output: io::sink(),
+            config,
+            usage: ResourceUsage::default(),
+            stack_base: 0,
+            break_base: 0,
+            code_range: 0..0,
+            data_range: 0..0,
+            recording: None,
+            history: VecDeque::new(),
+        }
+    }
+
+    // Fully bootstraps the emulator to allow execution of the given
+    // `program` from its beginning with given arguments `argv`.
+    pub fn bootstrap(&mut self, program: &Program, argv: &[String]) -> Result<(), EmulatorError> {
+        self.bootstrap_at(program, argv, initial_program_counter(program))
+    }
+
+    // Fully bootstraps the emulator like `bootstrap` does, but execution
+    // starts at the given `entry` point instead of the beginning of the
+    // code. For ELF files this is the entry point from the file header
+    // (see `elf_entry_point`), which need not be the start of `.text`.
+    pub fn bootstrap_at(
+        &mut self,
+        program: &Program,
+        argv: &[String],
+        entry: EmulatorValue,
+    ) -> Result<(), EmulatorError> {
+        let data = std::slice::from_ref(&program.data);
+        self.bootstrap_segments(&program.code, data, argv, entry)
+    }
+
+    // Fully bootstraps the emulator like `bootstrap_at` does, but with
+    // any number of data segments (e.g. separate `PT_LOAD` segments for
+    // `.rodata` and `.data`), each placed at its own address. Segments
+    // must not overlap each other. The heap starts at the page following
+    // the highest address of any segment, and the hull of all data
+    // segments is considered global data (see `data_segment`).
+    pub fn bootstrap_segments(
+        &mut self,
+        code: &ProgramSegment<u8>,
+        data: &[ProgramSegment<u8>],
+        argv: &[String],
+        entry: EmulatorValue,
+    ) -> Result<(), EmulatorError> {
+        self.bootstrap_with(code, data, argv, entry)
+    }
+
+    // Fully bootstraps the emulator like `bootstrap` does, but with raw
+    // bytes as arguments (e.g. from `argv_from_file`), which need not be
+    // valid UTF-8. Note that the guest sees each argument as C string,
+    // hence up to its first zero byte.
+    pub fn bootstrap_bytes(
+        &mut self,
+        program: &Program,
+        argv: &[Vec<u8>],
+    ) -> Result<(), EmulatorError> {
+        let data = std::slice::from_ref(&program.data);
+        let entry = initial_program_counter(program);
+        self.bootstrap_with(&program.code, data, argv, entry)
+    }
+
+    // Prepares the segment layout, loads all segments and pushes `argv`.
+    fn bootstrap_with<A: AsRef<[u8]>>(
+        &mut self,
+        code: &ProgramSegment<u8>,
+        data: &[ProgramSegment<u8>],
+        argv: &[A],
+        entry: EmulatorValue,
+    ) -> Result<(), EmulatorError> {
+        self.validate_segments(code, data)?;
+        self.call_frames.clear();
+        let highest_end = data
+            .iter()
+            .chain(std::iter::once(code))
+            .map(|segment| segment_range(segment).end)
+            .max()
+            .unwrap_or(0);
+        self.program_counter = entry;
+        self.program_break = next_multiple_of(highest_end, PAGE_SIZE as u64);
+        self.heap_start = self.program_break;
+        self.break_base = self.program_break;
+        let stack_top = self.memory.len() as u64 - self.stack_offset();
+        self.stack_base = stack_top;
+        self.set_reg(Register::Sp, stack_top);
+        self.load_code_segment(code);
+        self.load_data_segments(data);
+        self.load_stack_segment(argv)
+    }
+
+    // Partially prepares the emulator with the code segment from the
+    // given `program`. This can be used in conjunction with other
+    // mechanisms that restore the rest of the machine state.
+    pub fn prepare(&mut self, program: &Program) {
+        self.heap_start = initial_program_break(program);
+        self.load_code_segment(&program.code);
+    }
+
+    // Maps the raw contents of the file at `path` into memory starting
+    // at `address`, independent of any program segments. An image not
+    // ending on a word boundary is zero-padded up to the next one. Note
+    // that there is no notion of overlap, the image simply overwrites
+    // whatever was loaded before (e.g. code or data segments), just as
+    // anything loaded afterwards overwrites the image.
+    pub fn load_memory_image<P>(
+        &mut self,
+        address: EmulatorValue,
+        path: P,
+    ) -> Result<(), EmulatorError>
+    where
+        P: AsRef<Path>,
+    {
+        let mut image = std::fs::read(path)?;
+        let out_of_bounds = |size| EmulatorError::ImageOutOfBounds { address, size };
+        let image_end = match address.checked_add(image.len() as u64) {
+            Some(end) if end <= self.memory.len() as u64 => end,
+            _ => return Err(out_of_bounds(image.len() as u64)),
+        };
+        let padded_end = next_multiple_of(image_end, riscu::WORD_SIZE as u64);
+        image.resize(image.len() + (padded_end - image_end) as usize, 0);
+        if padded_end > self.memory.len() as u64 {
+            return Err(out_of_bounds(image.len() as u64));
+        }
+        debug!(
+            "loading memory image of {} bytes at {:#x}",
+            image.len(),
+            address
+        );
+        self.copy_mem(address, &image);
+        Ok(())
+    }
+
+    // Start emulation. Returns a report explaining why emulation stopped,
+    // errors stopping emulation are returned as such instead.
+    pub fn run(&mut self) -> Result<RunReport, EmulatorError> {
+        self.run_limited(None)
+    }
+
+    // Bootstraps the given program and runs it to completion with the
+    // given bytes as its standard input, capturing its standard output
+    // and error streams in memory. Replaces whatever streams were set
+    // with `set_stdio` before. Meant for tests checking the output of a
+    // program, e.g. `assert_eq!(state.run_with_io(..)?.stdout, b"..")`.
+    pub fn run_with_io(
+        &mut self,
+        program: &Program,
+        argv: &[String],
+        stdin: &[u8],
+    ) -> Result<IoResult, EmulatorError> {
+        self.run_with_io_at(program, argv, initial_program_counter(program), stdin)
+    }
+
+    // Runs the given program like `run_with_io` does, but starting at the
+    // given `entry` point (see `bootstrap_at`).
+    pub fn run_with_io_at(
+        &mut self,
+        program: &Program,
+        argv: &[String],
+        entry: EmulatorValue,
+        stdin: &[u8],
+    ) -> Result<IoResult, EmulatorError> {
+        let stdout = CapturedOutput::default();
+        let stderr = CapturedOutput::default();
+        self.bootstrap_at(program, argv, entry)?;
+        self.set_stdio(
+            io::Cursor::new(stdin.to_vec()),
+            stdout.clone(),
+            stderr.clone(),
+        );
+        let report = self.run()?;
+        Ok(IoResult {
+            stdout: stdout.take(),
+            stderr: stderr.take(),
+            exit_code: report.exit_code,
+            instructions: report.instructions_retired,
+        })
+    }
+
+    // Runs the given `program` once per input, each run starting from a
+    // freshly bootstrapped machine and stopping after at most `cap`
+    // retired instructions, e.g. to replay a fuzzing corpus. The machine
+    // memory is reused across runs and only cleared in between. Runs keep
+    // the configuration, coverage recording (see `record_coverage_bitmap`)
+    // and everything set up by the embedder, such as hooks and the stop
+    // handle, see `reset` for the details. Syscall overrides are replayed
+    // from the start for every run. Standard input is the one of the
+    // input, replacing any scripted input, and output is discarded. A
+    // failing run is reported in its outcome, the batch carries on. The
+    // machine is left in the state of the last run.
+    pub fn run_batch(
+        &mut self,
+        program: &Program,
+        inputs: &[BatchInput],
+        cap: u64,
+    ) -> Vec<BatchOutcome> {
+        let coverage_size = self.coverage.as_ref().map(|c| c.map.len());
+        let mut seen = vec![false; coverage_size.unwrap_or(0)];
+        let mut outcomes = Vec::with_capacity(inputs.len());
+        let overrides = self.syscall_overrides.clone();
+        for input in inputs {
+            self.reset();
+            self.syscall_overrides = overrides.clone();
+            if let Some(size) = coverage_size {
+                self.record_coverage_bitmap(size);
+            }
+            self.set_stdio(io::Cursor::new(input.stdin.clone()), io::sink(), io::sink());
+            let result = self
+                .bootstrap(program, &input.argv)
+                .and_then(|_| self.run_until_instruction(cap));
+            let mut new_coverage = 0;
+            for (hit, seen) in self.coverage_bitmap().iter().zip(seen.iter_mut()) {
+                if *hit != 0 && !*seen {
+                    *seen = true;
+                    new_coverage += 1;
+                }
+            }
+            outcomes.push(BatchOutcome {
+                result,
+                new_coverage,
+            });
+        }
+        outcomes
+    }
+
+    // Start emulation, stopping right after the `n`th retired instruction
+    // (unless emulation stops earlier) with all state left inspectable.
+    // Note that `n` counts instructions retired by this run, which are
+    // the ones that completed (a failing instruction is not counted). A
+    // bisection between two builds runs both to the same `n` and compares
+    // their fingerprints, narrowing `n` down to the first divergence.
+    pub fn run_until_instruction(&mut self, n: u64) -> Result<RunReport, EmulatorError> {
+        self.run_limited(Some(n))
+    }
+
+    fn run_limited(&mut self, limit: Option<u64>) -> Result<RunReport, EmulatorError> {
+        let result = self.run_to_stop(limit);
+        if let (Err(error), Some(path)) = (&result, &self.config.core_dump) {
+            let note = error.to_string();
+            match self.write_snapshot(path, &note) {
+                Ok(()) => info!("core dumped to {}", path.display()),
+                Err(e) => warn!("failed to dump core to {}: {}", path.display(), e),
+            }
+        }
+        result
+    }
+
+    fn run_to_stop(&mut self, limit: Option<u64>) -> Result<RunReport, EmulatorError> {
+        self.check_entry()?;
+        self.start();
+        while self.running {
+            if limit == Some(self.retired) {
+                let instructions_retired = self.retired;
+                self.stop(StopReason::InstructionLimit {
+                    instructions_retired,
+                });
+                break;
+            }
+            if self.retired & (INTERRUPT_CHECK_INTERVAL - 1) == 0
+                && self.interrupt.swap(false, Ordering::Relaxed)
+            {
+                self.stop(StopReason::Interrupted);
+                break;
+            }
+            self.step()?;
+        }
+        let stop_reason = self.stop_reason.expect("stopped for a reason");
+        let exit_code = match stop_reason {
+            StopReason::Exit { exit_code } => Some(exit_code),
+            _ => None,
+        };
+        let report = RunReport {
+            stop_reason,
+            exit_code,
+            instructions_retired: self.retired,
+            final_pc: self.program_counter,
+            usage: self.usage,
+        };
+        if self.config.summary_line {
+            let stderr = &mut self.stdio.stderr;
+            writeln!(stderr, "{}", report.summary())?;
+            stderr.flush()?;
+        }
+        Ok(report)
+    }
+
+    // Start emulation, executing one instruction per call to `next` on
+    // the returned iterator. The iterator ends once the machine halts,
+    // an error is yielded as the last item.
+    pub fn execution(
+        &mut self,
+    ) -> impl Iterator<Item = Result<RetiredInstruction, EmulatorError>> + '_ {
+        self.start();
+        std::iter::from_fn(move || {
+            if self.running {
+                Some(self.retire())
+            } else {
+                None
+            }
+        })
+    }
+
+    // Writes the complete machine state to the file at `path`. Snapshots
+    // use a portable little-endian format: The magic `UNISNAP1`, followed
+    // by the program counter, the program break, the start of the heap,
+    // all registers, the size of memory and its contents, and finally the
+    // size of a UTF-8 note and the note itself (for core files the error
+    // that stopped emulation, otherwise empty). Everything except memory
+    // contents and the note is encoded as 64-bit integers.
+    pub fn save_snapshot<P>(&self, path: P) -> Result<(), EmulatorError>
+    where
+        P: AsRef<Path>,
+    {
+        self.write_snapshot(path.as_ref(), "")
+    }
+
+    // Restores the machine state from a snapshot written by `save_snapshot`
+    // (or a core file), resizing memory to the size in the snapshot, and
+    // returns the note stored with it. Everything not part of a snapshot
+    // (e.g. configuration, descriptors, or hooks) is left as is.
+    pub fn load_snapshot<P>(&mut self, path: P) -> Result<String, EmulatorError>
+    where
+        P: AsRef<Path>,
+    {
+        let contents = std::fs::read(path)?;
+        let mut rest = contents
+            .strip_prefix(SNAPSHOT_MAGIC)
+            .ok_or(EmulatorError::MalformedSnapshot)?;
+        let mut take = |size: usize| -> Result<&[u8], EmulatorError> {
+            if rest.len() < size {
+                return Err(EmulatorError::MalformedSnapshot);
+            }
+            let (head, tail) = rest.split_at(size);
+            rest = tail;
+            Ok(head)
+        };
+        let program_counter = LittleEndian::read_u64(take(8)?);
+        let program_break = LittleEndian::read_u64(take(8)?);
+        let heap_start = LittleEndian::read_u64(take(8)?);
+        let mut registers = vec![0; NUMBER_OF_REGISTERS];
+        for register in registers.iter_mut() {
+            *register = LittleEndian::read_u64(take(8)?);
+        }
+        let memory_size = LittleEndian::read_u64(take(8)?) as usize;
+        let memory = take(memory_size)?.to_vec();
+        let note_size = LittleEndian::read_u64(take(8)?) as usize;
+        let note = String::from_utf8(take(note_size)?.to_vec())
+            .map_err(|_| EmulatorError::MalformedSnapshot)?;
+        self.program_counter = program_counter;
+        self.program_break = program_break;
+        self.heap_start = heap_start;
+        self.registers = registers;
+        self.memory = memory;
+        Ok(note)
+    }
+
+    // Execute a single instruction. Emulation stops in case of an error.
+    // Returns the reason in case this instruction stopped emulation.
+    pub fn step(&mut self) -> Result<Option<StopReason>, EmulatorError> {
+        self.retire()?;
+        Ok(self.stop_reason)
+    }
+
+    // Execute a single instruction like `step` does, unless it is a call
+    // (i.e. `jal` or `jalr` linking through `ra` or `t0`), which is run through
+    // until control returns to the instruction after the call. A return
+    // is only recognized with the stack pointer back at (or above) its
+    // value at the call, so that recursive calls returning to the same
+    // address from deeper frames are run through as well. Calls that do
+    // not return within `max_instructions` stop with `InstructionLimit`,
+    // reporting all instructions retired so far like `run` does.
+    pub fn step_over(
+        &mut self,
+        max_instructions: u64,
+    ) -> Result<Option<StopReason>, EmulatorError> {
+        if !self.peek().is_some_and(is_call) {
+            return self.step();
+        }
+        let return_address = self.program_counter + riscu::INSTRUCTION_SIZE as u64;
+        let frame = self.get_reg(Register::Sp);
+        for _ in 0..max_instructions {
+            if let Some(reason) = self.step()? {
+                return Ok(Some(reason));
+            }
+            if self.program_counter == return_address && self.get_reg(Register::Sp) >= frame {
+                return Ok(None);
+            }
+        }
+        self.stop(StopReason::InstructionLimit {
+            instructions_retired: self.retired,
+        });
+        Ok(self.stop_reason)
+    }
+
+    // Handle that can be used to request a stop of `run` from another
+    // thread. Requests are checked every few thousand instructions, a
+    // request made while not running stops the next run immediately.
+    pub fn stop_handle(&self) -> StopHandle {
+        StopHandle(self.interrupt.clone())
+    }
+
+    // Undoes the last recorded instruction (see `reverse_depth` in the
+    // configuration) by restoring all the values it overwrote. Returns
+    // `false` in case there is nothing left to undo. Note that effects
+    // outside of registers and memory (e.g. I/O performed by system calls,
+    // changes to the descriptor table or memory mappings) are not undone.
+    pub fn step_back(&mut self) -> bool {
+        match self.history.pop_back() {
+            Some(delta) => {
+                for (adr, bytes) in delta.memory.iter().rev() {
+                    self.copy_mem(*adr, bytes);
+                }
+                for (reg, val) in delta.registers.iter().rev() {
+                    self.registers[*reg] = *val;
+                }
+                self.program_counter = delta.program_counter;
+                self.program_break = delta.program_break;
+                trace!("stepped back to pc={:#x}", self.program_counter);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Reason why the last run (or step) stopped, `None` while still
+    // running or in case it stopped due to an error.
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.stop_reason
+    }
+
+    // Stops emulation as soon as the given register `reg` is written with
+    // a value satisfying the given `predicate` (use `|_| true` to watch
+    // for any write). The instruction performing the write is completed.
+    // Watches stay active until removed, predicates are evaluated on each
+    // write to the register and should hence be cheap.
+    pub fn add_register_watch<F>(&mut self, reg: Register, predicate: F)
+    where
+        F: Fn(EmulatorValue) -> bool + Send + 'static,
+    {
+        assert!(reg != Register::Zero, "cannot watch `zero` register");
+        self.register_watches.push(RegisterWatch {
+            register: reg,
+            predicate: Box::new(predicate),
+        });
+    }
+
+    pub fn clear_register_watches(&mut self) {
+        self.register_watches.clear();
+    }
+
+    // Stops emulation as soon as a store instruction writes (any part of)
+    // the memory word at `adr`, leaving it with a value satisfying the
+    // given `predicate` (use `|_| true` to watch for any write). Just like
+    // register watches, the store is completed and predicates should be
+    // cheap. Writes by system calls (e.g. `read`) are not watched. Costs
+    // nothing on the store path unless a watchpoint is set.
+    pub fn add_watchpoint<F>(&mut self, adr: EmulatorValue, predicate: F)
+    where
+        F: Fn(EmulatorValue) -> bool + Send + 'static,
+    {
+        assert!(adr & WORD_SIZE_MASK == 0, "address aligned");
+        self.memory_watches.push(MemoryWatch {
+            address: adr,
+            predicate: Box::new(predicate),
+        });
+    }
+
+    pub fn clear_watchpoints(&mut self) {
+        self.memory_watches.clear();
+    }
+
+    // Size of the guest memory in bytes.
+    pub fn memory_size_bytes(&self) -> usize {
+        self.memory.len()
+    }
+
+    // Classifies the given address into a region of the address space,
+    // see `MemoryRegion` for the boundaries of each region.
+    pub fn memory_region(&self, adr: EmulatorValue) -> MemoryRegion {
+        if self.code_range.contains(&adr) {
+            MemoryRegion::Code
+        } else if self.data_range.contains(&adr) {
+            MemoryRegion::Global
+        } else if (self.data_range.end..self.program_break).contains(&adr) {
+            MemoryRegion::Heap
+        } else if (self.program_break..self.memory.len() as u64).contains(&adr) {
+            MemoryRegion::Stack
+        } else {
+            MemoryRegion::Unmapped
+        }
+    }
+
+    // Consumes the emulator and hands out its machine state without any
+    // copying, in this order: the registers (indexed by register number,
+    // `zero` included), the memory as raw little-endian bytes (memory is
+    // byte-addressed and not necessarily a multiple of the word size),
+    // and the program counter.
+    pub fn into_parts(self) -> (Vec<EmulatorValue>, Vec<u8>, EmulatorValue) {
+        (self.registers, self.memory, self.program_counter)
+    }
+
+    // Decodes the instruction about to be executed at the current program
+    // counter without executing it, e.g. for debuggers to display (see
+    // `disassemble::InstructionText` for a human-readable rendering).
+    // Returns `None` in case the program counter is outside of the code
+    // segment or points to an unimplemented instruction.
+    pub fn peek(&self) -> Option<Instruction> {
+        self.instruction_at(self.program_counter)
+    }
+
+    // Decodes the instruction at the given address, just like `peek` does
+    // for the current program counter.
+    pub fn instruction_at(&self, address: EmulatorValue) -> Option<Instruction> {
+        let end = address.checked_add(3)?;
+        if !self.code_range.contains(&address) || !self.code_range.contains(&end) {
+            return None;
+        }
+        decode_one(self.get_mem_unaligned::<u32>(address))
+    }
+
+    // Protects the given range of memory (e.g. `.rodata` as found by
+    // `elf_read_only_data`) against stores by the guest, which then fail
+    // emulation with `ReadOnlyWrite`. Writes by system calls and through
+    // the public interface (e.g. `set_mem`) are not affected.
+    pub fn protect_read_only(&mut self, range: Range<EmulatorValue>) {
+        self.read_only.push(range);
+    }
+
+    // Schedules the `ordinal`th operation at the given site to fail (see
+    // `FaultSite` for the failure produced), for systematically exercising
+    // rare error paths of the guest. Ordinals start at one and are counted
+    // separately per site, across all runs from the moment the first fault
+    // is scheduled (no counting happens before, keeping the cost at zero).
+    // Loads and stores are only counted for instructions, accesses by
+    // system calls or through the public interface are not.
+    pub fn inject_fault(&mut self, site: FaultSite, ordinal: u64) {
+        self.fault_schedule.insert((site, ordinal));
+    }
+
+    // Queues an overridden result for the next invocation of the given
+    // system call, further overrides apply to subsequent invocations in
+    // the order they were queued. Errors are given as negated error
+    // number (e.g. `-(Errno::ENOMEM as i64)`). Overrides take precedence
+    // over everything else: The `ecall` just sets `a0` to the result,
+    // the real handler is skipped entirely and has no effect (e.g. an
+    // overridden `read` consumes no input and writes no buffer, and an
+    // overridden `exit` does not stop), no extra gas (see `syscall_costs`)
+    // is charged. Faults injected into `brk` or `mmap` are not counted.
+    pub fn override_syscall(&mut self, id: SyscallId, result: i64) {
+        self.syscall_overrides
+            .entry(id as u64)
+            .or_default()
+            .push_back(result as EmulatorValue);
+    }
+
+    // Installs a handler for the system call with the given number, which
+    // takes precedence over the built-in one (but not over queued results
+    // of `override_syscall`). The handler finds the arguments in registers
+    // and has to leave the result in `a0`, the program counter is advanced
+    // past the `ecall` afterwards. Replaces any previous handler.
+    pub fn set_syscall_handler<F>(&mut self, number: EmulatorValue, handler: F)
+    where
+        F: FnMut(&mut EmulatorState) + Send + 'static,
+    {
+        self.syscall_hooks
+            .insert(number, SyscallHook(Box::new(handler)));
+    }
+
+    // Calls the guest function at `address` from within a system call
+    // handler (see `set_syscall_handler`), e.g. a callback passed to the
+    // system call. Up to eight arguments are passed in `a0` to `a7` as
+    // per the calling convention, the function runs on the stack of the
+    // caller until it returns to a sentinel return address. Afterwards
+    // all registers and the program counter are restored, the result left
+    // in `a0` is returned. Reentrancy is subject to these limits:
+    //   - Calls nest at most `MAX_CALLBACK_NESTING` deep, a deeper call
+    //     fails emulation with `CallbackNestingExceeded`.
+    //   - A handler is not re-entered, invocations of its system call
+    //     from within its own callback are handled as if not installed.
+    //   - Instruction limits and stop requests of the surrounding run only
+    //     take effect once the outermost call returned.
+    //   - In case the function fails (or stops, e.g. exits) the run stops
+    //     after the `ecall` of the handler with registers left as they
+    //     are, zero is returned instead of a result.
+    pub fn call_function(
+        &mut self,
+        address: EmulatorValue,
+        args: &[EmulatorValue],
+    ) -> EmulatorValue {
+        assert!(
+            args.len() <= CALL_ARGUMENTS.len(),
+            "at most eight arguments"
+        );
+        if self.callback_depth >= MAX_CALLBACK_NESTING {
+            let context = self.instruction_context();
+            self.fail(EmulatorError::CallbackNestingExceeded {
+                depth: MAX_CALLBACK_NESTING,
+                context,
+            });
+            return 0;
+        }
+        let registers = self.registers.clone();
+        let program_counter = self.program_counter;
+        let recording = self.recording.take();
+        let tracing = self.tracing;
+        for (reg, arg) in CALL_ARGUMENTS.iter().zip(args) {
+            self.set_reg(*reg, *arg);
+        }
+        self.set_reg(Register::Ra, CALLBACK_RETURN);
+        self.program_counter = address;
+        self.callback_depth += 1;
+        let mut result = Ok(());
+        while self.running && self.program_counter != CALLBACK_RETURN {
+            if let Err(error) = self.retire() {
+                result = Err(error);
+                break;
+            }
+        }
+        self.callback_depth -= 1;
+        self.recording = recording;
+        self.tracing = tracing;
+        match result {
+            Err(error) => {
+                self.fail(error);
+                0
+            }
+            Ok(()) if !self.running => 0,
+            Ok(()) => {
+                let value = self.get_reg(Register::A0);
+                self.registers = registers;
+                self.program_counter = program_counter;
+                value
+            }
+        }
+    }
+
+    // Address range of the code segment, as captured when it was loaded.
+    pub fn code_segment(&self) -> Range<EmulatorValue> {
+        self.code_range.clone()
+    }
+
+    // Address range of the data segment, as captured when it was loaded.
+    pub fn data_segment(&self) -> Range<EmulatorValue> {
+        self.data_range.clone()
+    }
+
+    // Installs a hook receiving the events of the executed path, see
+    // `WitnessEvent` for the emitted schema. Replaces any previous hook.
+    pub fn set_path_witness<F>(&mut self, hook: F)
+    where
+        F: FnMut(WitnessEvent) + Send + 'static,
+    {
+        self.witness = Some(WitnessHook(Box::new(hook)));
+    }
+
+    pub fn clear_path_witness(&mut self) {
+        self.witness = None;
+    }
+
+    // Compares memory of this (old) and the `other` (new) emulator word by
+    // word and returns all differing words in ascending order. Memories of
+    // different size are compared as if the smaller one was zero-extended.
+    pub fn diff_memory(&self, other: &EmulatorState) -> Vec<MemoryDiff> {
+        let size = self.memory.len().max(other.memory.len());
+        (0..size)
+            .step_by(riscu::WORD_SIZE)
+            .filter_map(|adr| {
+                let old_word = word_or_zero(&self.memory, adr);
+                let new_word = word_or_zero(&other.memory, adr);
+                (old_word != new_word).then_some(MemoryDiff {
+                    address: adr as EmulatorValue,
+                    old_word,
+                    new_word,
+                })
+            })
+            .collect()
+    }
+
+    // Installs a hook invoked with the old and the new program break
+    // whenever the guest moves it through the `brk` system call. Requests
+    // that are rejected or leave the break unchanged are not reported.
+    pub fn on_break_change<F>(&mut self, hook: F)
+    where
+        F: FnMut(EmulatorValue, EmulatorValue) + Send + 'static,
+    {
+        self.break_hook = Some(BreakHook(Box::new(hook)));
+    }
+
+    // Replaces standard input of the guest with the given script, see
+    // `ScriptedInput` for how scripted data becomes available.
+    pub fn set_scripted_input(&mut self, script: ScriptedInput) {
+        self.script = Some(script);
+    }
+
+    // Applies all sources of nondeterminism captured in the given bundle,
+    // see `DeterminismConfig` for what is covered. Must be applied before
+    // the run that is meant to be reproduced.
+    pub fn set_determinism(&mut self, determinism: &DeterminismConfig) {
+        self.config.random_seed = determinism.random_seed;
+        self.config.instructions_per_tick = determinism.instructions_per_tick;
+        self.random_state = determinism.random_seed;
+        self.set_scripted_input(determinism.stdin.clone());
+    }
+
+    // Installs a hook invoked with a structured trace event for every
+    // retired instruction matching the trace filter (see `TraceFilter`).
+    // Replaces any previous hook.
+    pub fn on_trace<F>(&mut self, hook: F)
+    where
+        F: FnMut(&TraceEvent) + Send + 'static,
+    {
+        self.trace_hook = Some(TraceHook(Box::new(hook)));
+    }
+
+    // Streams a JSON-lines trace of all retired instructions matching the
+    // trace filter into `out`, see `TraceRecord` for the schema. Each line
+    // is written as soon as the instruction retires, hence `out` should be
+    // buffered (e.g. a `BufWriter`), it is flushed when the emulator is
+    // dropped. Builds on `on_trace` and thus replaces any previous hook.
+    // Write errors are logged once and end the trace.
+    #[cfg(feature = "serde")]
+    pub fn write_json_trace<W>(&mut self, mut out: W)
+    where
+        W: Write + Send + 'static,
+    {
+        let mut failed = false;
+        self.on_trace(move |event| {
+            if failed {
+                return;
+            }
+            if let Err(e) = writeln!(out, "{}", TraceRecord::new(event).to_json()) {
+                warn!("failed to write JSON trace: {}", e);
+                failed = true;
+            }
+        });
+    }
+
+    // Registers the named address range `start..end` (e.g. a function),
+    // invoking the given callbacks whenever the program counter enters or
+    // leaves it. Crossings are inferred from the program counter alone,
+    // hence a call out of the range counts as leaving it and the return
+    // as entering it again. Regions are tracked independently, nested or
+    // overlapping regions are entered and left in their own right: On a
+    // single step all regions being left are reported first (most
+    // recently registered first), followed by all regions being entered
+    // (in order of registration). A region the guest is still in when
+    // emulation stops is not reported as left.
+    pub fn register_region(
+        &mut self,
+        name: &str,
+        start: EmulatorValue,
+        end: EmulatorValue,
+        callbacks: RegionCallbacks,
+    ) {
+        self.regions.push(Region {
+            name: name.to_owned(),
+            range: start..end,
+            callbacks,
+            inside: false,
+        });
+    }
+
+    // Restricts the trace hook and the trace log to matching instructions,
+    // replacing any previous filter.
+    pub fn set_trace_filter(&mut self, filter: TraceFilter) {
+        self.trace_filter = filter;
+    }
+
+    // Installs a hook invoked for every data access performed by load and
+    // store instructions (but not by system calls), before the access is
+    // carried out. Replaces any previous hook.
+    pub fn on_memory_access<F>(&mut self, hook: F)
+    where
+        F: FnMut(MemoryAccess) + Send + 'static,
+    {
+        self.access_hook = Some(AccessHook(Box::new(hook)));
+    }
+
+    // Attaches a cache model observing all data accesses performed by load
+    // and store instructions (but not by system calls), e.g. the default
+    // `SetAssociativeCache`. Replaces any previously attached cache.
+    pub fn attach_cache<C>(&mut self, cache: C)
+    where
+        C: CacheModel + 'static,
+    {
+        self.cache = Some(AttachedCache(Box::new(cache)));
+    }
+
+    // Hit and miss statistics of the attached cache, if any.
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache
+            .as_ref()
+            .map(|AttachedCache(cache)| cache.stats())
+    }
+
+    // Starts recording a dynamic call graph, attributing everything up
+    // to the first call to the function at the current program counter.
+    // Recording an already recorded graph starts over. See `CallGraph`
+    // for how calls and returns are identified.
+    pub fn record_call_graph(&mut self) {
+        self.call_graph = Some(CallGraph::new(self.program_counter));
+    }
+
+    // The call graph recorded so far, if recording was started.
+    pub fn call_graph(&self) -> Option<&CallGraph> {
+        self.call_graph.as_ref()
+    }
+
+    // Number of calls that have not returned yet. Calls and returns are
+    // identified with the same heuristic as for the call graph: A call is
+    // a jump linking through `ra` or `t0`, a return is a `jalr` to the
+    // return address of a pending call, which also returns from all calls
+    // made after it (e.g. after a `longjmp`). Calls that never return in
+    // this sense (e.g. tail calls through `jal` without linking) are not
+    // an issue, but returns that jump elsewhere leave the depth too high.
+    pub fn call_depth(&self) -> u64 {
+        self.call_frames.len() as u64
+    }
+
+    // Handler addresses the guest installed with `rt_sigaction`, keyed by
+    // signal number, for diagnostics only (signals are never delivered).
+    pub fn signal_handlers(&self) -> &BTreeMap<EmulatorValue, EmulatorValue> {
+        &self.signal_handlers
+    }
+
+    // Violations of the calling convention detected so far, see the
+    // `calling_convention` check in the configuration.
+    pub fn calling_convention_violations(&self) -> &[ConventionViolation] {
+        self.convention
+            .as_ref()
+            .map_or(&[], |checker| checker.violations.as_slice())
+    }
+
+    // Starts recording edge coverage into a bitmap of the given `size`,
+    // the layout AFL-style fuzzers expect in their shared memory region
+    // (usually of 65536 bytes). Recording an already recorded bitmap
+    // starts over. See `CoverageBitmap` for how edges are hashed.
+    pub fn record_coverage_bitmap(&mut self, size: usize) {
+        assert!(size > 0, "bitmap not empty");
+        self.coverage = Some(CoverageBitmap::new(size));
+    }
+
+    // The coverage bitmap recorded so far, empty if recording was not
+    // started.
+    pub fn coverage_bitmap(&self) -> &[u8] {
+        self.coverage.as_ref().map_or(&[], |c| c.map.as_slice())
+    }
+
+    // Clears all recorded coverage (e.g. between two fuzzing inputs),
+    // keeping the size of the bitmap.
+    pub fn reset_coverage(&mut self) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.map.fill(0);
+            coverage.previous = 0;
+        }
+    }
+
+    // Attaches a symbol table (e.g. function symbols of the ELF file) used
+    // to annotate addresses in traces with `name+offset` style labels.
+    pub fn set_symbols(&mut self, mut symbols: Vec<(EmulatorValue, String)>) {
+        symbols.sort_by_key(|(address, _)| *address);
+        self.symbols = symbols;
+    }
+
+    // Symbol covering the given address together with the offset into
+    // it, which is the nearest symbol at or preceding the address.
+    pub fn symbol_at(&self, address: EmulatorValue) -> Option<(&str, EmulatorValue)> {
+        let index = self.symbols.partition_point(|(start, _)| *start <= address);
+        index.checked_sub(1).map(|index| {
+            let (start, name) = &self.symbols[index];
+            (name.as_str(), address - start)
+        })
+    }
+
+    // Addresses of all (possibly overlapping) occurrences of the byte
+    // pattern `needle` in memory, in ascending order. Memory is stored
+    // as plain bytes, so occurrences spanning word boundaries are found
+    // as well. An empty pattern is never found.
+    pub fn find_in_memory(&self, needle: &[u8]) -> Vec<EmulatorValue> {
+        let first = match needle.first() {
+            Some(first) => *first,
+            None => return Vec::new(),
+        };
+        let last_start = (self.memory.len() + 1).saturating_sub(needle.len());
+        self.memory[..last_start]
+            .iter()
+            .enumerate()
+            .filter(|(adr, byte)| **byte == first && self.memory[*adr..].starts_with(needle))
+            .map(|(adr, _)| adr as EmulatorValue)
+            .collect()
+    }
+
+    // Reads the zero-terminated C string at `address` from guest memory,
+    // without the terminating zero byte. Memory is stored as plain bytes,
+    // strings spanning word boundaries need no special treatment. Fails
+    // with `StringOutOfBounds` for addresses outside of memory, with
+    // `UnterminatedString` in case memory ends before the string does,
+    // and with `StringTooLong` for strings longer than
+    // `MAX_CSTRING_LENGTH` bytes (e.g. a pointer into garbage).
+    pub fn read_cstring(&self, address: EmulatorValue) -> Result<Vec<u8>, EmulatorError> {
+        self.cstring(address, MAX_CSTRING_LENGTH)
+            .map(<[u8]>::to_vec)
+    }
+
+    // Overwrites the instruction at the given address within the loaded
+    // code segment. Instructions are fetched from memory and decoded on
+    // every step (there is no decoded-instruction cache), so a patch takes
+    // effect the next time the address is executed, also mid-run. Fails
+    // with `PatchOutOfCode` unless the whole word lies within the segment.
+    pub fn patch_instruction(
+        &mut self,
+        address: EmulatorValue,
+        instr: Instruction,
+    ) -> Result<(), EmulatorError> {
+        let inside = address.checked_add(3).is_some_and(|last| {
+            self.code_range.contains(&address) && self.code_range.contains(&last)
+        });
+        if !inside {
+            return Err(EmulatorError::PatchOutOfCode { address });
+        }
+        trace!("patching pc={:#x} <- {:?}", address, instr);
+        self.set_mem_typed(address, u32::from(instr));
+        Ok(())
+    }
+
+    // Replaces the host streams backing the standard descriptors of the
+    // guest, which default to the standard streams of this process. The
+    // interpreter performs all guest I/O through these and the files
+    // opened by the guest, so embedders can capture or supply data.
+    pub fn set_stdio<R, W, E>(&mut self, stdin: R, stdout: W, stderr: E)
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+        E: Write + Send + 'static,
+    {
+        self.stdio = HostStdio {
+            stdin: Box::new(stdin),
+            stdout: Box::new(stdout),
+            stderr: Box::new(stderr),
+        };
+    }
+
+    // Mirrors everything the guest writes to its standard output and error
+    // streams into `log` as well, e.g. to keep a transcript of a session.
+    // Unlike capturing, the streams set before (see `set_stdio`) still
+    // receive all output, flushing flushes both. Both streams share the
+    // log, hence it interleaves them in the order they were written.
+    pub fn tee_output<L>(&mut self, log: L)
+    where
+        L: Write + Send + 'static,
+    {
+        let log: SharedLog = Arc::new(Mutex::new(Box::new(log)));
+        let stdout = std::mem::replace(&mut self.stdio.stdout, Box::new(io::sink()));
+        let stderr = std::mem::replace(&mut self.stdio.stderr, Box::new(io::sink()));
+        self.stdio.stdout = Box::new(TeeOutput {
+            sink: stdout,
+            log: log.clone(),
+        });
+        self.stdio.stderr = Box::new(TeeOutput { sink: stderr, log });
+    }
+
+    // Resources consumed by the guest during the current (or last) run.
+    pub fn resource_usage(&self) -> ResourceUsage {
+        self.usage
+    }
+
+    // Computes a fingerprint of the full machine state, covering all
+    // registers, the program counter, the program break, and all non-zero
+    // memory words together with their address. Values are hashed in this
+    // canonical order using 64-bit FNV-1a over their little-endian bytes,
+    // hence fingerprints are stable across runs, platforms and toolchains.
+    pub fn state_fingerprint(&self) -> u64 {
+        let mut hash = FNV_OFFSET_BASIS;
+        for value in self.registers.iter() {
+            hash = fnv1a(hash, *value);
+        }
+        hash = fnv1a(hash, self.program_counter);
+        hash = fnv1a(hash, self.program_break);
+        for (i, chunk) in self.memory.chunks(riscu::WORD_SIZE).enumerate() {
+            let mut bytes = [0; riscu::WORD_SIZE];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            let word = u64::from_le_bytes(bytes);
+            if word != 0 {
+                hash = fnv1a(hash, (i * riscu::WORD_SIZE) as u64);
+                hash = fnv1a(hash, word);
+            }
+        }
+        hash
+    }
+
+    // Computes a checksum over the `len` bytes of memory starting at the
+    // (not necessarily aligned) address `start`, for golden tests of data
+    // regions. This is the 64-bit FNV-1a hash of the bytes, hence stable
+    // across runs and platforms and independent of word boundaries.
+    // Returns `None` in case the region does not lie within memory.
+    pub fn checksum_region(&self, start: EmulatorValue, len: u64) -> Option<u64> {
+        let end = start.checked_add(len)?;
+        if end > self.memory.len() as u64 {
+            return None;
+        }
+        Some(fnv1a_bytes(
+            FNV_OFFSET_BASIS,
+            &self.memory[start as usize..end as usize],
+        ))
+    }
+}
+
+// Kind of an implemented instruction, i.e. its opcode without operands.
+// Each kind has a small numeric id (see `id`) for cheaply indexing tables
+// by kind (e.g. in profilers) instead of matching on instructions. Ids
+// are stable: They follow the order of `supported_instructions`, new
+// kinds are only ever appended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum InstructionKind {
+    Lui,
+    Auipc,
+    Jal,
+    Jalr,
+    Beq,
+    Bne,
+    Blt,
+    Bge,
+    Bltu,
+    Bgeu,
+    Lb,
+    Lh,
+    Lw,
+    Ld,
+    Lbu,
+    Lhu,
+    Sb,
+    Sh,
+    Sw,
+    Sd,
+    Addi,
+    Sltiu,
+    Xori,
+    Ori,
+    Andi,
+    Slli,
+    Srli,
+    Srai,
+    Addiw,
+    Slliw,
+    Srliw,
+    Sraiw,
+    Add,
+    Sub,
+    Sll,
+    Slt,
+    Sltu,
+    Srl,
+    Sra,
+    Or,
+    And,
+    Mul,
+    Div,
+    Divu,
+    Rem,
+    Remu,
+    Addw,
+    Subw,
+    Sllw,
+    Mulw,
+    Divw,
+    Remw,
+    Ecall,
+}
+
+impl InstructionKind {
+    // Number of kinds, all ids are below.
+    pub const COUNT: usize = 53;
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        SUPPORTED_INSTRUCTIONS[self as usize]
+    }
+}
+
+// Kind of the given instruction in case it is covered by `execute` below,
+// keep the two (as well as `SUPPORTED_INSTRUCTIONS`, in the same order as
+// `InstructionKind`) in sync when adding new instructions.
+pub fn instruction_kind(instr: Instruction) -> Option<InstructionKind> {
+    match instr {
+        Instruction::Lui(_) => Some(InstructionKind::Lui),
+        Instruction::Auipc(_) => Some(InstructionKind::Auipc),
+        Instruction::Jal(_) => Some(InstructionKind::Jal),
+        Instruction::Jalr(_) => Some(InstructionKind::Jalr),
+        Instruction::Beq(_) => Some(InstructionKind::Beq),
+        Instruction::Bne(_) => Some(InstructionKind::Bne),
+        Instruction::Blt(_) => Some(InstructionKind::Blt),
+        Instruction::Bge(_) => Some(InstructionKind::Bge),
+        Instruction::Bltu(_) => Some(InstructionKind::Bltu),
+        Instruction::Bgeu(_) => Some(InstructionKind::Bgeu),
+        Instruction::Lb(_) => Some(InstructionKind::Lb),
+        Instruction::Lh(_) => Some(InstructionKind::Lh),
+        Instruction::Lw(_) => Some(InstructionKind::Lw),
+        Instruction::Ld(_) => Some(InstructionKind::Ld),
+        Instruction::Lbu(_) => Some(InstructionKind::Lbu),
+        Instruction::Lhu(_) => Some(InstructionKind::Lhu),
+        Instruction::Sb(_) => Some(InstructionKind::Sb),
+        Instruction::Sh(_) => Some(InstructionKind::Sh),
+        Instruction::Sw(_) => Some(InstructionKind::Sw),
+        Instruction::Sd(_) => Some(InstructionKind::Sd),
+        Instruction::Addi(_) => Some(InstructionKind::Addi),
+        Instruction::Sltiu(_) => Some(InstructionKind::Sltiu),
+        Instruction::Xori(_) => Some(InstructionKind::Xori),
+        Instruction::Ori(_) => Some(InstructionKind::Ori),
+        Instruction::Andi(_) => Some(InstructionKind::Andi),
+        Instruction::Slli(_) => Some(InstructionKind::Slli),
+        Instruction::Srli(_) => Some(InstructionKind::Srli),
+        Instruction::Srai(_) => Some(InstructionKind::Srai),
+        Instruction::Addiw(_) => Some(InstructionKind::Addiw),
+        Instruction::Slliw(_) => Some(InstructionKind::Slliw),
+        Instruction::Srliw(_) => Some(InstructionKind::Srliw),
+        Instruction::Sraiw(_) => Some(InstructionKind::Sraiw),
+        Instruction::Add(_) => Some(InstructionKind::Add),
+        Instruction::Sub(_) => Some(InstructionKind::Sub),
+        Instruction::Sll(_) => Some(InstructionKind::Sll),
+        Instruction::Slt(_) => Some(InstructionKind::Slt),
+        Instruction::Sltu(_) => Some(InstructionKind::Sltu),
+        Instruction::Srl(_) => Some(InstructionKind::Srl),
+        Instruction::Sra(_) => Some(InstructionKind::Sra),
+        Instruction::Or(_) => Some(InstructionKind::Or),
+        Instruction::And(_) => Some(InstructionKind::And),
+        Instruction::Mul(_) => Some(InstructionKind::Mul),
+        Instruction::Div(_) => Some(InstructionKind::Div),
+        Instruction::Divu(_) => Some(InstructionKind::Divu),
+        Instruction::Rem(_) => Some(InstructionKind::Rem),
+        Instruction::Remu(_) => Some(InstructionKind::Remu),
+        Instruction::Addw(_) => Some(InstructionKind::Addw),
+        Instruction::Subw(_) => Some(InstructionKind::Subw),
+        Instruction::Sllw(_) => Some(InstructionKind::Sllw),
+        Instruction::Mulw(_) => Some(InstructionKind::Mulw),
+        Instruction::Divw(_) => Some(InstructionKind::Divw),
+        Instruction::Remw(_) => Some(InstructionKind::Remw),
+        Instruction::Ecall(_) => Some(InstructionKind::Ecall),
+        _ => None,
+    }
+}
+
+// Mnemonics of all instructions implemented by the emulator.
+pub fn supported_instructions() -> &'static [&'static str] {
+    SUPPORTED_INSTRUCTIONS
+}
+
+// System calls implemented by the emulator. Note that some of them are
+// merely stubs (see the respective documentation of each system call).
+pub fn supported_syscalls() -> Vec<SyscallId> {
+    SYSCALL_HANDLERS.iter().map(|(id, _)| *id).collect()
+}
+
+// Builds the argument vector for executing the program at `path` with
+// the given `args`. Just like `exec` does, `argv[0]` defaults to the path
+// of the executed file, an explicitly provided `argv0` always wins.
+pub fn program_argv<P>(path: P, argv0: Option<&str>, args: &[String]) -> Vec<String>
+where
+    P: AsRef<Path>,
+{
+    let argv0 = match argv0 {
+        Some(argv0) => argv0.to_string(),
+        None => path.as_ref().to_string_lossy().into_owned(),
+    };
+    std::iter::once(argv0).chain(args.iter().cloned()).collect()
+}
+
+// Reads an argument vector of arbitrary bytes (see `bootstrap_bytes`)
+// from the file at `path`, avoiding any quoting or encoding issues. The
+// file holds the number of arguments, followed by the length and bytes
+// of each argument in turn, all numbers being 32-bit little-endian:
+//
+// | count | length[0] | bytes[0] | ... | length[n] | bytes[n] |
+//
+// Trailing bytes after the last argument are rejected.
+pub fn argv_from_file<P>(path: P) -> Result<Vec<Vec<u8>>, EmulatorError>
+where
+    P: AsRef<Path>,
+{
+    fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], EmulatorError> {
+        if bytes.len() < len {
+            return Err(EmulatorError::MalformedArgvFile);
+        }
+        let (head, tail) = bytes.split_at(len);
+        *bytes = tail;
+        Ok(head)
+    }
+    let contents = std::fs::read(path)?;
+    let mut bytes = contents.as_slice();
+    let count = LittleEndian::read_u32(take(&mut bytes, size_of::<u32>())?);
+    let mut argv = Vec::new();
+    for _ in 0..count {
+        let len = LittleEndian::read_u32(take(&mut bytes, size_of::<u32>())?);
+        argv.push(take(&mut bytes, len as usize)?.to_vec());
+    }
+    if !bytes.is_empty() {
+        return Err(EmulatorError::MalformedArgvFile);
+    }
+    Ok(argv)
+}
+
+// Reads the entry point (i.e. `e_entry`) from the header of the ELF file
+// at `path`, supporting both 32-bit and 64-bit files.
+pub fn elf_entry_point<P>(path: P) -> Result<EmulatorValue, EmulatorError>
+where
+    P: AsRef<Path>,
+{
+    let mut header = [0; ELF_ENTRY_OFFSET + 8];
+    File::open(path)?.read_exact(&mut header)?;
+    if !header.starts_with(b"\x7fELF") {
+        return Err(io::Error::new(ErrorKind::InvalidData, "not an ELF file").into());
+    }
+    let entry = &header[ELF_ENTRY_OFFSET..];
+    match header[ELF_CLASS_OFFSET] {
+        ELF_CLASS_32 => Ok(LittleEndian::read_u32(entry) as EmulatorValue),
+        _ => Ok(LittleEndian::read_u64(entry)),
+    }
+}
+
+// Collects the address ranges of read-only data (e.g. `.rodata`) from the
+// section headers of the ELF file at `path`, i.e. all allocated sections
+// of program data which are neither writable nor executable. This is
+// best-effort, as the loader only distinguishes code from data: Files
+// without section headers (e.g. stripped ones, or those produced by
+// selfie, which merges constants into writable data) yield no ranges.
+pub fn elf_read_only_data<P>(path: P) -> Result<Vec<Range<EmulatorValue>>, EmulatorError>
+where
+    P: AsRef<Path>,
+{
+    let contents = std::fs::read(path)?;
+    let truncated = || io::Error::new(ErrorKind::InvalidData, "truncated ELF file");
+    if !contents.starts_with(b"\x7fELF") {
+        return Err(io::Error::new(ErrorKind::InvalidData, "not an ELF file").into());
+    }
+    let is32 = contents.get(ELF_CLASS_OFFSET) == Some(&ELF_CLASS_32);
+    let field = |offset: usize, size: usize| -> Result<u64, io::Error> {
+        let bytes = contents.get(offset..offset + size).ok_or_else(truncated)?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0, |value, &byte| value << 8 | u64::from(byte)))
+    };
+    let word = if is32 { 4 } else { 8 };
+    let (shoff, shentsize, shnum) = if is32 {
+        (field(0x20, 4)?, field(0x2e, 2)?, field(0x30, 2)?)
+    } else {
+        (field(0x28, 8)?, field(0x3a, 2)?, field(0x3c, 2)?)
+    };
+    let mut ranges = Vec::new();
+    for index in 0..shnum {
+        let header = (shoff + index * shentsize) as usize;
+        let kind = field(header + 4, 4)?;
+        let flags = field(header + 8, word)?;
+        let address = field(header + 8 + word, word)?;
+        let size = field(header + 8 + 3 * word, word)?;
+        if kind == SHT_PROGBITS
+            && flags & (SHF_ALLOC | SHF_WRITE | SHF_EXECINSTR) == SHF_ALLOC
+            && size > 0
+        {
+            ranges.push(address..address + size);
+        }
+    }
+    Ok(ranges)
+}
+
+// Decodes a single raw instruction word, returning `None` for anything
+// the emulator does not implement (even if `riscu` can decode it).
+pub fn decode_one(word: u32) -> Option<Instruction> {
+    riscu::decode(word)
+        .ok()
+        .filter(|instr| mnemonic(*instr).is_some())
+}
+
+// Encodes an implemented instruction back into its raw instruction word,
+// returning `None` for anything the emulator does not implement. This is
+// assembled from the decoded fields (i.e. registers and immediates) and
+// hence independent of the decoder, allowing to cross-check it.
+pub fn encode(instr: Instruction) -> Option<u32> {
+    let word = match instr {
+        Instruction::Lui(utype) => encode_utype(0x37, utype),
+        Instruction::Auipc(utype) => encode_utype(0x17, utype),
+        Instruction::Jal(jtype) => encode_jtype(0x6f, jtype),
+        Instruction::Jalr(itype) => encode_itype(0x67, 0, itype),
+        Instruction::Beq(btype) => encode_btype(0x63, 0, btype),
+        Instruction::Bne(btype) => encode_btype(0x63, 1, btype),
+        Instruction::Blt(btype) => encode_btype(0x63, 4, btype),
+        Instruction::Bge(btype) => encode_btype(0x63, 5, btype),
+        Instruction::Bltu(btype) => encode_btype(0x63, 6, btype),
+        Instruction::Bgeu(btype) => encode_btype(0x63, 7, btype),
+        Instruction::Lb(itype) => encode_itype(0x03, 0, itype),
+        Instruction::Lh(itype) => encode_itype(0x03, 1, itype),
+        Instruction::Lw(itype) => encode_itype(0x03, 2, itype),
+        Instruction::Ld(itype) => encode_itype(0x03, 3, itype),
+        Instruction::Lbu(itype) => encode_itype(0x03, 4, itype),
+        Instruction::Lhu(itype) => encode_itype(0x03, 5, itype),
+        Instruction::Sb(stype) => encode_stype(0x23, 0, stype),
+        Instruction::Sh(stype) => encode_stype(0x23, 1, stype),
+        Instruction::Sw(stype) => encode_stype(0x23, 2, stype),
+        Instruction::Sd(stype) => encode_stype(0x23, 3, stype),
+        // Shift amounts and the bit distinguishing arithmetic shifts are
+        // both part of the immediate of the shift instructions.
+        Instruction::Addi(itype) => encode_itype(0x13, 0, itype),
+        Instruction::Slli(itype) => encode_itype(0x13, 1, itype),
+        Instruction::Sltiu(itype) => encode_itype(0x13, 3, itype),
+        Instruction::Xori(itype) => encode_itype(0x13, 4, itype),
+        Instruction::Srli(itype) | Instruction::Srai(itype) => encode_itype(0x13, 5, itype),
+        Instruction::Ori(itype) => encode_itype(0x13, 6, itype),
+        Instruction::Andi(itype) => encode_itype(0x13, 7, itype),
+        Instruction::Addiw(itype) => encode_itype(0x1b, 0, itype),
+        Instruction::Slliw(itype) => encode_itype(0x1b, 1, itype),
+        Instruction::Srliw(itype) | Instruction::Sraiw(itype) => encode_itype(0x1b, 5, itype),
+        Instruction::Add(rtype) => encode_rtype(0x33, 0, 0x00, rtype),
+        Instruction::Sub(rtype) => encode_rtype(0x33, 0, 0x20, rtype),
+        Instruction::Sll(rtype) => encode_rtype(0x33, 1, 0x00, rtype),
+        Instruction::Slt(rtype) => encode_rtype(0x33, 2, 0x00, rtype),
+        Instruction::Sltu(rtype) => encode_rtype(0x33, 3, 0x00, rtype),
+        Instruction::Srl(rtype) => encode_rtype(0x33, 5, 0x00, rtype),
+        Instruction::Sra(rtype) => encode_rtype(0x33, 5, 0x20, rtype),
+        Instruction::Or(rtype) => encode_rtype(0x33, 6, 0x00, rtype),
+        Instruction::And(rtype) => encode_rtype(0x33, 7, 0x00, rtype),
+        Instruction::Mul(rtype) => encode_rtype(0x33, 0, 0x01, rtype),
+        Instruction::Div(rtype) => encode_rtype(0x33, 4, 0x01, rtype),
+        Instruction::Divu(rtype) => encode_rtype(0x33, 5, 0x01, rtype),
+        Instruction::Rem(rtype) => encode_rtype(0x33, 6, 0x01, rtype),
+        Instruction::Remu(rtype) => encode_rtype(0x33, 7, 0x01, rtype),
+        Instruction::Addw(rtype) => encode_rtype(0x3b, 0, 0x00, rtype),
+        Instruction::Subw(rtype) => encode_rtype(0x3b, 0, 0x20, rtype),
+        Instruction::Sllw(rtype) => encode_rtype(0x3b, 1, 0x00, rtype),
+        Instruction::Mulw(rtype) => encode_rtype(0x3b, 0, 0x01, rtype),
+        Instruction::Divw(rtype) => encode_rtype(0x3b, 4, 0x01, rtype),
+        Instruction::Remw(rtype) => encode_rtype(0x3b, 6, 0x01, rtype),
+        Instruction::Ecall(itype) => encode_itype(0x73, 0, itype),
+        _ => return None,
+    };
+    Some(word)
+}
+
+// Builds a decoded program from raw code and data bytes (e.g. assembled
+// by hand) located at the given addresses, without going through an ELF
+// file. Code is decoded word by word, any word not implemented by the
+// emulator (see `decode_one`) is rejected with `UnsupportedInstruction`.
+// Data is packed into little-endian double words, which requires a word-
+// aligned data address (otherwise `MisalignedDataSegment` is returned).
+// A trailing partial word in either segment is padded with zero bytes,
+// note that a padded instruction word is decoded like any other (i.e.
+// might be accepted).
+pub fn decoded_program_from_bytes(
+    code_address: EmulatorValue,
+    code: &[u8],
+    data_address: EmulatorValue,
+    data: &[u8],
+) -> Result<DecodedProgram, EmulatorError> {
+    let instructions = code_words(code_address, code)
+        .map(|(address, word)| {
+            decode_one(word).ok_or(EmulatorError::UnsupportedInstruction { address, word })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if data_address & WORD_SIZE_MASK != 0 {
+        return Err(EmulatorError::MisalignedDataSegment {
+            address: data_address,
+        });
+    }
+    let words = data
+        .chunks(size_of::<EmulatorValue>())
+        .map(|chunk| {
+            let mut bytes = [0; size_of::<EmulatorValue>()];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            EmulatorValue::from_le_bytes(bytes)
+        })
+        .collect();
+    Ok(DecodedProgram {
+        code: ProgramSegment {
+            address: code_address,
+            content: instructions,
+        },
+        data: ProgramSegment {
+            address: data_address,
+            content: words,
+        },
+    })
+}
+
+// Decodes all of the given raw code bytes located at `address` into an
+// annotated listing, the non-aborting counterpart to `decode_one`. Every
+// word is reported with its address, words that can not be decoded are
+// flagged inline (see `DecodeError`). A trailing partial word is padded
+// with zero bytes, just like `decoded_program_from_bytes` does.
+pub fn disassemble_bytes(
+    address: EmulatorValue,
+    code: &[u8],
+) -> Vec<(EmulatorValue, Result<Instruction, DecodeError>)> {
+    code_words(address, code)
+        .map(|(address, word)| {
+            let decoded = match riscu::decode(word) {
+                Ok(instr) if mnemonic(instr).is_some() => Ok(instr),
+                Ok(_) => Err(DecodeError::Unsupported { word }),
+                Err(_) => Err(DecodeError::Invalid { word }),
+            };
+            (address, decoded)
+        })
+        .collect()
+}
+
+// Statically scans the code segment of the given `program` for `ecall`
+// instructions and reports the set of system call numbers it may invoke.
+// This is a heuristic local analysis: The value of `a7` is only tracked
+// through straight-line code where it is set by immediate loads (i.e.
+// `lui` and `addi`). Any other write to `a7` or any jump (i.e. calls) in
+// between makes the value unknown, and such `ecall` instructions are
+// not reported. Hence system call numbers loaded indirectly (e.g. from
+// memory or passed into a generic `syscall` wrapper) will not be caught.
+pub fn required_syscalls(program: &DecodedProgram) -> BTreeSet<u64> {
+    let mut syscalls = BTreeSet::new();
+    let mut a7_value: Option<u64> = None;
+    for instr in program.code.content.iter() {
+        match *instr {
+            Instruction::Ecall(_) => {
+                if let Some(id) = a7_value {
+                    syscalls.insert(id);
+                }
+            }
+            Instruction::Jal(_) | Instruction::Jalr(_) => a7_value = None,
+            Instruction::Lui(utype) if utype.rd() == Register::A7 => {
+                a7_value = Some(((utype.imm() as i32) << 12) as u64);
+            }
+            Instruction::Addi(itype) if itype.rd() == Register::A7 => {
+                a7_value = match itype.rs1() {
+                    Register::Zero => Some(itype.imm() as u64),
+                    Register::A7 => a7_value.map(|v| v.wrapping_add(itype.imm() as u64)),
+                    _ => None,
+                };
+            }
+            _ if destination_register(*instr) == Some(Register::A7) => a7_value = None,
+            _ => (),
+        }
+    }
+    syscalls
+}
+
+// Statically traverses the control-flow graph of the given `program`
+// starting at `entry` and reports the addresses of all instructions it
+// reaches, following fall-through as well as direct branches and jumps.
+// Calls (i.e. jumps linking a return address) are assumed to return,
+// hence also continue with the next instruction. Targets of indirect
+// jumps (i.e. `jalr`) are unknown and not followed, so code reached only
+// through function pointers, jump tables or returns to other call sites
+// is missed. Comparing the result against dynamic coverage shows code
+// that is reachable but was never executed.
+pub fn static_reachable(program: &DecodedProgram, entry: u64) -> BTreeSet<u64> {
+    let code = &program.code;
+    let size = riscu::INSTRUCTION_SIZE as u64;
+    let end = code.address + code.content.len() as u64 * size;
+    let mut reachable = BTreeSet::new();
+    let mut pending = vec![entry];
+    while let Some(pc) = pending.pop() {
+        if pc < code.address || pc >= end || (pc - code.address) % size != 0 {
+            continue;
+        }
+        if !reachable.insert(pc) {
+            continue;
+        }
+        let instr = code.content[((pc - code.address) / size) as usize];
+        match instr {
+            Instruction::Jal(jtype) => {
+                pending.push(pc.wrapping_add(jtype.imm() as u64));
+                if jtype.rd() != Register::Zero {
+                    pending.push(pc + size);
+                }
+            }
+            Instruction::Jalr(itype) => {
+                if itype.rd() != Register::Zero {
+                    pending.push(pc + size);
+                }
+            }
+            Instruction::Beq(btype)
+            | Instruction::Bne(btype)
+            | Instruction::Blt(btype)
+            | Instruction::Bge(btype)
+            | Instruction::Bltu(btype)
+            | Instruction::Bgeu(btype) => {
+                pending.push(pc.wrapping_add(btype.imm() as u64));
+                pending.push(pc + size);
+            }
+            _ => pending.push(pc + size),
+        }
+    }
+    reachable
+}
+
+//
+// Private Implementation
+//
+
+const INSTRUCTION_SIZE_MASK: u64 = 2_u64 - 1;
+const WORD_SIZE_MASK: u64 = riscu::WORD_SIZE as u64 - 1;
+const MAX_FILENAME_LENGTH: usize = 128;
+const MAX_CSTRING_LENGTH: usize = 4096;
+const MAX_DESCRIPTORS: usize = 1024;
+const FAKE_PID: EmulatorValue = 1;
+const FAKE_TID: EmulatorValue = FAKE_PID;
+const INTERRUPT_CHECK_INTERVAL: u64 = 4096;
+const CLOCK_TICKS_PER_SECOND: u64 = 100;
+const RUSAGE_SIZE: usize = 144;
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+const MAX_IOVCNT: u64 = 1024;
+const MAX_STACK_OFFSET: u64 = 64 * 1024;
+const SYSINFO_SIZE: usize = 112;
+const UTSNAME_FIELD_SIZE: usize = 65;
+const ELF_CLASS_OFFSET: usize = 4;
+const ELF_CLASS_32: u8 = 1;
+const ELF_ENTRY_OFFSET: usize = 24;
+const SNAPSHOT_MAGIC: &[u8] = b"UNISNAP1";
+const SHT_PROGBITS: u64 = 1;
+const SHF_WRITE: u64 = 0x1;
+const SHF_ALLOC: u64 = 0x2;
+const SHF_EXECINSTR: u64 = 0x4;
+const RLIMIT_DATA: u64 = 2;
+const RLIMIT_STACK: u64 = 3;
+const RLIMIT_AS: u64 = 9;
+const RLIM_INFINITY: u64 = u64::MAX;
+const STACK_ALIGNMENT: u64 = 16;
+const CLOCK_PROCESS_CPUTIME_ID: u64 = 2;
+const CLOCK_THREAD_CPUTIME_ID: u64 = 3;
+const CLOCK_BOOTTIME: u64 = 7;
+const TIMER_ABSTIME: u64 = 1;
+
+// Terminal requests understood by `ioctl`, only the standard descriptors
+// behave like a terminal. `TCGETS` reports the `termios` of a terminal in
+// canonical mode (see `FAKE_TERMIOS`), `TIOCGWINSZ` reports a window of
+// 24 rows and 80 columns (with zero pixel sizes).
+const TCGETS: u64 = 0x5401;
+const TIOCGWINSZ: u64 = 0x5413;
+const FAKE_WINDOW_ROWS: u16 = 24;
+const FAKE_WINDOW_COLS: u16 = 80;
+
+// The 36 bytes of `struct termios` as returned by `TCGETS`, these are the
+// flags `ICRNL|IXON`, `OPOST|ONLCR`, `B38400|CS8|CREAD` and finally
+// `ISIG|ICANON|ECHO|ECHOE|ECHOK|ECHOCTL|ECHOKE|IEXTEN`, followed by line
+// discipline and control characters all being zero.
+const FAKE_TERMIOS: [u32; 4] = [0x0000_0500, 0x0000_0005, 0x0000_00bf, 0x0000_8a3b];
+const TERMIOS_SIZE: usize = 36;
+
+// Protection and flags understood by `mmap`, any other flags are ignored.
+const PROT_WRITE: u64 = 0x2;
+const MAP_FIXED: u64 = 0x10;
+const MAP_ANONYMOUS: u64 = 0x20;
+
+// Flags understood by `pipe2`, `O_CLOEXEC` has no effect as there is no
+// `execve` to close descriptors on.
+const O_NONBLOCK: u64 = 0o4000;
+const O_CLOEXEC: u64 = 0o2000000;
+
+// Signal numbers and the layout of `struct sigaction` as used by Linux on
+// RISC-V, i.e. handler, flags and mask (there is no restorer field).
+const NSIG: u64 = 64;
+const SIGKILL: u64 = 9;
+const SIGSTOP: u64 = 19;
+const SIGACTION_SIZE: usize = 24;
+
+// Accessibility checks understood by `faccessat`, no bits means `F_OK`.
+const AT_FDCWD: i64 = -100;
+
+// Guest functions called through `call_function` return to this address.
+const CALLBACK_RETURN: EmulatorValue = 0;
+const MAX_CALLBACK_NESTING: usize = 16;
+const CALL_ARGUMENTS: [Register; 8] = [
+    Register::A0,
+    Register::A1,
+    Register::A2,
+    Register::A3,
+    Register::A4,
+    Register::A5,
+    Register::A6,
+    Register::A7,
+];
+const R_OK: u64 = 0x4;
+const W_OK: u64 = 0x2;
+const X_OK: u64 = 0x1;
+
+const SUPPORTED_INSTRUCTIONS: &[&str] = &[
+    "lui", "auipc", "jal", "jalr", "beq", "bne", "blt", "bge", "bltu", "bgeu", "lb", "lh", "lw",
+    "ld", "lbu", "lhu", "sb", "sh", "sw", "sd", "addi", "sltiu", "xori", "ori", "andi", "slli",
+    "srli", "srai", "addiw", "slliw", "srliw", "sraiw", "add", "sub", "sll", "slt", "sltu", "srl",
+    "sra", "or", "and", "mul", "div", "divu", "rem", "remu", "addw", "subw", "sllw", "mulw",
+    "divw", "remw", "ecall",
+];
+
+type SyscallHandler = fn(&mut EmulatorState);
+
+// System calls implemented by `exec_ecall` together with their handlers.
+const SYSCALL_HANDLERS: &[(SyscallId, SyscallHandler)] = &[
+    (SyscallId::Exit, syscall_exit),
+    (SyscallId::ExitGroup, syscall_exit),
+    (SyscallId::Read, syscall_read),
+    (SyscallId::Write, syscall_write),
+    (SyscallId::Open, syscall_open),
+    (SyscallId::Openat, syscall_openat),
+    (SyscallId::Brk, syscall_brk),
+    (SyscallId::Close, syscall_close),
+    (SyscallId::SetTidAddress, syscall_set_tid_address),
+    (SyscallId::Getpid, syscall_getpid),
+    (SyscallId::Gettid, syscall_gettid),
+    (SyscallId::Dup, syscall_dup),
+    (SyscallId::Dup3, syscall_dup3),
+    (SyscallId::Pipe2, syscall_pipe2),
+    (SyscallId::RtSigaction, syscall_rt_sigaction),
+    (SyscallId::RtSigprocmask, syscall_rt_sigprocmask),
+    (SyscallId::Getcwd, syscall_getcwd),
+    (SyscallId::Access, syscall_access),
+    (SyscallId::Faccessat, syscall_faccessat),
+    (SyscallId::Times, syscall_times),
+    (SyscallId::Getrusage, syscall_getrusage),
+    (SyscallId::Ioctl, syscall_ioctl),
+    (SyscallId::Mmap, syscall_mmap),
+    (SyscallId::Getrandom, syscall_getrandom),
+    (SyscallId::SchedYield, syscall_sched_yield),
+    (SyscallId::Sysinfo, syscall_sysinfo),
+    (SyscallId::Uname, syscall_uname),
+    (SyscallId::Getrlimit, syscall_getrlimit),
+    (SyscallId::Prlimit64, syscall_prlimit64),
+    (SyscallId::Readv, syscall_readv),
+    (SyscallId::Writev, syscall_writev),
+    (SyscallId::Nanosleep, syscall_nanosleep),
+    (SyscallId::ClockNanosleep, syscall_clock_nanosleep),
+    (SyscallId::ClockGettime, syscall_clock_gettime),
+];
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+fn fnv1a(hash: u64, value: u64) -> u64 {
+    fnv1a_bytes(hash, &value.to_le_bytes())
+}
+
+fn fnv1a_bytes(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(hash, |h, b| (h ^ *b as u64).wrapping_mul(FNV_PRIME))
+}
+
+fn initial_program_counter(program: &Program) -> EmulatorValue {
+    program.instruction_range.start
+}
+
+fn initial_program_break(program: &Program) -> EmulatorValue {
+    let data_size = program.data.content.len();
+    let data_end = program.data.address + data_size as u64;
+    next_multiple_of(data_end, PAGE_SIZE as u64)
+}
+
+impl EmulatorState {
+    fn pc_add(&mut self, imm: u64) {
+        self.program_counter = self.program_counter.wrapping_add(imm);
+    }
+
+    fn current_instruction_lenght(&self) -> usize {
+        instruction_length(self.get_mem_unaligned::<u16>(self.program_counter))
+    }
+
+    fn pc_next(&mut self) {
+        self.pc_add(self.current_instruction_lenght() as u64);
+    }
+
+    // TODO: Move to public portion of file.
+    pub fn pc_set(&mut self, val: EmulatorValue) {
+        assert!(val & INSTRUCTION_SIZE_MASK == 0, "program counter aligned");
+        self.program_counter = val;
+    }
+
+    // TODO: Move to public portion of file.
+    pub fn get_reg(&self, reg: Register) -> EmulatorValue {
+        // The `zero` register reads as zero independent of its backing
+        // slot, so the invariant holds regardless of how it was written.
+        if reg == Register::Zero {
+            return 0;
+        }
+        self.registers[reg as usize]
+    }
+
+    // TODO: Move to public portion of file.
+    pub fn set_reg(&mut self, reg: Register, val: EmulatorValue) {
+        debug_assert!(reg != Register::Zero, "cannot set `zero` register");
+        if let Some(delta) = &mut self.recording {
+            delta
+                .registers
+                .push((reg as usize, self.registers[reg as usize]));
+        }
+        self.registers[reg as usize] = val;
+        if reg == Register::Sp && self.config.resource_accounting {
+            self.track_stack_peak(val);
+        }
+        if reg == Register::Sp && self.config.check_stack_pointer && self.running {
+            self.check_stack_pointer(val);
+        }
+        if !self.register_watches.is_empty() {
+            self.check_register_watches(reg, val);
+        }
+    }
+
+    // Copies the given words into the guest buffer at `adr`, failing in
+    // case the buffer is unaligned or does not fit into memory.
+    fn copy_words_out(&mut self, adr: EmulatorValue, words: &[u64]) -> Result<(), Errno> {
+        let size = words.len() as u64 * riscu::WORD_SIZE as u64;
+        match adr.checked_add(size) {
+            Some(end) if adr & WORD_SIZE_MASK == 0 && end <= self.memory.len() as u64 => {}
+            _ => return Err(Errno::EFAULT),
+        }
+        for (i, word) in words.iter().enumerate() {
+            self.set_mem(adr + (i * riscu::WORD_SIZE) as u64, *word);
+        }
+        Ok(())
+    }
+
+    // Copies the given bytes into the guest buffer at `adr`, failing in
+    // case the buffer does not fit into memory.
+    fn copy_bytes_out(&mut self, adr: EmulatorValue, bytes: &[u8]) -> Result<(), Errno> {
+        match adr.checked_add(bytes.len() as u64) {
+            Some(end) if end <= self.memory.len() as u64 => {}
+            _ => return Err(Errno::EFAULT),
+        }
+        self.copy_mem(adr, bytes);
+        Ok(())
+    }
+
+    // Finds the zero-terminated string at `adr` in guest memory, which
+    // has to be at most `limit` bytes long (without the terminator).
+    fn cstring(&self, adr: EmulatorValue, limit: usize) -> Result<&[u8], EmulatorError> {
+        if adr >= self.memory.len() as u64 {
+            return Err(EmulatorError::StringOutOfBounds { address: adr });
+        }
+        let bytes = &self.memory[adr as usize..];
+        match bytes.iter().take(limit + 1).position(|b| *b == 0) {
+            Some(len) => Ok(&bytes[..len]),
+            None if bytes.len() <= limit => Err(EmulatorError::UnterminatedString { address: adr }),
+            None => Err(EmulatorError::StringTooLong {
+                address: adr,
+                limit,
+            }),
+        }
+    }
+
+    // Reads the zero-terminated path name at `adr` from guest memory,
+    // failing in case it is not terminated within memory or does not fit
+    // into `MAX_FILENAME_LENGTH` bytes (including the terminator).
+    fn read_path(&self, adr: EmulatorValue) -> Result<String, Errno> {
+        let bytes = self
+            .cstring(adr, MAX_FILENAME_LENGTH - 1)
+            .map_err(|error| match error {
+                EmulatorError::StringTooLong { .. } => Errno::ENAMETOOLONG,
+                // Either `StringOutOfBounds` or `UnterminatedString`.
+                _ => Errno::EFAULT,
+            })?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| Errno::ENOENT)
+    }
+
+    fn next_random(&mut self) -> u64 {
+        splitmix64(&mut self.random_state)
+    }
+
+    // Replaces the machine state by that of a new machine with the same
+    // configuration, reusing (but clearing) the allocated memory. State
+    // set up by the embedder is kept: hooks, watches, regions, symbols,
+    // the trace filter, read-only ranges, scheduled faults, syscall
+    // overrides, the attached cache, standard streams and the stop
+    // handle. Everything derived from running the guest (registers,
+    // memory, descriptors, signal handlers, the recorded call graph and
+    // coverage, usage and history) is cleared, as is scripted input.
+    fn reset(&mut self) {
+        let mut memory = std::mem::take(&mut self.memory);
+        memory.fill(0);
+        let fresh = Self::with_config(0, self.config.clone());
+        let old = std::mem::replace(self, fresh);
+        self.memory = memory;
+        self.read_only = old.read_only;
+        self.fault_schedule = old.fault_schedule;
+        self.register_watches = old.register_watches;
+        self.memory_watches = old.memory_watches;
+        self.symbols = old.symbols;
+        self.witness = old.witness;
+        self.break_hook = old.break_hook;
+        self.access_hook = old.access_hook;
+        self.cache = old.cache;
+        self.trace_hook = old.trace_hook;
+        self.regions = old
+            .regions
+            .into_iter()
+            .map(|region| Region {
+                inside: false,
+                ..region
+            })
+            .collect();
+        self.trace_filter = old.trace_filter;
+        self.tracing = old.tracing;
+        self.syscall_overrides = old.syscall_overrides;
+        self.syscall_hooks = old.syscall_hooks;
+        self.interrupt = old.interrupt;
+        self.stdio = old.stdio;
+    }
+
+    // Offset of the initial stack pointer from the top of memory, see
+    // `layout_seed` in the configuration.
+    fn stack_offset(&self) -> u64 {
+        match self.config.layout_seed {
+            Some(mut seed) => {
+                let space = (self.memory.len() as u64).saturating_sub(self.program_break);
+                let limit = min(MAX_STACK_OFFSET, space / 2) / STACK_ALIGNMENT;
+                match limit {
+                    0 => 0,
+                    _ => splitmix64(&mut seed) % limit * STACK_ALIGNMENT,
+                }
+            }
+            None => 0,
+        }
+    }
+
+    // Synthetic user time consumed so far, in microseconds.
+    fn user_time_micros(&self) -> u64 {
+        let micros_per_tick = 1_000_000 / CLOCK_TICKS_PER_SECOND;
+        let micros = self.usage.instructions as u128 * micros_per_tick as u128
+            / self.config.instructions_per_tick as u128;
+        micros as u64
+    }
+
+    // Synthetic wall-clock time elapsed so far, in nanoseconds. This is
+    // the user time plus the time the guest requested to sleep.
+    fn elapsed_nanos(&self) -> u64 {
+        self.user_time_micros() * 1_000 + self.slept_nanos
+    }
+
+    // Copies `count` words out of the guest buffer at `adr`, failing in
+    // case the buffer is unaligned or does not fit into memory.
+    fn copy_words_in(&self, adr: EmulatorValue, count: usize) -> Result<Vec<u64>, Errno> {
+        let size = count as u64 * riscu::WORD_SIZE as u64;
+        match adr.checked_add(size) {
+            Some(end) if adr & WORD_SIZE_MASK == 0 && end <= self.memory.len() as u64 => {}
+            _ => return Err(Errno::EFAULT),
+        }
+        let words = (0..count).map(|i| self.get_mem(adr + (i * riscu::WORD_SIZE) as u64));
+        Ok(words.collect())
+    }
+
+    fn witness_branch(&mut self, taken: bool) {
+        let pc = self.program_counter;
+        if let Some(WitnessHook(hook)) = &mut self.witness {
+            hook(WitnessEvent::Branch { pc, taken });
+        }
+    }
+
+    fn witness_input(&mut self, fd: EmulatorValue, adr: EmulatorValue, bytes: &[u8]) {
+        if let Some(WitnessHook(hook)) = &mut self.witness {
+            for (address, byte) in (adr..).zip(bytes) {
+                hook(WitnessEvent::Input {
+                    fd,
+                    address,
+                    byte: *byte,
+                });
+            }
+        }
+    }
+
+    fn observe_jump(
+        &mut self,
+        origin: EmulatorValue,
+        instr: Instruction,
+        return_address: EmulatorValue,
+    ) {
+        let target = self.program_counter;
+        if let Some(call_graph) = &mut self.call_graph {
+            call_graph.observe_jump(instr, return_address, target);
+        }
+        if is_call(instr) {
+            self.call_frames.push(return_address);
+            if let Some(limit) = self.config.max_call_depth {
+                if self.call_frames.len() as u64 > limit {
+                    self.fail(EmulatorError::RecursionLimitExceeded {
+                        depth: limit,
+                        pc: origin,
+                    });
+                }
+            }
+        } else if let Some(depth) = self.call_frames.iter().rposition(|ra| *ra == target) {
+            self.call_frames.truncate(depth);
+        }
+        let violation = match &mut self.convention {
+            Some(checker) => checker.observe_jump(origin, instr, return_address, target),
+            None => None,
+        };
+        if let Some(violation) = violation {
+            warn!("calling convention violated: {}", violation);
+            if self.config.calling_convention == CallingConventionCheck::Abort {
+                self.fail(EmulatorError::CallingConventionViolation { violation });
+            }
+        }
+    }
+
+    // Reports regions entered or left now that execution arrived at `pc`
+    // (see `register_region` for the order).
+    fn observe_regions(&mut self, pc: EmulatorValue) {
+        let instructions_retired = self.retired;
+        for region in self.regions.iter_mut().rev() {
+            if region.inside && !region.range.contains(&pc) {
+                region.inside = false;
+                let event = RegionEvent {
+                    name: &region.name,
+                    pc,
+                    instructions_retired,
+                };
+                (region.callbacks.on_leave)(&event);
+            }
+        }
+        for region in self.regions.iter_mut() {
+            if !region.inside && region.range.contains(&pc) {
+                region.inside = true;
+                let event = RegionEvent {
+                    name: &region.name,
+                    pc,
+                    instructions_retired,
+                };
+                (region.callbacks.on_enter)(&event);
+            }
+        }
+    }
+
+    fn emit_trace(&mut self, pc: EmulatorValue, instruction: Instruction) {
+        if self.trace_hook.is_none() {
+            return;
+        }
+        let event = TraceEvent {
+            pc,
+            instruction,
+            result: destination_register(instruction).map(|reg| (reg, self.get_reg(reg))),
+        };
+        if let Some(TraceHook(hook)) = &mut self.trace_hook {
+            hook(&event);
+        }
+    }
+
+    fn pc_label(&self, pc: EmulatorValue) -> PcLabel<'_> {
+        PcLabel {
+            pc,
+            symbol: self.symbol_at(pc),
+        }
+    }
+
+    fn check_register_watches(&mut self, reg: Register, val: EmulatorValue) {
+        let hit = self
+            .register_watches
+            .iter()
+            .any(|watch| watch.register == reg && (watch.predicate)(val));
+        if hit {
+            debug!("register watch hit: {:?}={:#x}", reg, val);
+            self.stop(StopReason::RegisterWatch {
+                register: reg,
+                value: val,
+                pc: self.program_counter,
+            });
+        }
+    }
+
+    fn check_memory_watches(&mut self, adr: EmulatorValue, size: u64) {
+        let hit = self.memory_watches.iter().find_map(|watch| {
+            let word_end = watch.address + riscu::WORD_SIZE as u64;
+            let overlaps = adr < word_end && watch.address < adr + size;
+            let value = word_or_zero(&self.memory, watch.address as usize);
+            (overlaps && (watch.predicate)(value)).then_some((watch.address, value))
+        });
+        if let Some((address, value)) = hit {
+            debug!("memory watch hit: mem[{:#x}]={:#x}", address, value);
+            self.stop(StopReason::MemoryWatch {
+                address,
+                value,
+                pc: self.program_counter,
+            });
+        }
+    }
+
+    fn write_snapshot(&self, path: &Path, note: &str) -> Result<(), EmulatorError> {
+        let mut file = io::BufWriter::new(File::create(path)?);
+        file.write_all(SNAPSHOT_MAGIC)?;
+        let header = [self.program_counter, self.program_break, self.heap_start];
+        for value in header.iter().chain(self.registers.iter()) {
+            file.write_all(&value.to_le_bytes())?;
+        }
+        file.write_all(&(self.memory.len() as u64).to_le_bytes())?;
+        file.write_all(&self.memory)?;
+        file.write_all(&(note.len() as u64).to_le_bytes())?;
+        file.write_all(note.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+
+    // Validates that there is code to run and that the program counter
+    // points into it, before anything is fetched from memory.
+    fn check_entry(&self) -> Result<(), EmulatorError> {
+        if self.code_range.is_empty() {
+            return Err(EmulatorError::EmptyProgram);
+        }
+        if !self.code_range.contains(&self.program_counter) {
+            return Err(EmulatorError::EntryOutOfText {
+                pc: self.program_counter,
+                code: self.code_range.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    fn start(&mut self) {
+        self.usage = ResourceUsage::default();
+        self.track_stack_peak(self.get_reg(Register::Sp));
+        self.slept_nanos = 0;
+        if self.config.calling_convention != CallingConventionCheck::Off
+            && self.convention.is_none()
+        {
+            self.convention = Some(ConventionChecker::new(self.program_counter));
+        }
+        self.retired = 0;
+        self.gas_used = 0;
+        self.storm_window = 0;
+        self.storm_syscalls = 0;
+        self.running = true;
+    }
+
+    // Execute a single instruction. Emulation stops in case of an error.
+    fn retire(&mut self) -> Result<RetiredInstruction, EmulatorError> {
+        let origin = self.program_counter;
+        self.stop_reason = None;
+        let fetched = fetch(self);
+        let decoded = decode(fetched);
+        if self.config.verify_decode && instruction_length(fetched as u16) == 4 {
+            let reencoded = encode(decoded).unwrap_or(0);
+            if reencoded != fetched {
+                self.running = false;
+                return Err(EmulatorError::DecodeMismatch {
+                    pc: origin,
+                    word: fetched,
+                    reencoded,
+                });
+            }
+        }
+        if self.config.reverse_depth > 0 {
+            self.recording = Some(StateDelta::new(origin, self.program_break));
+        }
+        self.observe_regions(origin);
+        self.tracing = self.trace_filter.matches(origin, decoded);
+        execute(self, decoded);
+        if self.tracing {
+            self.emit_trace(origin, decoded);
+        }
+        if let Some(checker) = &mut self.convention {
+            checker.observe_instruction(decoded);
+        }
+        if let Some(coverage) = &mut self.coverage {
+            if is_control_transfer(decoded) {
+                coverage.observe_block(self.program_counter);
+            }
+        }
+        if let Some(delta) = self.recording.take() {
+            if self.history.len() >= self.config.reverse_depth {
+                self.history.pop_front();
+            }
+            self.history.push_back(delta);
+        }
+        self.retired += 1;
+        if let Some(budget) = self.config.gas_budget {
+            self.gas_used += self.config.cost_model.cost(decoded);
+            if self.gas_used >= budget {
+                let gas_used = self.gas_used;
+                self.stop(StopReason::OutOfGas { gas_used });
+            }
+        }
+        if self.config.resource_accounting {
+            self.usage.instructions += 1;
+        }
+        if let Some(error) = self.fault.take() {
+            return Err(error);
+        }
+        if self.config.check_pc_in_text
+            && self.running
+            && !self.code_range.contains(&self.program_counter)
+            && !(self.callback_depth > 0 && self.program_counter == CALLBACK_RETURN)
+        {
+            self.running = false;
+            return Err(EmulatorError::PcOutOfText {
+                pc: self.program_counter,
+                origin,
+                instruction: decoded,
+            });
+        }
+        Ok(RetiredInstruction {
+            pc: origin,
+            instruction: decoded,
+        })
+    }
+
+    // Stops emulation after the current instruction, only the first of
+    // several reasons raised by one instruction is retained.
+    fn stop(&mut self, reason: StopReason) {
+        if self.stop_reason.is_none() {
+            self.stop_reason = Some(reason);
+        }
+        self.running = false;
+    }
+
+    // Aborts emulation from within an instruction, the given error is
+    // reported by `step` once the instruction completed.
+    fn fail(&mut self, error: EmulatorError) {
+        self.fault.get_or_insert(error);
+        self.running = false;
+    }
+
+    // Context of the instruction currently being executed, for errors
+    // raised while executing it (i.e. before the program counter moves).
+    fn instruction_context(&self) -> InstructionContext {
+        let instruction = decode(self.get_mem_unaligned::<u32>(self.program_counter));
+        let operands = source_registers(instruction)
+            .into_iter()
+            .map(|reg| (reg, self.get_reg(reg)))
+            .collect();
+        InstructionContext {
+            pc: self.program_counter,
+            instruction,
+            operands,
+        }
+    }
+
+    fn check_stack_pointer(&mut self, sp: EmulatorValue) {
+        if sp < self.program_break || sp > self.memory.len() as u64 {
+            let context = self.instruction_context();
+            self.fail(EmulatorError::InvalidStackPointer { value: sp, context });
+        }
+    }
+
+    // Counts a system call towards the window of the current instruction,
+    // see `SyscallRateLimit` for when the rate counts as excessive.
+    fn check_syscall_rate(&mut self, limit: SyscallRateLimit) {
+        let window = self.retired / limit.window.max(1);
+        if window != self.storm_window {
+            self.storm_window = window;
+            self.storm_syscalls = 0;
+        }
+        self.storm_syscalls += 1;
+        if self.storm_syscalls == limit.max_syscalls.saturating_add(1) {
+            warn!(
+                "system call storm: more than {} system calls within {} instructions at {:#x}",
+                limit.max_syscalls, limit.window, self.program_counter
+            );
+            if limit.stop {
+                self.stop(StopReason::SyscallStorm {
+                    syscalls: self.storm_syscalls,
+                    window: limit.window,
+                    pc: self.program_counter,
+                });
+            }
+        }
+    }
+
+    fn track_heap_peak(&mut self) {
+        let size = self.program_break.saturating_sub(self.break_base);
+        if size > self.usage.heap_peak {
+            self.usage.heap_peak = size;
+        }
+    }
+
+    fn track_stack_peak(&mut self, sp: EmulatorValue) {
+        let depth = self.stack_base.saturating_sub(sp);
+        if depth > self.usage.stack_peak {
+            self.usage.stack_peak = depth;
+        }
+    }
+
+    fn set_reg_maybe(&mut self, reg: Register, val: EmulatorValue) {
+        if reg == Register::Zero {
+            return;
+        };
+        self.set_reg(reg, val);
+    }
+
+    // TODO: Move to public portion of file.
+    pub fn get_mem(&self, adr: EmulatorValue) -> EmulatorValue {
+        assert!(adr & WORD_SIZE_MASK == 0, "address aligned");
+        LittleEndian::read_u64(&self.memory[adr as usize..])
+    }
+
+    fn get_mem_unaligned<T: MyLittleEndian>(&self, adr: EmulatorValue) -> T {
+        MyLittleEndian::read(&self.memory[adr as usize..])
+    }
+
+    // TODO: Move to public portion of file.
+    pub fn set_mem(&mut self, adr: EmulatorValue, val: EmulatorValue) {
+        assert!(adr & WORD_SIZE_MASK == 0, "address aligned");
+        self.record_mem(adr, size_of::<EmulatorValue>());
+        LittleEndian::write_u64(&mut self.memory[adr as usize..], val);
+    }
+
+    fn set_mem_typed<T: MyLittleEndian>(&mut self, adr: EmulatorValue, val: T) {
+        assert!(adr % (size_of::<T>() as u64) == 0, "address aligned");
+        self.set_mem_unaligned(adr, val);
+    }
+
+    fn set_mem_unaligned<T: MyLittleEndian>(&mut self, adr: EmulatorValue, val: T) {
+        self.record_mem(adr, size_of::<T>());
+        MyLittleEndian::write(&mut self.memory[adr as usize..], val);
+    }
+
+    fn record_mem(&mut self, adr: EmulatorValue, size: usize) {
+        if let Some(delta) = &mut self.recording {
+            let old = &self.memory[adr as usize..adr as usize + size];
+            delta.memory.push((adr, old.to_vec()));
+        }
+    }
+
+    // Data access performed by a load instruction on behalf of the guest.
+    // Returns `None` in case the access failed emulation, in which case the
+    // destination register is left untouched.
+    fn load<T: MyLittleEndian>(&mut self, adr: EmulatorValue) -> Option<T> {
+        if self.config.resource_accounting {
+            self.usage.memory_reads += 1;
+        }
+        self.observe_access::<T>(AccessKind::Load, adr);
+        if self.check_alignment::<T>(adr)
+            || self.check_injected_access(FaultSite::Load, adr)
+            || self.check_null_guard(adr)
+        {
+            return None;
+        }
+        Some(self.get_mem_unaligned::<T>(adr))
+    }
+
+    // Data access performed by a store instruction on behalf of the guest.
+    fn store<T: MyLittleEndian>(&mut self, adr: EmulatorValue, val: T) {
+        if self.config.resource_accounting {
+            self.usage.memory_writes += 1;
+        }
+        self.observe_access::<T>(AccessKind::Store, adr);
+        if self.check_alignment::<T>(adr)
+            || self.check_injected_access(FaultSite::Store, adr)
+            || self.check_null_guard(adr)
+            || self.check_read_only::<T>(adr)
+        {
+            return;
+        }
+        self.set_mem_unaligned::<T>(adr, val);
+        if !self.memory_watches.is_empty() {
+            self.check_memory_watches(adr, size_of::<T>() as u64);
+        }
+    }
+
+    fn observe_access<T>(&mut self, kind: AccessKind, adr: EmulatorValue) {
+        if self.access_hook.is_none() && self.cache.is_none() {
+            return;
+        }
+        let access = MemoryAccess {
+            pc: self.program_counter,
+            kind,
+            effective_address: adr,
+            size: size_of::<T>() as u64,
+            region: self.memory_region(adr),
+        };
+        if let Some(AttachedCache(cache)) = &mut self.cache {
+            cache.access(&access);
+        }
+        if let Some(AccessHook(hook)) = &mut self.access_hook {
+            hook(access);
+        }
+    }
+
+    // Fails emulation in case the address falls into the guard region,
+    // returns whether it did so the access can be suppressed.
+    fn check_null_guard(&mut self, adr: EmulatorValue) -> bool {
+        match self.config.null_guard_size {
+            Some(size) if adr < size => {
+                let context = self.instruction_context();
+                self.fail(EmulatorError::NullPointerAccess {
+                    address: adr,
+                    context,
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Counts an operation at the given site, returns whether a fault was
+    // scheduled for it (see `inject_fault`).
+    fn fault_injected(&mut self, site: FaultSite) -> Option<u64> {
+        if self.fault_schedule.is_empty() {
+            return None;
+        }
+        let count = self.fault_counts.entry(site).or_insert(0);
+        *count += 1;
+        let ordinal = *count;
+        self.fault_schedule
+            .contains(&(site, ordinal))
+            .then_some(ordinal)
+    }
+
+    // Fails emulation in case a fault was injected into the access,
+    // returns whether it did so the access can be suppressed.
+    fn check_injected_access(&mut self, site: FaultSite, adr: EmulatorValue) -> bool {
+        match self.fault_injected(site) {
+            Some(ordinal) => {
+                let context = self.instruction_context();
+                self.fail(EmulatorError::InjectedFault {
+                    site,
+                    ordinal,
+                    address: adr,
+                    context,
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Fails emulation in case the access is not naturally aligned and this
+    // is enabled in the configuration, returns whether it did so the
+    // access can be suppressed.
+    fn check_alignment<T>(&mut self, adr: EmulatorValue) -> bool {
+        let width = size_of::<T>() as u64;
+        if self.config.trap_unaligned_access && adr % width != 0 {
+            let context = self.instruction_context();
+            self.fail(EmulatorError::UnalignedAccess {
+                address: adr,
+                width,
+                context,
+            });
+            return true;
+        }
+        false
+    }
+
+    // Fails emulation in case the access overlaps read-only memory (e.g.
+    // a file-backed mapping), returns whether it did so the access can be
+    // suppressed.
+    fn check_read_only<T>(&mut self, adr: EmulatorValue) -> bool {
+        let end = adr + size_of::<T>() as u64;
+        if self
+            .read_only
+            .iter()
+            .any(|range| adr < range.end && range.start < end)
+        {
+            let context = self.instruction_context();
+            self.fail(EmulatorError::ReadOnlyWrite {
+                address: adr,
+                context,
+            });
+            return true;
+        }
+        false
+    }
+
+    // Fails emulation on overflowing signed division in case this is
+    // enabled in the configuration.
+    fn check_division_overflow(&mut self, overflow: bool) {
+        if overflow && self.config.trap_signed_division_overflow {
+            let context = self.instruction_context();
+            self.fail(EmulatorError::SignedDivisionOverflow { context });
+        }
+    }
+
+    fn copy_mem(&mut self, adr: EmulatorValue, src: &[u8]) {
+        self.memory[adr as usize..adr as usize + src.len()].copy_from_slice(src);
+    }
+
+    // Stores the given bytes at `adr`, recording them to be undone.
+    fn write_bytes(&mut self, adr: EmulatorValue, bytes: &[u8]) {
+        self.record_mem(adr, bytes.len());
+        self.copy_mem(adr, bytes);
+    }
+
+    // Checks that both segments fit into memory and that they do not
+    // overlap, which would otherwise silently clobber code with data.
+    fn validate_segments(
+        &self,
+        code: &ProgramSegment<u8>,
+        data: &[ProgramSegment<u8>],
+    ) -> Result<(), EmulatorError> {
+        let code = segment_range(code);
+        let data: Vec<_> = data.iter().map(segment_range).collect();
+        for segment in data.iter().chain(std::iter::once(&code)) {
+            if segment.end > self.memory.len() as u64 {
+                return Err(EmulatorError::SegmentOutOfBounds {
+                    address: segment.start,
+                    size: segment.end - segment.start,
+                });
+            }
+        }
+        let overlap = |a: &Range<EmulatorValue>, b: &Range<EmulatorValue>| {
+            !a.is_empty() && !b.is_empty() && a.start < b.end && b.start < a.end
+        };
+        for (index, segment) in data.iter().enumerate() {
+            if overlap(&code, segment) {
+                return Err(EmulatorError::SegmentOverlap {
+                    code,
+                    data: segment.clone(),
+                });
+            }
+            if let Some(other) = data[..index].iter().find(|other| overlap(other, segment)) {
+                return Err(EmulatorError::DataSegmentOverlap {
+                    first: other.clone(),
+                    second: segment.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn load_code_segment(&mut self, code: &ProgramSegment<u8>) {
+        self.code_range = segment_range(code);
+        self.copy_mem(code.address, &code.content);
+    }
+
+    // Segments are copied byte by byte, data addresses hence need not be
+    // word-aligned (e.g. for hand-crafted loaders packing segments).
+    fn load_data_segments(&mut self, data: &[ProgramSegment<u8>]) {
+        let ranges = data.iter().map(segment_range);
+        let start = ranges.clone().map(|range| range.start).min().unwrap_or(0);
+        let end = ranges.map(|range| range.end).max().unwrap_or(0);
+        self.data_range = start..end;
+        for segment in data {
+            self.copy_mem(segment.address, &segment.content);
+        }
+    }
+
+    // Pushes the arguments onto the stack, which is checked beforehand to
+    // not grow into the heap (starting at the program break) and below.
+    fn load_stack_segment<A: AsRef<[u8]>>(&mut self, argv: &[A]) -> Result<(), EmulatorError> {
+        debug!(
+            "argc: {}, argv: {:?}",
+            argv.len(),
+            argv.iter()
+                .map(|arg| String::from_utf8_lossy(arg.as_ref()))
+                .collect::<Vec<_>>()
+        );
+        let required = unix_stack_size(argv);
+        let available = self
+            .get_reg(Register::Sp)
+            .saturating_sub(self.program_break);
+        debug!(
+            "stack layout: {} bytes required, {} bytes available",
+            required, available
+        );
+        if required > available {
+            return Err(EmulatorError::StackSetupOverflow {
+                required,
+                available,
+            });
+        }
+        for val in prepare_unix_stack(argv, self.get_reg(Register::Sp)) {
+            let sp = self.get_reg(Register::Sp) - riscu::WORD_SIZE as u64;
+            self.set_reg(Register::Sp, sp);
+            self.set_mem(sp, val);
+        }
+        Ok(())
+    }
+
+    // TODO: Move to public portion of file.
+    pub fn get_program_counter(&self) -> EmulatorValue {
+        self.program_counter
+    }
+
+    // TODO: Move to public portion of file.
+    pub fn get_program_break(&self) -> EmulatorValue {
+        self.program_break
+    }
+
+    // TODO: Move to public portion of file.
+    pub fn set_program_break(&mut self, val: EmulatorValue) {
+        assert!(val & WORD_SIZE_MASK == 0, "program break aligned");
+        assert!(val >= self.heap_start, "above start of heap");
+        self.program_break = val;
+    }
+
+    // Allocates fresh pages above the program break for a mapping of the
+    // given `length` and fills them (see `syscall_mmap` for details).
+    fn mmap(
+        &mut self,
+        length: u64,
+        prot: u64,
+        flags: u64,
+        fd: EmulatorValue,
+        offset: u64,
+    ) -> Result<EmulatorValue, Errno> {
+        if self.fault_injected(FaultSite::Mmap).is_some() {
+            return Err(Errno::ENOMEM);
+        }
+        let page_size = PAGE_SIZE as u64;
+        if length == 0 || offset % page_size != 0 || flags & MAP_FIXED != 0 {
+            return Err(Errno::EINVAL);
+        }
+        let contents = if flags & MAP_ANONYMOUS != 0 {
+            Vec::new()
+        } else {
+            let file = match self.fd_get(fd)? {
+                Descriptor::File(file) => file,
+                _ => return Err(Errno::ENODEV),
+            };
+            if prot & PROT_WRITE != 0 {
+                return Err(Errno::EACCES);
+            }
+            read_file_region(file, offset, length).map_err(|e| errno_from_io(&e))?
+        };
+        let start = next_multiple_of(self.program_break, page_size);
+        let end = match length.checked_add(page_size - 1) {
+            Some(size) => start + size / page_size * page_size,
+            None => return Err(Errno::ENOMEM),
+        };
+        if end >= self.get_reg(Register::Sp) {
+            return Err(Errno::ENOMEM);
+        }
+        self.memory[start as usize..end as usize].fill(0);
+        self.copy_mem(start, &contents);
+        if flags & MAP_ANONYMOUS == 0 {
+            self.read_only.push(start..end);
+        }
+        self.heap_start = end;
+        self.program_break = end;
+        Ok(start)
+    }
+
+    // Allocates the lowest unused descriptor number, like UNIX does.
+    fn fd_new(&mut self, descriptor: Descriptor) -> EmulatorValue {
+        let fd = match self.descriptors.iter().position(|d| d.is_none()) {
+            Some(fd) => fd,
+            None => {
+                self.descriptors.push(None);
+                self.descriptors.len() - 1
+            }
+        };
+        self.descriptors[fd] = Some(descriptor);
+        fd as EmulatorValue
+    }
+
+    // Makes `new_fd` refer to the same open description as `old_fd`,
+    // closing whatever `new_fd` referred to before.
+    fn fd_dup_to(&mut self, old_fd: EmulatorValue, new_fd: EmulatorValue) -> Result<(), Errno> {
+        let duplicate = self
+            .fd_get(old_fd)?
+            .try_clone()
+            .map_err(|e| errno_from_io(&e))?;
+        if new_fd as usize >= MAX_DESCRIPTORS {
+            return Err(Errno::EBADF);
+        }
+        if new_fd as usize >= self.descriptors.len() {
+            self.descriptors.resize_with(new_fd as usize + 1, || None);
+        }
+        self.descriptors[new_fd as usize] = Some(duplicate);
+        Ok(())
+    }
+
+    fn fd_get(&mut self, fd: EmulatorValue) -> Result<&mut Descriptor, Errno> {
+        match self.descriptors.get_mut(fd as usize) {
+            Some(Some(descriptor)) => Ok(descriptor),
+            _ => Err(Errno::EBADF),
+        }
+    }
+
+    fn fd_close(&mut self, fd: EmulatorValue) -> Result<(), Errno> {
+        self.fd_get(fd)?;
+        self.descriptors[fd as usize] = None;
+        Ok(())
+    }
+
+    fn fd_read(&mut self, fd: EmulatorValue) -> Result<&mut dyn Read, Errno> {
+        match self.descriptors.get_mut(fd as usize) {
+            Some(Some(Descriptor::Stdin | Descriptor::File(_))) if self.config.dry_run => {
+                Ok(&mut self.dry_input)
+            }
+            Some(Some(Descriptor::Stdin)) => match &mut self.script {
+                Some(script) => Ok(script),
+                None => Ok(&mut *self.stdio.stdin),
+            },
+            Some(Some(Descriptor::File(file))) => Ok(file),
+            Some(Some(Descriptor::Device(device))) => Ok(device),
+            Some(Some(Descriptor::Pipe(end))) if !end.write => {
+                if end.nonblocking && end.is_starved() {
+                    return Err(Errno::EAGAIN);
+                }
+                Ok(end)
+            }
+            _ => Err(Errno::EBADF),
+        }
+    }
+
+    // Whether reading from the descriptor has to block, which is the
+    // case while scripted standard input is not yet available or while
+    // a pipe is empty but still open for writing. Note that the guest is
+    // single-threaded, a pipe whose write ends are all held by the guest
+    // itself can hence never be filled while the guest blocks. Emulation
+    // fails with `PipeDeadlock` in this case instead of retrying forever.
+    fn input_blocks(&mut self, fd: EmulatorValue) -> bool {
+        let deadlocked = match (&mut self.script, self.descriptors.get(fd as usize)) {
+            (Some(script), Some(Some(Descriptor::Stdin))) => return !script.advance(self.retired),
+            (_, Some(Some(Descriptor::Pipe(end)))) => {
+                if end.write || end.nonblocking || !end.is_starved() {
+                    return false;
+                }
+                let writers_held = self
+                    .descriptors
+                    .iter()
+                    .filter(|descriptor| match descriptor {
+                        Some(Descriptor::Pipe(other)) => other.write && other.same_pipe(end),
+                        _ => false,
+                    })
+                    .count();
+                writers_held == end.writers()
+            }
+            _ => return false,
+        };
+        if deadlocked {
+            let context = self.instruction_context();
+            self.fail(EmulatorError::PipeDeadlock { fd, context });
+        }
+        true
+    }
+
+    fn fd_write(&mut self, fd: EmulatorValue) -> Result<&mut dyn Write, Errno> {
+        match self.descriptors.get_mut(fd as usize) {
+            Some(Some(Descriptor::Stdout | Descriptor::Stderr | Descriptor::File(_)))
+                if self.config.dry_run =>
+            {
+                Ok(&mut self.dry_output)
+            }
+            Some(Some(Descriptor::Stdout)) => Ok(&mut *self.stdio.stdout),
+            Some(Some(Descriptor::Stderr)) => Ok(&mut *self.stdio.stderr),
+            Some(Some(Descriptor::File(file))) => Ok(file),
+            Some(Some(Descriptor::Device(device))) => Ok(device),
+            Some(Some(Descriptor::Pipe(end))) if end.write => {
+                if end.is_broken() {
+                    return Err(Errno::EPIPE);
+                }
+                Ok(end)
+            }
+            _ => Err(Errno::EBADF),
+        }
+    }
+}
+
+// Values overwritten by one instruction, allowing it to be undone.
+#[derive(Debug)]
+struct StateDelta {
+    program_counter: EmulatorValue,
+    program_break: EmulatorValue,
+    registers: Vec<(usize, EmulatorValue)>,
+    memory: Vec<(EmulatorValue, Vec<u8>)>,
+}
+
+impl StateDelta {
+    fn new(program_counter: EmulatorValue, program_break: EmulatorValue) -> Self {
+        Self {
+            program_counter,
+            program_break,
+            registers: Vec::new(),
+            memory: Vec::new(),
+        }
+    }
+}
+
+struct RegisterWatch {
+    register: Register,
+    predicate: Box<dyn Fn(EmulatorValue) -> bool + Send>,
+}
+
+impl fmt::Debug for RegisterWatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RegisterWatch({:?})", self.register)
+    }
+}
+
+struct MemoryWatch {
+    address: EmulatorValue,
+    predicate: Box<dyn Fn(EmulatorValue) -> bool + Send>,
+}
+
+impl fmt::Debug for MemoryWatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MemoryWatch({:#x})", self.address)
+    }
+}
+
+struct WitnessHook(Box<dyn FnMut(WitnessEvent) + Send>);
+
+impl fmt::Debug for WitnessHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WitnessHook")
+    }
+}
+
+struct SyscallHook(Box<dyn FnMut(&mut EmulatorState) + Send>);
+
+impl fmt::Debug for SyscallHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SyscallHook")
+    }
+}
+
+struct BreakHook(Box<dyn FnMut(EmulatorValue, EmulatorValue) + Send>);
+
+impl fmt::Debug for BreakHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BreakHook")
+    }
+}
+
+// Address range registered with `register_region`, remembering whether
+// execution is currently inside of it.
+#[derive(Debug)]
+struct Region {
+    name: String,
+    range: Range<EmulatorValue>,
+    callbacks: RegionCallbacks,
+    inside: bool,
+}
+
+struct TraceHook(Box<dyn FnMut(&TraceEvent) + Send>);
+
+impl fmt::Debug for TraceHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TraceHook")
+    }
+}
+
+struct AccessHook(Box<dyn FnMut(MemoryAccess) + Send>);
+
+impl fmt::Debug for AccessHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AccessHook")
+    }
+}
+
+struct AttachedCache(Box<dyn CacheModel>);
+
+impl fmt::Debug for AttachedCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AttachedCache")
+    }
+}
+
+// Address formatted together with its symbol (if any) for traces.
+struct PcLabel<'a> {
+    pc: EmulatorValue,
+    symbol: Option<(&'a str, EmulatorValue)>,
+}
+
+impl fmt::Display for PcLabel<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.symbol {
+            Some((name, 0)) => write!(f, "{:#x} <{}>", self.pc, name),
+            Some((name, offset)) => write!(f, "{:#x} <{}+{:#x}>", self.pc, name, offset),
+            None => write!(f, "{:#x}", self.pc),
+        }
+    }
+}
+
+struct HostStdio {
+    stdin: Box<dyn Read + Send>,
+    stdout: Box<dyn Write + Send>,
+    stderr: Box<dyn Write + Send>,
+}
+
+impl fmt::Debug for HostStdio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HostStdio")
+    }
+}
+
+// In-memory output stream that can be read back by the embedder while
+// the emulator holds on to a clone of it.
+#[derive(Clone, Debug, Default)]
+struct CapturedOutput(Arc<Mutex<Vec<u8>>>);
+
+impl CapturedOutput {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().expect("buffer not poisoned"))
+    }
+}
+
+impl Write for CapturedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().expect("buffer not poisoned").write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+type SharedLog = Arc<Mutex<Box<dyn Write + Send>>>;
+
+// Output stream duplicating all bytes accepted by `sink` into `log`.
+struct TeeOutput {
+    sink: Box<dyn Write + Send>,
+    log: SharedLog,
+}
+
+impl Write for TeeOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.sink.write(buf)?;
+        self.log
+            .lock()
+            .expect("log not poisoned")
+            .write_all(&buf[..written])?;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.sink.flush()?;
+        self.log.lock().expect("log not poisoned").flush()
+    }
+}
+
+// Entries of the descriptor table, indexed by descriptor number. The
+// standard streams refer to the respective streams of the host.
+#[derive(Debug)]
+enum Descriptor {
+    Stdin,
+    Stdout,
+    Stderr,
+    File(File),
+    Pipe(PipeEnd),
+    Device(VirtualDevice),
+}
+
+fn word_or_zero(memory: &[u8], adr: usize) -> EmulatorValue {
+    let mut bytes = [0; riscu::WORD_SIZE];
+    if let Some(word) = memory.get(adr..) {
+        let len = min(word.len(), riscu::WORD_SIZE);
+        bytes[..len].copy_from_slice(&word[..len]);
+    }
+    EmulatorValue::from_le_bytes(bytes)
+}
+
+// Splits raw code bytes at `address` into instruction words together
+// with their address, zero-padding a trailing partial word.
+fn code_words(
+    address: EmulatorValue,
+    code: &[u8],
+) -> impl Iterator<Item = (EmulatorValue, u32)> + '_ {
+    (address..)
+        .step_by(size_of::<u32>())
+        .zip(code.chunks(size_of::<u32>()))
+        .map(|(address, chunk)| {
+            let mut bytes = [0; size_of::<u32>()];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            (address, u32::from_le_bytes(bytes))
+        })
+}
+
+fn segment_range(segment: &ProgramSegment<u8>) -> Range<EmulatorValue> {
+    segment.address..segment.address + segment.content.len() as u64
+}
+
+impl Descriptor {
+    // Duplicates the descriptor, files share their offset with the copy.
+    // Note that a duplicated `/dev/urandom` continues independently.
+    fn try_clone(&self) -> io::Result<Descriptor> {
+        Ok(match self {
+            Descriptor::Stdin => Descriptor::Stdin,
+            Descriptor::Stdout => Descriptor::Stdout,
+            Descriptor::Stderr => Descriptor::Stderr,
+            Descriptor::File(file) => Descriptor::File(file.try_clone()?),
+            Descriptor::Pipe(end) => Descriptor::Pipe(end.duplicate()),
+            Descriptor::Device(device) => Descriptor::Device(device.clone()),
+        })
+    }
+}
+
+// Encodes a failing system call result as the negated error number.
+fn syscall_error(errno: Errno) -> EmulatorValue {
+    -(errno as i64) as EmulatorValue
+}
+
+// Reads up to `length` bytes of the `file` starting at `offset`, leaving
+// the current position of the file untouched (just like `mmap` does).
+fn read_file_region(file: &mut File, offset: u64, length: u64) -> io::Result<Vec<u8>> {
+    let position = file.stream_position()?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut contents = Vec::new();
+    let result = (&*file).take(length).read_to_end(&mut contents);
+    file.seek(SeekFrom::Start(position))?;
+    result.map(|_| contents)
+}
+
+// Next value of the pseudo-random generator, which is SplitMix64 as it
+// is tiny and its whole state is the seed advanced so far.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Renders a count with a metric suffix and one decimal, e.g. "42.1M".
+fn compact_count(count: u64) -> String {
+    const SUFFIXES: [(u64, &str); 3] = [(1_000_000_000, "G"), (1_000_000, "M"), (1_000, "K")];
+    match SUFFIXES.iter().find(|(unit, _)| count >= *unit) {
+        Some((unit, suffix)) => format!("{:.1}{}", count as f64 / *unit as f64, suffix),
+        None => count.to_string(),
+    }
+}
+
+// Renders a size in bytes with a binary suffix, e.g. "8KiB" or "1.5MiB".
+fn compact_size(size: u64) -> String {
+    const SUFFIXES: [(u64, &str); 3] = [(1 << 30, "GiB"), (1 << 20, "MiB"), (1 << 10, "KiB")];
+    match SUFFIXES.iter().find(|(unit, _)| size >= *unit) {
+        Some((unit, suffix)) if size % unit == 0 => format!("{}{}", size / unit, suffix),
+        Some((unit, suffix)) => format!("{:.1}{}", size as f64 / *unit as f64, suffix),
+        None => format!("{}B", size),
+    }
+}
+
+// Quotes the given string as a JSON string literal.
+#[cfg(feature = "serde")]
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn errno_from_io(error: &io::Error) -> Errno {
+    match error.kind() {
+        ErrorKind::NotFound => Errno::ENOENT,
+        ErrorKind::PermissionDenied => Errno::EACCES,
+        ErrorKind::AlreadyExists => Errno::EEXIST,
+        ErrorKind::InvalidInput => Errno::EINVAL,
+        ErrorKind::BrokenPipe => Errno::EPIPE,
+        _ if cfg!(unix) && error.raw_os_error() == Some(Errno::EISDIR as i32) => Errno::EISDIR,
+        _ => Errno::EIO,
+    }
+}
+
+// Assertions on the hot path of the interpreter loop come in two flavors:
+// Preconditions that guests can violate (e.g. an unaligned program
+// counter) are checked with `assert!` in all builds. Writes to the `zero`
+// register are legal for guests and discarded (see `set_reg_maybe`),
+// `set_reg` only guards against such writes by the host.
+// Invariants already established by earlier validation, or redundant with
+// the checks Rust performs on its own, use `debug_assert!` and are hence
+// skipped in release builds. Correctness-focused users can keep them by
+// enabling `debug-assertions` in their build profile.
+
+fn fetch(state: &mut EmulatorState) -> u32 {
+    // Guaranteed by `pc_set` and branch/jump offsets always being even.
+    debug_assert!(state.program_counter & INSTRUCTION_SIZE_MASK == 0);
+    state.get_mem_unaligned::<u32>(state.program_counter)
+}
+
+fn decode(instruction_half_word: u32) -> Instruction {
+    riscu::decode(instruction_half_word).expect("valid instruction")
+}
+
+// Mnemonic of the given instruction in case it is covered by `execute`.
+fn mnemonic(instr: Instruction) -> Option<&'static str> {
+    instruction_kind(instr).map(InstructionKind::mnemonic)
+}
+
+fn encode_rtype(opcode: u32, funct3: u32, funct7: u32, rtype: RType) -> u32 {
+    let rs2 = rtype.rs2() as u32;
+    let rs1 = rtype.rs1() as u32;
+    let rd = rtype.rd() as u32;
+    funct7 << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode
+}
+
+fn encode_itype(opcode: u32, funct3: u32, itype: IType) -> u32 {
+    let imm = itype.imm() as u32 & 0xfff;
+    let rs1 = itype.rs1() as u32;
+    let rd = itype.rd() as u32;
+    imm << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode
+}
+
+fn encode_stype(opcode: u32, funct3: u32, stype: SType) -> u32 {
+    let imm = stype.imm() as u32;
+    let rs2 = stype.rs2() as u32;
+    let rs1 = stype.rs1() as u32;
+    (imm >> 5 & 0x7f) << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | (imm & 0x1f) << 7 | opcode
+}
+
+fn encode_btype(opcode: u32, funct3: u32, btype: BType) -> u32 {
+    let imm = btype.imm() as u32;
+    let rs2 = btype.rs2() as u32;
+    let rs1 = btype.rs1() as u32;
+    (imm >> 12 & 0x1) << 31
+        | (imm >> 5 & 0x3f) << 25
+        | rs2 << 20
+        | rs1 << 15
+        | funct3 << 12
+        | (imm >> 1 & 0xf) << 8
+        | (imm >> 11 & 0x1) << 7
+        | opcode
+}
+
+fn encode_utype(opcode: u32, utype: UType) -> u32 {
+    let rd = utype.rd() as u32;
+    (utype.imm() & 0xf_ffff) << 12 | rd << 7 | opcode
+}
+
+fn encode_jtype(opcode: u32, jtype: JType) -> u32 {
+    let imm = jtype.imm() as u32;
+    let rd = jtype.rd() as u32;
+    (imm >> 20 & 0x1) << 31
+        | (imm >> 1 & 0x3ff) << 21
+        | (imm >> 11 & 0x1) << 20
+        | (imm >> 12 & 0xff) << 12
+        | rd << 7
+        | opcode
+}
+
+// Both `ra` and the alternate link register `t0` are used for calls and
+// returns by the standard calling convention.
+fn is_link_register(register: Register) -> bool {
+    register == Register::Ra || register == Register::T0
+}
+
+// Jumps that link through `ra` or `t0` are calls.
+fn is_call(instr: Instruction) -> bool {
+    match instr {
+        Instruction::Jal(jtype) => is_link_register(jtype.rd()),
+        Instruction::Jalr(itype) => is_link_register(itype.rd()),
+        _ => false,
+    }
+}
+
+fn is_control_transfer(instr: Instruction) -> bool {
+    matches!(
+        instr,
+        Instruction::Jal(_)
+            | Instruction::Jalr(_)
+            | Instruction::Beq(_)
+            | Instruction::Bne(_)
+            | Instruction::Blt(_)
+            | Instruction::Bge(_)
+            | Instruction::Bltu(_)
+            | Instruction::Bgeu(_)
+    )
+}
+
+// Registers read by the given instruction as operands, in operand order.
+fn source_registers(instr: Instruction) -> Vec<Register> {
+    match instr {
+        Instruction::Beq(btype)
+        | Instruction::Bne(btype)
+        | Instruction::Blt(btype)
+        | Instruction::Bge(btype)
+        | Instruction::Bltu(btype)
+        | Instruction::Bgeu(btype) => vec![btype.rs1(), btype.rs2()],
+        Instruction::Jalr(itype)
+        | Instruction::Lb(itype)
+        | Instruction::Lh(itype)
+        | Instruction::Lw(itype)
+        | Instruction::Ld(itype)
+        | Instruction::Lbu(itype)
+        | Instruction::Lhu(itype)
+        | Instruction::Addi(itype)
+        | Instruction::Sltiu(itype)
+        | Instruction::Xori(itype)
+        | Instruction::Ori(itype)
+        | Instruction::Andi(itype)
+        | Instruction::Slli(itype)
+        | Instruction::Srli(itype)
+        | Instruction::Srai(itype)
+        | Instruction::Addiw(itype)
+        | Instruction::Slliw(itype)
+        | Instruction::Srliw(itype)
+        | Instruction::Sraiw(itype) => vec![itype.rs1()],
+        Instruction::Sb(stype)
+        | Instruction::Sh(stype)
+        | Instruction::Sw(stype)
+        | Instruction::Sd(stype) => vec![stype.rs1(), stype.rs2()],
+        Instruction::Add(rtype)
+        | Instruction::Sub(rtype)
+        | Instruction::Sll(rtype)
+        | Instruction::Slt(rtype)
+        | Instruction::Sltu(rtype)
+        | Instruction::Srl(rtype)
+        | Instruction::Sra(rtype)
+        | Instruction::Or(rtype)
+        | Instruction::And(rtype)
+        | Instruction::Mul(rtype)
+        | Instruction::Div(rtype)
+        | Instruction::Divu(rtype)
+        | Instruction::Rem(rtype)
+        | Instruction::Remu(rtype)
+        | Instruction::Addw(rtype)
+        | Instruction::Subw(rtype)
+        | Instruction::Sllw(rtype)
+        | Instruction::Mulw(rtype)
+        | Instruction::Divw(rtype)
+        | Instruction::Remw(rtype) => vec![rtype.rs1(), rtype.rs2()],
+        Instruction::Ecall(_) => vec![Register::A7, Register::A0, Register::A1, Register::A2],
+        _ => vec![],
+    }
+}
+
+// Register written by the given instruction (if any), including writes
+// to the `zero` register which are discarded.
+fn destination_register(instr: Instruction) -> Option<Register> {
+    match instr {
+        Instruction::Lui(utype) | Instruction::Auipc(utype) => Some(utype.rd()),
+        Instruction::Jal(jtype) => Some(jtype.rd()),
+        Instruction::Beq(_)
+        | Instruction::Bne(_)
+        | Instruction::Blt(_)
+        | Instruction::Bge(_)
+        | Instruction::Bltu(_)
+        | Instruction::Bgeu(_) => None,
+        Instruction::Sb(_) | Instruction::Sh(_) | Instruction::Sw(_) | Instruction::Sd(_) => None,
+        Instruction::Ecall(_) => None,
+        Instruction::Jalr(itype)
+        | Instruction::Lb(itype)
+        | Instruction::Lh(itype)
+        | Instruction::Lw(itype)
+        | Instruction::Ld(itype)
+        | Instruction::Lbu(itype)
+        | Instruction::Lhu(itype)
+        | Instruction::Addi(itype)
+        | Instruction::Sltiu(itype)
+        | Instruction::Xori(itype)
+        | Instruction::Ori(itype)
+        | Instruction::Andi(itype)
+        | Instruction::Slli(itype)
+        | Instruction::Srli(itype)
+        | Instruction::Srai(itype)
+        | Instruction::Addiw(itype)
+        | Instruction::Slliw(itype)
+        | Instruction::Srliw(itype)
+        | Instruction::Sraiw(itype) => Some(itype.rd()),
+        Instruction::Add(rtype)
+        | Instruction::Sub(rtype)
+        | Instruction::Sll(rtype)
+        | Instruction::Slt(rtype)
+        | Instruction::Sltu(rtype)
+        | Instruction::Srl(rtype)
+        | Instruction::Sra(rtype)
+        | Instruction::Or(rtype)
+        | Instruction::And(rtype)
+        | Instruction::Mul(rtype)
+        | Instruction::Div(rtype)
+        | Instruction::Divu(rtype)
+        | Instruction::Rem(rtype)
+        | Instruction::Remu(rtype)
+        | Instruction::Addw(rtype)
+        | Instruction::Subw(rtype)
+        | Instruction::Sllw(rtype)
+        | Instruction::Mulw(rtype)
+        | Instruction::Divw(rtype)
+        | Instruction::Remw(rtype) => Some(rtype.rd()),
+        // TODO: Cover all needed instructions here.
+        _ => None,
+    }
+}
+
+fn execute(state: &mut EmulatorState, instr: Instruction) {
+    match instr {
+        Instruction::Lui(utype) => exec_lui(state, utype),
+        Instruction::Auipc(utype) => exec_auipc(state, utype),
+        Instruction::Jal(jtype) => exec_jal(state, jtype),
+        Instruction::Jalr(itype) => exec_jalr(state, itype),
+        Instruction::Beq(btype) => exec_beq(state, btype),
+        Instruction::Bne(btype) => exec_bne(state, btype),
+        Instruction::Blt(btype) => exec_blt(state, btype),
+        Instruction::Bge(btype) => exec_bge(state, btype),
+        Instruction::Bltu(btype) => exec_bltu(state, btype),
+        Instruction::Bgeu(btype) => exec_bgeu(state, btype),
+        Instruction::Lb(itype) => exec_lb(state, itype),
+        Instruction::Lh(itype) => exec_lh(state, itype),
+        Instruction::Lw(itype) => exec_lw(state, itype),
+        Instruction::Ld(itype) => exec_ld(state, itype),
+        Instruction::Lbu(itype) => exec_lbu(state, itype),
+        Instruction::Lhu(itype) => exec_lhu(state, itype),
+        Instruction::Sb(stype) => exec_sb(state, stype),
+        Instruction::Sh(stype) => exec_sh(state, stype),
+        Instruction::Sw(stype) => exec_sw(state, stype),
+        Instruction::Sd(stype) => exec_sd(state, stype),
+        Instruction::Addi(itype) => exec_addi(state, itype),
+        Instruction::Sltiu(itype) => exec_sltiu(state, itype),
+        Instruction::Xori(itype) => exec_xori(state, itype),
+        Instruction::Ori(itype) => exec_ori(state, itype),
+        Instruction::Andi(itype) => exec_andi(state, itype),
+        Instruction::Slli(itype) => exec_slli(state, itype),
+        Instruction::Srli(itype) => exec_srli(state, itype),
+        Instruction::Srai(itype) => exec_srai(state, itype),
+        Instruction::Addiw(itype) => exec_addiw(state, itype),
+        Instruction::Slliw(itype) => exec_slliw(state, itype),
+        Instruction::Srliw(itype) => exec_srliw(state, itype),
+        Instruction::Sraiw(itype) => exec_sraiw(state, itype),
+        Instruction::Add(rtype) => exec_add(state, rtype),
+        Instruction::Sub(rtype) => exec_sub(state, rtype),
+        Instruction::Sll(rtype) => exec_sll(state, rtype),
+        Instruction::Slt(rtype) => exec_slt(state, rtype),
+        Instruction::Sltu(rtype) => exec_sltu(state, rtype),
+        Instruction::Srl(rtype) => exec_srl(state, rtype),
+        Instruction::Sra(rtype) => exec_sra(state, rtype),
+        Instruction::Or(rtype) => exec_or(state, rtype),
+        Instruction::And(rtype) => exec_and(state, rtype),
+        Instruction::Mul(rtype) => exec_mul(state, rtype),
+        Instruction::Div(rtype) => exec_div(state, rtype),
+        Instruction::Divu(rtype) => exec_divu(state, rtype),
+        Instruction::Rem(rtype) => exec_rem(state, rtype),
+        Instruction::Remu(rtype) => exec_remu(state, rtype),
+        Instruction::Addw(rtype) => exec_addw(state, rtype),
+        Instruction::Subw(rtype) => exec_subw(state, rtype),
+        Instruction::Sllw(rtype) => exec_sllw(state, rtype),
+        Instruction::Mulw(rtype) => exec_mulw(state, rtype),
+        Instruction::Divw(rtype) => exec_divw(state, rtype),
+        Instruction::Remw(rtype) => exec_remw(state, rtype),
+        Instruction::Ecall(_itype) => exec_ecall(state),
+        // TODO: Cover all needed instructions here.
+        _ => unimplemented!("not implemented: {:?}", instr),
+    }
+}
+
+//
+// RISC-V Instruction Semantics
+//
+// The following functions each codify the semantics of one RISC-V
+// instruction in terms of a given `EmulatorState`. Above each function
+// is a comment providing an informal description of the semantics. The
+// following conventions are used in these comments:
+//   - All registers and 'mem[x]' locations have 64 bit width.
+//   - Some operators are suffixed with 's' and 'u' to distinguish the
+//     difference between signed and unsigned variants, e.g. '<u', '<s'.
+//   - The bit width of operators is determined by their operands.
+//   - Any sign extension (sext) or zero extension (zext) denotes the
+//     target bit width explicitly, e.g. 's64(x)', 'z64(x)', 's32(x)'
+//   - Reduction of bit with is denoted in braces, e.g. rs1{32}, and
+//     indicates selecting the given number of least-significant bits,
+//     or used to make implicit bit widths explicit, e.g. imm{12}.
+//   - Memory access of smaller bit width uses, e.g. mem16[x], mem8[x].
+//   - Memory is always byte-addressed, independent of the bit width.
+//   - Division by zero does not trap, just like on RISC-V the quotient
+//     has all bits set and the remainder is the dividend.
+//
+
+// rd = s64(imm{20}) << 12
+// pc = pc + instruction_length
+fn exec_lui(state: &mut EmulatorState, utype: UType) {
+    let rd_value = ((utype.imm() as i32) << 12) as u64;
+    trace_utype(state, "lui", utype, rd_value);
+    state.set_reg_maybe(utype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = pc + s64(imm{20}) << 12
+// pc = pc + instruction_length
+fn exec_auipc(state: &mut EmulatorState, utype: UType) {
+    let rd_value = ((utype.imm() as i32) << 12) as u64 + state.program_counter;
+    trace_utype(state, "auipc", utype, rd_value);
+    state.set_reg_maybe(utype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = pc + instruction_length
+// pc = pc + s64(imm)
+fn exec_jal(state: &mut EmulatorState, jtype: JType) {
+    let rd_value = state.program_counter + (state.current_instruction_lenght() as u64);
+    trace_jtype(state, "jal", jtype, rd_value);
+    let origin = state.program_counter;
+    state.set_reg_maybe(jtype.rd(), rd_value);
+    state.pc_add(jtype.imm() as u64);
+    state.observe_jump(origin, Instruction::Jal(jtype), rd_value);
+}
+
+// rd = pc + instruction_length
+// pc = rs1 + s64(imm)
+fn exec_jalr(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let rd_value = state.program_counter + (state.current_instruction_lenght() as u64);
+    let pc_value = rs1_value.wrapping_add(itype.imm() as u64);
+    trace_itype(state, "jalr", itype, rd_value);
+    let origin = state.program_counter;
+    state.set_reg_maybe(itype.rd(), rd_value);
+    state.pc_set(pc_value);
+    state.observe_jump(origin, Instruction::Jalr(itype), rd_value);
+}
+
+// pc = pc + s64(imm)           ||| if (rs1 == rs2)
+// pc = pc + instruction_length ||| otherwise
+fn exec_beq(state: &mut EmulatorState, btype: BType) {
+    let rs1_value = state.get_reg(btype.rs1());
+    let rs2_value = state.get_reg(btype.rs2());
+    let condition = rs1_value == rs2_value;
+    trace_btype(state, "beq", btype, condition);
+    state.witness_branch(condition);
+    if condition {
+        state.pc_add(btype.imm() as u64);
+    } else {
+        state.pc_next();
+    }
+}
+
+// pc = pc + s64(imm)           ||| if (rs1 != rs2)
+// pc = pc + instruction_length ||| otherwise
+fn exec_bne(state: &mut EmulatorState, btype: BType) {
+    let rs1_value = state.get_reg(btype.rs1());
+    let rs2_value = state.get_reg(btype.rs2());
+    let condition = rs1_value != rs2_value;
+    trace_btype(state, "bne", btype, condition);
+    state.witness_branch(condition);
+    if condition {
+        state.pc_add(btype.imm() as u64);
+    } else {
+        state.pc_next();
+    }
+}
+
+// pc = pc + s64(imm)           ||| if (rs1 <s rs2)
+// pc = pc + instruction_length ||| otherwise
+fn exec_blt(state: &mut EmulatorState, btype: BType) {
+    let rs1_value = state.get_reg(btype.rs1());
+    let rs2_value = state.get_reg(btype.rs2());
+    let condition = (rs1_value as i64) < (rs2_value as i64);
+    trace_btype(state, "blt", btype, condition);
+    state.witness_branch(condition);
+    if condition {
+        state.pc_add(btype.imm() as u64);
+    } else {
+        state.pc_next();
+    }
+}
+
+// pc = pc + s64(imm)           ||| if (rs1 >=s rs2)
+// pc = pc + instruction_length ||| otherwise
+fn exec_bge(state: &mut EmulatorState, btype: BType) {
+    let rs1_value = state.get_reg(btype.rs1());
+    let rs2_value = state.get_reg(btype.rs2());
+    let condition = (rs1_value as i64) >= (rs2_value as i64);
+    trace_btype(state, "bge", btype, condition);
+    state.witness_branch(condition);
+    if condition {
+        state.pc_add(btype.imm() as u64);
+    } else {
+        state.pc_next();
+    }
+}
+
+// pc = pc + s64(imm)           ||| if (rs1 <u rs2)
+// pc = pc + instruction_length ||| otherwise
+fn exec_bltu(state: &mut EmulatorState, btype: BType) {
+    let rs1_value = state.get_reg(btype.rs1());
+    let rs2_value = state.get_reg(btype.rs2());
+    let condition = rs1_value < rs2_value;
+    trace_btype(state, "bltu", btype, condition);
+    state.witness_branch(condition);
+    if condition {
+        state.pc_add(btype.imm() as u64);
+    } else {
+        state.pc_next();
+    }
+}
+
+// pc = pc + s64(imm)           ||| if (rs1 >=u rs2)
+// pc = pc + instruction_length ||| otherwise
+fn exec_bgeu(state: &mut EmulatorState, btype: BType) {
+    let rs1_value = state.get_reg(btype.rs1());
+    let rs2_value = state.get_reg(btype.rs2());
+    let condition = rs1_value >= rs2_value;
+    trace_btype(state, "bgeu", btype, condition);
+    state.witness_branch(condition);
+    if condition {
+        state.pc_add(btype.imm() as u64);
+    } else {
+        state.pc_next();
+    }
+}
+
+// rd = s64(mem8[rs1 + s64(imm{12})])
+// pc = pc + instruction_length
+fn exec_lb(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let address = rs1_value.wrapping_add(itype.imm() as u64);
+    if let Some(value) = state.load::<i8>(address) {
+        let rd_value = value as u64;
+        trace_itype(state, "lb", itype, rd_value);
+        state.set_reg_maybe(itype.rd(), rd_value);
+    }
+    state.pc_next();
+}
+
+// rd = z64(mem8[rs1 + s64(imm{12})])
+// pc = pc + instruction_length
+fn exec_lbu(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let address = rs1_value.wrapping_add(itype.imm() as u64);
+    if let Some(value) = state.load::<u8>(address) {
+        let rd_value = value as u64;
+        trace_itype(state, "lbu", itype, rd_value);
+        state.set_reg_maybe(itype.rd(), rd_value);
+    }
+    state.pc_next();
+}
+
+// rd = s64(mem16[rs1 + s64(imm{12})])
+// pc = pc + instruction_length
+fn exec_lh(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let address = rs1_value.wrapping_add(itype.imm() as u64);
+    if let Some(value) = state.load::<i16>(address) {
+        let rd_value = value as u64;
+        trace_itype(state, "lh", itype, rd_value);
+        state.set_reg_maybe(itype.rd(), rd_value);
+    }
+    state.pc_next();
+}
+
+// rd = z64(mem16[rs1 + s64(imm{12})])
+// pc = pc + instruction_length
+fn exec_lhu(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let address = rs1_value.wrapping_add(itype.imm() as u64);
+    if let Some(value) = state.load::<u16>(address) {
+        let rd_value = value as u64;
+        trace_itype(state, "lhu", itype, rd_value);
+        state.set_reg_maybe(itype.rd(), rd_value);
+    }
+    state.pc_next();
+}
+
+// rd = s64(mem32[rs1 + s64(imm{12})])
+// pc = pc + instruction_length
+fn exec_lw(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let address = rs1_value.wrapping_add(itype.imm() as u64);
+    if let Some(value) = state.load::<i32>(address) {
+        let rd_value = value as u64;
+        trace_itype(state, "lw", itype, rd_value);
+        state.set_reg_maybe(itype.rd(), rd_value);
+    }
+    state.pc_next();
+}
+
+// rd = mem[rs1 + s64(imm{12})]
+// pc = pc + instruction_length
+fn exec_ld(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let address = rs1_value.wrapping_add(itype.imm() as u64);
+    if let Some(value) = state.load::<u64>(address) {
+        let rd_value = value;
+        trace_itype(state, "ld", itype, rd_value);
+        state.set_reg_maybe(itype.rd(), rd_value);
+    }
+    state.pc_next();
+}
+
+// mem8[rs1 + s64(imm{12})] = rs2{8}
+// pc = pc + instruction_length
+fn exec_sb(state: &mut EmulatorState, stype: SType) {
+    let rs1_value = state.get_reg(stype.rs1());
+    let rs2_value = state.get_reg(stype.rs2());
+    let address = rs1_value.wrapping_add(stype.imm() as u64);
+    trace_stype(state, "sb", stype, address);
+    state.store::<u8>(address, rs2_value as u8);
+    state.pc_next();
+}
+
+// mem16[rs1 + s64(imm{12})] = rs2{16}
+// pc = pc + instruction_length
+fn exec_sh(state: &mut EmulatorState, stype: SType) {
+    let rs1_value = state.get_reg(stype.rs1());
+    let rs2_value = state.get_reg(stype.rs2());
+    let address = rs1_value.wrapping_add(stype.imm() as u64);
+    trace_stype(state, "sh", stype, address);
+    state.store::<u16>(address, rs2_value as u16);
+    state.pc_next();
+}
+
+// mem32[rs1 + s64(imm{12})] = rs2{32}
+// pc = pc + instruction_length
+fn exec_sw(state: &mut EmulatorState, stype: SType) {
+    let rs1_value = state.get_reg(stype.rs1());
+    let rs2_value = state.get_reg(stype.rs2());
+    let address = rs1_value.wrapping_add(stype.imm() as u64);
+    trace_stype(state, "sw", stype, address);
+    state.store::<u32>(address, rs2_value as u32);
+    state.pc_next();
+}
+
+// mem[rs1 + s64(imm{12})] = rs2
+// pc = pc + instruction_length
+fn exec_sd(state: &mut EmulatorState, stype: SType) {
+    let rs1_value = state.get_reg(stype.rs1());
+    let rs2_value = state.get_reg(stype.rs2());
+    let address = rs1_value.wrapping_add(stype.imm() as u64);
+    trace_stype(state, "sd", stype, address);
+    state.store::<u64>(address, rs2_value);
+    state.pc_next();
+}
+
+// rd = rs1 + s64(imm{12})
+// pc = pc + instruction_length
+fn exec_addi(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let rd_value = alu_addi(rs1_value, itype.imm() as i64);
+    trace_itype(state, "addi", itype, rd_value);
+    state.set_reg_maybe(itype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = s64(rs1{32} + s32(imm{12}))
+// pc = pc + instruction_length
+fn exec_addiw(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let rd_value = alu_addiw(rs1_value, itype.imm() as i64);
+    trace_itype(state, "addiw", itype, rd_value);
+    state.set_reg_maybe(itype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = 1                     ||| if (rs1 <u s64(imm{12}))
+// rd = 0                     ||| otherwise
+// pc = pc + instruction_length
+fn exec_sltiu(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let rd_value = alu_sltiu(rs1_value, itype.imm() as i64);
+    trace_itype(state, "sltiu", itype, rd_value);
+    state.set_reg_maybe(itype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 ^ s64(imm{12})
+// pc = pc + instruction_length
+fn exec_xori(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let rd_value = alu_xori(rs1_value, itype.imm() as i64);
+    trace_itype(state, "xori", itype, rd_value);
+    state.set_reg_maybe(itype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 | s64(imm{12})
+// pc = pc + instruction_length
+fn exec_ori(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let rd_value = alu_ori(rs1_value, itype.imm() as i64);
+    trace_itype(state, "ori", itype, rd_value);
+    state.set_reg_maybe(itype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 & s64(imm{12})
+// pc = pc + instruction_length
+fn exec_andi(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let rd_value = alu_andi(rs1_value, itype.imm() as i64);
+    trace_itype(state, "andi", itype, rd_value);
+    state.set_reg_maybe(itype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 << z32(imm{6})
+// pc = pc + instruction_length
+fn exec_slli(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let rd_value = alu_slli(rs1_value, itype.imm() as i64);
+    trace_itype(state, "slli", itype, rd_value);
+    state.set_reg_maybe(itype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = s64(rs1{32} << z32(imm{5}))
+// pc = pc + instruction_length
+fn exec_slliw(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let rd_value = alu_slliw(rs1_value, itype.imm() as i64);
+    trace_itype(state, "slliw", itype, rd_value);
+    state.set_reg_maybe(itype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 >>u z32(imm{6})
+// pc = pc + instruction_length
+fn exec_srli(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let rd_value = alu_srli(rs1_value, itype.imm() as i64);
+    trace_itype(state, "srli", itype, rd_value);
+    state.set_reg_maybe(itype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = s64(rs1{32} >>u z32(imm{5}))
+// pc = pc + instruction_length
+fn exec_srliw(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let rd_value = alu_srliw(rs1_value, itype.imm() as i64);
+    trace_itype(state, "srliw", itype, rd_value);
+    state.set_reg_maybe(itype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 >>s z32(imm{6})
+// pc = pc + instruction_length
+fn exec_srai(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let rd_value = alu_srai(rs1_value, itype.imm() as i64);
+    trace_itype(state, "srai", itype, rd_value);
+    state.set_reg_maybe(itype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = s64(rs1{32} >>s z32(imm{5}))
+// pc = pc + instruction_length
+fn exec_sraiw(state: &mut EmulatorState, itype: IType) {
+    let rs1_value = state.get_reg(itype.rs1());
+    let rd_value = alu_sraiw(rs1_value, itype.imm() as i64);
+    trace_itype(state, "sraiw", itype, rd_value);
+    state.set_reg_maybe(itype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 + rs2
+// pc = pc + instruction_length
+fn exec_add(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_add(rs1_value, rs2_value);
+    trace_rtype(state, "add", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = s64(rs1{32} + rs2{32})
+// pc = pc + instruction_length
+fn exec_addw(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_addw(rs1_value, rs2_value);
+    trace_rtype(state, "addw", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 - rs2
+// pc = pc + instruction_length
+fn exec_sub(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_sub(rs1_value, rs2_value);
+    trace_rtype(state, "sub", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = s64(rs1{32} - rs2{32})
+// pc = pc + instruction_length
+fn exec_subw(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_subw(rs1_value, rs2_value);
+    trace_rtype(state, "subw", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 << z32(rs2{6})
+// pc = pc + instruction_length
+fn exec_sll(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_sll(rs1_value, rs2_value);
+    trace_rtype(state, "sll", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = s64(rs1{32} << z32(rs2{5}))
+// pc = pc + instruction_length
+fn exec_sllw(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_sllw(rs1_value, rs2_value);
+    trace_rtype(state, "sllw", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 >>u z32(rs2{6})
+// pc = pc + instruction_length
+fn exec_srl(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_srl(rs1_value, rs2_value);
+    trace_rtype(state, "srl", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 >>s z32(rs2{6})
+// pc = pc + instruction_length
+fn exec_sra(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_sra(rs1_value, rs2_value);
+    trace_rtype(state, "sra", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = 1                     ||| if (rs1 <s rs2)
+// rd = 0                     ||| otherwise
+// pc = pc + 4
+fn exec_slt(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_slt(rs1_value, rs2_value);
+    trace_rtype(state, "slt", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = 1                     ||| if (rs1 <u rs2)
+// rd = 0                     ||| otherwise
+// pc = pc + instruction_length
+fn exec_sltu(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_sltu(rs1_value, rs2_value);
+    trace_rtype(state, "sltu", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 | rs2
+// pc = pc + instruction_length
+fn exec_or(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_or(rs1_value, rs2_value);
+    trace_rtype(state, "or", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 & rs2
+// pc = pc + instruction_length
+fn exec_and(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_and(rs1_value, rs2_value);
+    trace_rtype(state, "and", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 * rs2
+// pc = pc + instruction_length
+fn exec_mul(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_mul(rs1_value, rs2_value);
+    trace_rtype(state, "mul", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = s64(rs1{32} * rs2{32})
+// pc = pc + instruction_length
+fn exec_mulw(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_mulw(rs1_value, rs2_value);
+    trace_rtype(state, "mulw", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 /s rs2
+// pc = pc + instruction_length
+fn exec_div(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let overflow = rs1_value as i64 == i64::MIN && rs2_value as i64 == -1;
+    let rd_value = alu_div(rs1_value, rs2_value);
+    trace_rtype(state, "div", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.check_division_overflow(overflow);
+    state.pc_next();
+}
+
+// rd = s64(rs1{32} /s rs2{32})
+// pc = pc + 4
+fn exec_divw(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let overflow = rs1_value as i32 == i32::MIN && rs2_value as i32 == -1;
+    let rd_value = alu_divw(rs1_value, rs2_value);
+    trace_rtype(state, "divw", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.check_division_overflow(overflow);
+    state.pc_next();
+}
+
+// rd = rs1 /u rs2
+// pc = pc + instruction_length
+fn exec_divu(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_divu(rs1_value, rs2_value);
+    trace_rtype(state, "divu", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// rd = rs1 %s rs2
+// pc = pc + 4
+fn exec_rem(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let overflow = rs1_value as i64 == i64::MIN && rs2_value as i64 == -1;
+    let rd_value = alu_rem(rs1_value, rs2_value);
+    trace_rtype(state, "rem", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.check_division_overflow(overflow);
+    state.pc_next();
+}
+
+// rd = s64(rs1{32} %s rs2{32})
+// pc = pc + 4
+fn exec_remw(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let overflow = rs1_value as i32 == i32::MIN && rs2_value as i32 == -1;
+    let rd_value = alu_remw(rs1_value, rs2_value);
+    trace_rtype(state, "remw", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.check_division_overflow(overflow);
+    state.pc_next();
+}
+
+// rd = rs1 %u rs2
+// pc = pc + instruction_length
+fn exec_remu(state: &mut EmulatorState, rtype: RType) {
+    let rs1_value = state.get_reg(rtype.rs1());
+    let rs2_value = state.get_reg(rtype.rs2());
+    let rd_value = alu_remu(rs1_value, rs2_value);
+    trace_rtype(state, "remu", rtype, rd_value);
+    state.set_reg_maybe(rtype.rd(), rd_value);
+    state.pc_next();
+}
+
+// Pure semantics of all arithmetic and logic instructions, as used by the
+// `exec_*` functions above, indexed by mnemonic.
+const ALU_OPERATIONS: &[(&str, AluOperation)] = &[
+    ("addi", AluOperation::Immediate(alu_addi)),
+    ("addiw", AluOperation::Immediate(alu_addiw)),
+    ("sltiu", AluOperation::Immediate(alu_sltiu)),
+    ("xori", AluOperation::Immediate(alu_xori)),
+    ("ori", AluOperation::Immediate(alu_ori)),
+    ("andi", AluOperation::Immediate(alu_andi)),
+    ("slli", AluOperation::Immediate(alu_slli)),
+    ("slliw", AluOperation::Immediate(alu_slliw)),
+    ("srli", AluOperation::Immediate(alu_srli)),
+    ("srliw", AluOperation::Immediate(alu_srliw)),
+    ("srai", AluOperation::Immediate(alu_srai)),
+    ("sraiw", AluOperation::Immediate(alu_sraiw)),
+    ("add", AluOperation::Register(alu_add)),
+    ("addw", AluOperation::Register(alu_addw)),
+    ("sub", AluOperation::Register(alu_sub)),
+    ("subw", AluOperation::Register(alu_subw)),
+    ("sll", AluOperation::Register(alu_sll)),
+    ("sllw", AluOperation::Register(alu_sllw)),
+    ("srl", AluOperation::Register(alu_srl)),
+    ("sra", AluOperation::Register(alu_sra)),
+    ("slt", AluOperation::Register(alu_slt)),
+    ("sltu", AluOperation::Register(alu_sltu)),
+    ("or", AluOperation::Register(alu_or)),
+    ("and", AluOperation::Register(alu_and)),
+    ("mul", AluOperation::Register(alu_mul)),
+    ("mulw", AluOperation::Register(alu_mulw)),
+    ("div", AluOperation::Register(alu_div)),
+    ("divw", AluOperation::Register(alu_divw)),
+    ("divu", AluOperation::Register(alu_divu)),
+    ("rem", AluOperation::Register(alu_rem)),
+    ("remw", AluOperation::Register(alu_remw)),
+    ("remu", AluOperation::Register(alu_remu)),
+];
+
+fn alu_addi(rs1: u64, imm: i64) -> u64 {
+    rs1.wrapping_add(imm as u64)
+}
+
+fn alu_addiw(rs1: u64, imm: i64) -> u64 {
+    (rs1 as i32).wrapping_add(imm as i32) as u64
+}
+
+fn alu_sltiu(rs1: u64, imm: i64) -> u64 {
+    EmulatorValue::from(rs1 < (imm as u64))
+}
+
+fn alu_xori(rs1: u64, imm: i64) -> u64 {
+    rs1 ^ (imm as u64)
+}
+
+fn alu_ori(rs1: u64, imm: i64) -> u64 {
+    rs1 | (imm as u64)
+}
+
+fn alu_andi(rs1: u64, imm: i64) -> u64 {
+    rs1 & (imm as u64)
+}
+
+fn alu_slli(rs1: u64, imm: i64) -> u64 {
+    rs1.wrapping_shl(imm as u32)
+}
+
+fn alu_slliw(rs1: u64, imm: i64) -> u64 {
+    (rs1 as i32).wrapping_shl(imm as u32) as u64
+}
+
+fn alu_srli(rs1: u64, imm: i64) -> u64 {
+    rs1.wrapping_shr(imm as u32)
+}
+
+fn alu_srliw(rs1: u64, imm: i64) -> u64 {
+    (rs1 as u32).wrapping_shr(imm as u32) as i32 as u64
+}
+
+fn alu_srai(rs1: u64, imm: i64) -> u64 {
+    (rs1 as i64).wrapping_shr(imm as u32) as u64
+}
+
+fn alu_sraiw(rs1: u64, imm: i64) -> u64 {
+    (rs1 as i32).wrapping_shr(imm as u32) as u64
+}
+
+fn alu_add(rs1: u64, rs2: u64) -> u64 {
+    rs1.wrapping_add(rs2)
+}
+
+fn alu_addw(rs1: u64, rs2: u64) -> u64 {
+    (rs1 as i32).wrapping_add(rs2 as i32) as u64
+}
+
+fn alu_sub(rs1: u64, rs2: u64) -> u64 {
+    rs1.wrapping_sub(rs2)
+}
+
+fn alu_subw(rs1: u64, rs2: u64) -> u64 {
+    (rs1 as i32).wrapping_sub(rs2 as i32) as u64
+}
+
+fn alu_sll(rs1: u64, rs2: u64) -> u64 {
+    rs1.wrapping_shl(rs2 as u32)
+}
+
+fn alu_sllw(rs1: u64, rs2: u64) -> u64 {
+    (rs1 as i32).wrapping_shl(rs2 as u32) as u64
+}
+
+fn alu_srl(rs1: u64, rs2: u64) -> u64 {
+    rs1.wrapping_shr(rs2 as u32)
+}
+
+fn alu_sra(rs1: u64, rs2: u64) -> u64 {
+    (rs1 as i64).wrapping_shr(rs2 as u32) as u64
+}
+
+fn alu_slt(rs1: u64, rs2: u64) -> u64 {
+    EmulatorValue::from((rs1 as i64) < (rs2 as i64))
+}
+
+fn alu_sltu(rs1: u64, rs2: u64) -> u64 {
+    EmulatorValue::from(rs1 < rs2)
+}
+
+fn alu_or(rs1: u64, rs2: u64) -> u64 {
+    rs1 | rs2
+}
+
+fn alu_and(rs1: u64, rs2: u64) -> u64 {
+    rs1 & rs2
+}
+
+fn alu_mul(rs1: u64, rs2: u64) -> u64 {
+    rs1.wrapping_mul(rs2)
+}
+
+fn alu_mulw(rs1: u64, rs2: u64) -> u64 {
+    (rs1 as i32).wrapping_mul(rs2 as i32) as u64
+}
+
+fn alu_div(rs1: u64, rs2: u64) -> u64 {
+    match rs2 {
+        0 => u64::MAX,
+        _ => (rs1 as i64).wrapping_div(rs2 as i64) as u64,
+    }
+}
+
+fn alu_divw(rs1: u64, rs2: u64) -> u64 {
+    match rs2 as i32 {
+        0 => u64::MAX,
+        divisor => (rs1 as i32).wrapping_div(divisor) as u64,
+    }
+}
+
+fn alu_divu(rs1: u64, rs2: u64) -> u64 {
+    rs1.checked_div(rs2).unwrap_or(u64::MAX)
+}
+
+fn alu_rem(rs1: u64, rs2: u64) -> u64 {
+    match rs2 {
+        0 => rs1,
+        _ => (rs1 as i64).wrapping_rem(rs2 as i64) as u64,
+    }
+}
+
+fn alu_remw(rs1: u64, rs2: u64) -> u64 {
+    match rs2 as i32 {
+        0 => rs1 as i32 as u64,
+        divisor => (rs1 as i32).wrapping_rem(divisor) as u64,
+    }
+}
+
+fn alu_remu(rs1: u64, rs2: u64) -> u64 {
+    rs1.checked_rem(rs2).unwrap_or(rs1)
+}
+
+fn exec_ecall(state: &mut EmulatorState) {
+    let a7_value = state.get_reg(Register::A7);
+    let overridden = state
+        .syscall_overrides
+        .get_mut(&a7_value)
+        .and_then(|queue| queue.pop_front());
+    if let Some(result) = overridden {
+        debug!("syscall {} overridden -> {}", a7_value, result as i64);
+        state.set_reg(Register::A0, result);
+    } else if let Some(SyscallHook(mut hook)) = state.syscall_hooks.remove(&a7_value) {
+        hook(state);
+        // Keep a handler installed by the handler itself.
+        state
+            .syscall_hooks
+            .entry(a7_value)
+            .or_insert(SyscallHook(hook));
+    } else if let Some(handler) = syscall_handler(a7_value) {
+        handler(state);
+        if state.syscall_blocked {
+            // Retry the very same `ecall` with the next step.
+            state.syscall_blocked = false;
+            return;
+        }
+        if state.config.gas_budget.is_some() {
+            state.gas_used += state.config.syscall_costs.get(&a7_value).unwrap_or(&0);
+        }
+    } else if a7_value == SyscallId::Newfstat as u64 {
+        // TODO newfstat system call
+        warn!("unimplemented 'fstat' system call reached");
+    } else if let Some(syscall) = threading_syscall(a7_value) {
+        // Threads are not modeled, the set of unsupported system calls is
+        // given by `threading_syscall` below. Emulation is aborted instead
+        // of failing the call, as guests rarely recover from that.
+        let context = state.instruction_context();
+        state.fail(EmulatorError::ThreadingUnsupported { syscall, context });
+        return;
+    } else {
+        if state.unknown_syscalls.insert(a7_value) {
+            warn!("unknown system call: {}", a7_value);
+        }
+        match state.config.unknown_syscall {
+            UnknownSyscallPolicy::Ignore => state.set_reg(Register::A0, u64::MAX),
+            UnknownSyscallPolicy::Enosys => {
+                state.set_reg(Register::A0, syscall_error(Errno::ENOSYS))
+            }
+            UnknownSyscallPolicy::Abort => {
+                let context = state.instruction_context();
+                state.fail(EmulatorError::UnknownSyscall {
+                    number: a7_value,
+                    context,
+                });
+                return;
+            }
+        }
+    }
+    if state.config.resource_accounting {
+        state.usage.syscalls += 1;
+        state.track_heap_peak();
+    }
+    if let Some(limit) = state.config.syscall_rate_limit {
+        state.check_syscall_rate(limit);
+    }
+    state.pc_next();
+}
+
+fn syscall_handler(a7_value: EmulatorValue) -> Option<SyscallHandler> {
+    SYSCALL_HANDLERS
+        .iter()
+        .find(|(id, _)| a7_value == *id as u64)
+        .map(|(_, handler)| *handler)
+}
+
+fn threading_syscall(a7_value: EmulatorValue) -> Option<SyscallId> {
+    [SyscallId::Clone, SyscallId::Clone3, SyscallId::Futex]
+        .iter()
+        .copied()
+        .find(|&id| a7_value == id as u64)
+}
+
+fn syscall_exit(state: &mut EmulatorState) {
+    let exit_code = state.get_reg(Register::A0);
+    if !state.config.dry_run {
+        state.stdio.stdout.flush().expect("stdout flush success");
+    }
+    info!("program exiting with exit code {}", exit_code);
+    state.stop(StopReason::Exit { exit_code });
+}
+
+fn syscall_read(state: &mut EmulatorState) {
+    let fd = state.get_reg(Register::A0);
+    let buffer = state.get_reg(Register::A1);
+    let size = state.get_reg(Register::A2);
+
+    // Block while scripted standard input is not yet available.
+    if state.input_blocks(fd) {
+        state.syscall_blocked = true;
+        return;
+    }
+
+    let result = read_into(state, fd, buffer, size).unwrap_or_else(syscall_error);
+
+    state.set_reg(Register::A0, result);
+    debug!("read({},{:#x},{}) -> {}", fd, buffer, size, result as i64);
+}
+
+fn syscall_write(state: &mut EmulatorState) {
+    let fd = state.get_reg(Register::A0);
+    let buffer = state.get_reg(Register::A1);
+    let size = state.get_reg(Register::A2);
+
+    let result = write_from(state, fd, buffer, size).unwrap_or_else(syscall_error);
+
+    state.set_reg(Register::A0, result);
+    debug!("write({},{:#x},{}) -> {}", fd, buffer, size, result as i64);
+}
+
+// Scatter-gather variants of `read` and `write`, performing the I/O for
+// each `struct iovec` (a base address and a length) of the given array
+// in turn. Transfer stops at the first buffer that is only partially
+// transferred, the total number of bytes is returned. Errors are only
+// reported in case nothing was transferred before.
+
+fn syscall_readv(state: &mut EmulatorState) {
+    let fd = state.get_reg(Register::A0);
+    let iov = state.get_reg(Register::A1);
+    let iovcnt = state.get_reg(Register::A2);
+
+    if state.input_blocks(fd) {
+        state.syscall_blocked = true;
+        return;
+    }
+
+    let result = vectored(state, fd, iov, iovcnt, read_into).unwrap_or_else(syscall_error);
+
+    state.set_reg(Register::A0, result);
+    debug!("readv({},{:#x},{}) -> {}", fd, iov, iovcnt, result as i64);
+}
+
+fn syscall_writev(state: &mut EmulatorState) {
+    let fd = state.get_reg(Register::A0);
+    let iov = state.get_reg(Register::A1);
+    let iovcnt = state.get_reg(Register::A2);
+
+    let result = vectored(state, fd, iov, iovcnt, write_from).unwrap_or_else(syscall_error);
+
+    state.set_reg(Register::A0, result);
+    debug!("writev({},{:#x},{}) -> {}", fd, iov, iovcnt, result as i64);
+}
+
+type BufferTransfer =
+    fn(&mut EmulatorState, EmulatorValue, EmulatorValue, u64) -> Result<u64, Errno>;
+
+fn vectored(
+    state: &mut EmulatorState,
+    fd: EmulatorValue,
+    iov: EmulatorValue,
+    iovcnt: u64,
+    transfer: BufferTransfer,
+) -> Result<u64, Errno> {
+    if iovcnt > MAX_IOVCNT {
+        return Err(Errno::EINVAL);
+    }
+    let vector = state.copy_words_in(iov, iovcnt as usize * 2)?;
+    let mut total_bytes = 0;
+    for entry in vector.chunks(2) {
+        let (base, len) = (entry[0], entry[1]);
+        let bytes = match transfer(state, fd, base, len) {
+            Ok(bytes) => bytes,
+            Err(errno) if total_bytes == 0 => return Err(errno),
+            Err(_) => break,
+        };
+        total_bytes += bytes;
+        if bytes != len {
+            break;
+        }
+    }
+    Ok(total_bytes)
+}
+
+// Emulates reading into a single guest buffer via `std::io::Read`, chunk
+// by chunk (of up to one word) until the buffer is full or reading
+// returns fewer bytes than requested.
+fn read_into(
+    state: &mut EmulatorState,
+    fd: EmulatorValue,
+    buffer: EmulatorValue,
+    size: u64,
+) -> Result<u64, Errno> {
+    state.fd_read(fd)?;
+    match buffer.checked_add(size) {
+        Some(end) if end <= state.memory.len() as u64 => {}
+        _ => return Err(Errno::EFAULT),
+    }
+    let mut total_bytes = 0; // counts total bytes read
+    let mut tmp_buffer = [0; riscu::WORD_SIZE]; // scratch buffer
+    while total_bytes < size {
+        let bytes_to_read = min(size - total_bytes, riscu::WORD_SIZE as u64) as usize;
+        let bytes = &mut tmp_buffer[0..bytes_to_read];
+        let bytes_read = match state.fd_read(fd).expect("valid descriptor").read(bytes) {
+            Ok(bytes_read) => bytes_read,
+            // Host errors after a partial read are reported as short read.
+            Err(_) if total_bytes > 0 => break,
+            Err(error) => return Err(errno_from_io(&error)),
+        };
+        let adr = buffer + total_bytes;
+        state.write_bytes(adr, &tmp_buffer[0..bytes_read]);
+        state.witness_input(fd, adr, &tmp_buffer[0..bytes_read]);
+        total_bytes += bytes_read as u64; // tally all bytes
+        if bytes_read != bytes_to_read {
+            break;
+        }
+    }
+    if state.config.resource_accounting {
+        state.usage.bytes_read += total_bytes;
+    }
+    Ok(total_bytes)
+}
+
+// Emulates writing a single guest buffer via `std::io::Write`, chunk by
+// chunk (of up to one word) until the buffer is written or writing
+// accepts fewer bytes than provided.
+fn write_from(
+    state: &mut EmulatorState,
+    fd: EmulatorValue,
+    buffer: EmulatorValue,
+    size: u64,
+) -> Result<u64, Errno> {
+    state.fd_write(fd)?;
+    match buffer.checked_add(size) {
+        Some(end) if end <= state.memory.len() as u64 => {}
+        _ => return Err(Errno::EFAULT),
+    }
+
+    // Check the output limit has not been reached yet, the first write
+    // exceeding it is cut short and reported once.
+    let size_allowed = match state.config.max_output_bytes {
+        Some(cap) => min(size, cap.saturating_sub(state.output_bytes)),
+        None => size,
+    };
+    if size_allowed < size && !state.output_capped {
+        warn!("output limit reached, dropping further output");
+        state.output_capped = true;
+    }
+    if size_allowed == 0 && size > 0 {
+        return Err(Errno::ENOSPC);
+    }
+
+    let size = size_allowed;
+    let mut total_bytes = 0; // counts total bytes written
+    while total_bytes < size {
+        let bytes_to_write = min(size - total_bytes, riscu::WORD_SIZE as u64) as usize;
+        let adr = (buffer + total_bytes) as usize;
+        let bytes = state.memory[adr..adr + bytes_to_write].to_vec();
+        let bytes_written = match state.fd_write(fd).expect("valid descriptor").write(&bytes) {
+            Ok(bytes_written) => bytes_written,
+            // Host errors after a partial write are reported as short write.
+            Err(_) if total_bytes > 0 => break,
+            Err(error) => return Err(errno_from_io(&error)),
+        };
+        total_bytes += bytes_written as u64; // tally all bytes
+        if bytes_written != bytes_to_write {
+            break;
+        }
+    }
+    state.output_bytes += total_bytes;
+    if state.config.resource_accounting {
+        state.usage.bytes_written += total_bytes;
+    }
+    Ok(total_bytes)
+}
+
+fn syscall_open(state: &mut EmulatorState) {
+    let path = state.get_reg(Register::A0);
+    let flag = state.get_reg(Register::A1);
+    let mode = state.get_reg(Register::A2);
+    let temp = state.get_reg(Register::A3);
+
+    // TODO Set fd to AT_FDCWD
+    // state.set_reg(Register::A0, AT_FDCWD);
+    state.set_reg(Register::A1, path);
+    state.set_reg(Register::A2, flag);
+    state.set_reg(Register::A3, mode);
+
+    syscall_openat(state);
+
+    // needed?
+    state.set_reg(Register::A1, flag);
+    state.set_reg(Register::A2, mode);
+    state.set_reg(Register::A3, temp);
+}
+
+fn syscall_openat(state: &mut EmulatorState) {
+    let fd = state.get_reg(Register::A0);
+    let path = state.get_reg(Register::A1);
+    let flag = state.get_reg(Register::A2);
+    let mode = state.get_reg(Register::A3);
+
+    // Copy path name from memory into a string, and emulate `openat`
+    // system call via `File::open` unless the path names one of the
+    // virtual devices.
+    let result = match state.read_path(path) {
+        Ok(path_string) => match VirtualDevice::open(state, &path_string) {
+            Some(device) => state.fd_new(Descriptor::Device(device)),
+            None => match File::open(path_string) {
+                Ok(file) => state.fd_new(Descriptor::File(file)),
+                Err(error) => syscall_error(errno_from_io(&error)),
+            },
+        },
+        Err(errno) => syscall_error(errno),
+    };
+
+    state.set_reg(Register::A0, result);
+    debug!("openat({},{:#x},{},{}) -> {}", fd, path, flag, mode, result);
+}
+
+fn syscall_close(state: &mut EmulatorState) {
+    let fd = state.get_reg(Register::A0);
+
+    let result = match state.fd_close(fd) {
+        Ok(()) => 0,
+        Err(errno) => syscall_error(errno),
+    };
+
+    state.set_reg(Register::A0, result);
+    debug!("close({}) -> {:#x}", fd, result);
+}
+
+fn syscall_dup(state: &mut EmulatorState) {
+    let fd = state.get_reg(Register::A0);
+
+    let result = match state
+        .fd_get(fd)
+        .and_then(|d| d.try_clone().map_err(|e| errno_from_io(&e)))
+    {
+        Ok(duplicate) => state.fd_new(duplicate),
+        Err(errno) => syscall_error(errno),
+    };
+
+    state.set_reg(Register::A0, result);
+    debug!("dup({}) -> {}", fd, result as i64);
+}
+
+// Note that RISC-V has no `dup2` system call, `libc` implements it on top
+// of `dup3`. Hence duplicating a descriptor onto itself is accepted as a
+// no-op (as `dup2` would) instead of failing (as `dup3` would).
+fn syscall_dup3(state: &mut EmulatorState) {
+    let old_fd = state.get_reg(Register::A0);
+    let new_fd = state.get_reg(Register::A1);
+    let flags = state.get_reg(Register::A2);
+
+    let result = if old_fd == new_fd {
+        state.fd_get(old_fd).map(|_| new_fd)
+    } else {
+        state.fd_dup_to(old_fd, new_fd).map(|_| new_fd)
+    }
+    .unwrap_or_else(syscall_error);
+
+    state.set_reg(Register::A0, result);
+    debug!(
+        "dup3({},{},{:#x}) -> {}",
+        old_fd, new_fd, flags, result as i64
+    );
+}
+
+// Creates a pipe and stores the descriptors of its read end and its write
+// end as two consecutive 32-bit integers into the array at `fildes`.
+fn syscall_pipe2(state: &mut EmulatorState) {
+    let fildes = state.get_reg(Register::A0);
+    let flags = state.get_reg(Register::A1);
+
+    let result = if flags & !(O_NONBLOCK | O_CLOEXEC) != 0 {
+        Err(Errno::EINVAL)
+    } else {
+        let (read_end, write_end) = PipeEnd::pair(flags & O_NONBLOCK != 0);
+        let read_fd = state.fd_new(Descriptor::Pipe(read_end));
+        let write_fd = state.fd_new(Descriptor::Pipe(write_end));
+        let mut bytes = [0; 8];
+        bytes[0..4].copy_from_slice(&(read_fd as u32).to_le_bytes());
+        bytes[4..8].copy_from_slice(&(write_fd as u32).to_le_bytes());
+        let copied = state.copy_bytes_out(fildes, &bytes);
+        if copied.is_err() {
+            state.descriptors[read_fd as usize] = None;
+            state.descriptors[write_fd as usize] = None;
+        }
+        copied
+    }
+    .map_or_else(syscall_error, |_| 0);
+
+    state.set_reg(Register::A0, result);
+    debug!("pipe2({:#x},{:#x}) -> {}", fildes, flags, result as i64);
+}
+
+fn syscall_ioctl(state: &mut EmulatorState) {
+    let fd = state.get_reg(Register::A0);
+    let request = state.get_reg(Register::A1);
+    let argp = state.get_reg(Register::A2);
+
+    let result = match state.fd_get(fd) {
+        Ok(Descriptor::File(_) | Descriptor::Pipe(_) | Descriptor::Device(_)) => Err(Errno::ENOTTY),
+        Ok(_) => match request {
+            TCGETS => {
+                let mut termios = [0; TERMIOS_SIZE];
+                for (chunk, flags) in termios.chunks_mut(4).zip(FAKE_TERMIOS.iter()) {
+                    chunk.copy_from_slice(&flags.to_le_bytes());
+                }
+                state.copy_bytes_out(argp, &termios)
+            }
+            TIOCGWINSZ => {
+                let mut winsize = [0; 8];
+                winsize[0..2].copy_from_slice(&FAKE_WINDOW_ROWS.to_le_bytes());
+                winsize[2..4].copy_from_slice(&FAKE_WINDOW_COLS.to_le_bytes());
+                state.copy_bytes_out(argp, &winsize)
+            }
+            _ => Err(Errno::ENOTTY),
+        },
+        Err(errno) => Err(errno),
+    }
+    .map_or_else(syscall_error, |_| 0);
+
+    state.set_reg(Register::A0, result);
+    debug!(
+        "ioctl({},{:#x},{:#x}) -> {}",
+        fd, request, argp, result as i64
+    );
+}
+
+// Memory mappings are carved out of the heap: pages for a new mapping
+// are taken right above the current program break, which is moved past
+// the mapping. The start of the heap moves along with it, so `brk` can
+// never shrink the heap into a mapping again. Anonymous mappings are
+// zero-filled and writable, file-backed mappings receive a copy of the
+// file contents and are read-only (stores into them fail emulation with
+// `ReadOnlyWrite`). Mappings are never unmapped and the address hint is
+// ignored, placement at a fixed address is not supported.
+fn syscall_mmap(state: &mut EmulatorState) {
+    let address = state.get_reg(Register::A0);
+    let length = state.get_reg(Register::A1);
+    let prot = state.get_reg(Register::A2);
+    let flags = state.get_reg(Register::A3);
+    let fd = state.get_reg(Register::A4);
+    let offset = state.get_reg(Register::A5);
+
+    let result = state
+        .mmap(length, prot, flags, fd, offset)
+        .unwrap_or_else(syscall_error);
+
+    state.set_reg(Register::A0, result);
+    debug!(
+        "mmap({:#x},{},{:#x},{:#x},{},{}) -> {:#x}",
+        address, length, prot, flags, fd as i64, offset, result
+    );
+}
+
+// Reports the working directory from the configuration as a terminated
+// string. Returns the buffer (as the `libc` wrapper does) instead of the
+// length of the string (as the kernel does), callers only check either
+// for success.
+fn syscall_getcwd(state: &mut EmulatorState) {
+    let buffer = state.get_reg(Register::A0);
+    let size = state.get_reg(Register::A1);
+
+    let mut cwd = state.config.working_directory.clone().into_bytes();
+    cwd.push(0);
+    let result = if (cwd.len() as u64) > size {
+        Err(Errno::ERANGE)
+    } else {
+        state.copy_bytes_out(buffer, &cwd).map(|_| buffer)
+    }
+    .unwrap_or_else(syscall_error);
+
+    state.set_reg(Register::A0, result);
+    debug!("getcwd({:#x},{}) -> {:#x}", buffer, size, result);
+}
+
+fn syscall_access(state: &mut EmulatorState) {
+    let path = state.get_reg(Register::A0);
+    let mode = state.get_reg(Register::A1);
+
+    let result = host_access(state, AT_FDCWD as u64, path, mode)
+        .map(|_| 0)
+        .unwrap_or_else(syscall_error);
+
+    state.set_reg(Register::A0, result);
+    debug!("access({:#x},{:#o}) -> {}", path, mode, result as i64);
+}
+
+fn syscall_faccessat(state: &mut EmulatorState) {
+    let fd = state.get_reg(Register::A0);
+    let path = state.get_reg(Register::A1);
+    let mode = state.get_reg(Register::A2);
+
+    let result = host_access(state, fd, path, mode)
+        .map(|_| 0)
+        .unwrap_or_else(syscall_error);
+
+    state.set_reg(Register::A0, result);
+    debug!(
+        "faccessat({},{:#x},{:#o}) -> {}",
+        fd as i64, path, mode, result as i64
+    );
+}
+
+// Checks accessibility of the host file at the guest path, relative paths
+// being resolved against the configured working directory. Relative paths
+// are only supported with `AT_FDCWD` as descriptor. Files are checked for
+// existence, readability (by opening them) and writability (permissions
+// not being read-only). Executability is not modeled, only directories
+// are considered searchable.
+fn host_access(
+    state: &EmulatorState,
+    fd: EmulatorValue,
+    adr: EmulatorValue,
+    mode: EmulatorValue,
+) -> Result<(), Errno> {
+    let guest_path = state.read_path(adr)?;
+    if Path::new(&guest_path).is_relative() && fd as i64 != AT_FDCWD {
+        return Err(Errno::EBADF);
+    }
+    let host_path = Path::new(&state.config.working_directory).join(guest_path);
+    let metadata = host_path.metadata().map_err(|e| errno_from_io(&e))?;
+    if mode & W_OK != 0 && metadata.permissions().readonly() {
+        return Err(Errno::EACCES);
+    }
+    if mode & X_OK != 0 && !metadata.is_dir() {
+        return Err(Errno::EACCES);
+    }
+    if mode & R_OK != 0 && metadata.is_file() {
+        File::open(&host_path).map_err(|e| errno_from_io(&e))?;
+    }
+    Ok(())
+}
+
+// Random bytes are drawn from a seeded generator (see `random_seed` in
+// the configuration), the flags are ignored as this never blocks.
+fn syscall_getrandom(state: &mut EmulatorState) {
+    let buffer = state.get_reg(Register::A0);
+    let size = state.get_reg(Register::A1);
+    let flags = state.get_reg(Register::A2);
+
+    let result = match buffer.checked_add(size) {
+        Some(end) if end <= state.memory.len() as u64 => {
+            let mut bytes = Vec::with_capacity(size as usize + riscu::WORD_SIZE);
+            while (bytes.len() as u64) < size {
+                let word = state.next_random();
+                bytes.extend_from_slice(&word.to_le_bytes());
+            }
+            bytes.truncate(size as usize);
+            state.copy_mem(buffer, &bytes);
+            size
+        }
+        _ => syscall_error(Errno::EFAULT),
+    };
+
+    state.set_reg(Register::A0, result);
+    debug!(
+        "getrandom({:#x},{},{:#x}) -> {}",
+        buffer, size, flags, result as i64
+    );
+}
+
+fn syscall_brk(state: &mut EmulatorState) {
+    let address = state.get_reg(Register::A0);
+
+    // Check provided address is valid and falls between the start of the
+    // heap (lowest heap) and `sp` register (lowest stack). Note that the
+    // heap can shrink again, but never below the end of the data segment.
+    assert!(address & WORD_SIZE_MASK == 0, "program break aligned");
+    if state.fault_injected(FaultSite::Brk).is_some() {
+        warn!("injected fault into brk({:#x})", address);
+    } else if (address >= state.heap_start) && (address < state.get_reg(Register::Sp)) {
+        let old_break = state.program_break;
+        state.set_program_break(address);
+        if let Some(BreakHook(hook)) = &mut state.break_hook {
+            if address != old_break {
+                hook(old_break, address);
+            }
+        }
+    }
+    let result = state.program_break;
+
+    state.set_reg(Register::A0, result);
+    debug!("brk({:#x}) -> {:#x}", address, result);
+}
+
+// Note that threading is not modeled. The following system calls are
+// cosmetic stubs that report a single process consisting of a single
+// thread with fixed identifiers, just enough to get `libc` startup code
+// (e.g. musl) past its initialization.
+
+fn syscall_set_tid_address(state: &mut EmulatorState) {
+    let tidptr = state.get_reg(Register::A0);
+
+    // The pointer is ignored, there is no thread exit that would ever
+    // clear the location it points to.
+    let result = FAKE_TID;
+
+    state.set_reg(Register::A0, result);
+    debug!("set_tid_address({:#x}) -> {}", tidptr, result);
+}
+
+fn syscall_getpid(state: &mut EmulatorState) {
+    let result = FAKE_PID;
+
+    state.set_reg(Register::A0, result);
+    debug!("getpid() -> {}", result);
+}
+
+fn syscall_gettid(state: &mut EmulatorState) {
+    let result = FAKE_TID;
+
+    state.set_reg(Register::A0, result);
+    debug!("gettid() -> {}", result);
+}
+
+// Note that signals are not modeled, none is ever delivered to the guest.
+// The following system calls are accepted as stubs reporting success,
+// just enough to get `libc` startup code (e.g. musl) past installing its
+// handlers. Installed handlers are merely recorded for diagnostics.
+
+fn syscall_rt_sigaction(state: &mut EmulatorState) {
+    let signal = state.get_reg(Register::A0);
+    let act = state.get_reg(Register::A1);
+    let oldact = state.get_reg(Register::A2);
+
+    // Handlers of `SIGKILL` and `SIGSTOP` cannot be changed.
+    let invalid =
+        signal == 0 || signal > NSIG || (act != 0 && (signal == SIGKILL || signal == SIGSTOP));
+    let result = if invalid {
+        Err(Errno::EINVAL)
+    } else {
+        let previous = state.signal_handlers.get(&signal).copied().unwrap_or(0);
+        let mut old_action = [0; SIGACTION_SIZE];
+        old_action[..size_of::<u64>()].copy_from_slice(&previous.to_le_bytes());
+        match oldact {
+            0 => Ok(()),
+            _ => state.copy_bytes_out(oldact, &old_action),
+        }
+        .and_then(|_| match act {
+            0 => Ok(()),
+            _ => state.copy_words_in(act, 1).map(|action| {
+                match action[0] {
+                    0 => state.signal_handlers.remove(&signal),
+                    handler => state.signal_handlers.insert(signal, handler),
+                };
+            }),
+        })
+    }
+    .map(|_| 0)
+    .unwrap_or_else(syscall_error);
+
+    state.set_reg(Register::A0, result);
+    debug!(
+        "rt_sigaction({},{:#x},{:#x}) -> {}",
+        signal, act, oldact, result as i64
+    );
+}
+
+fn syscall_rt_sigprocmask(state: &mut EmulatorState) {
+    let how = state.get_reg(Register::A0);
+    let set = state.get_reg(Register::A1);
+    let oldset = state.get_reg(Register::A2);
+
+    // Nothing is ever blocked, the old mask is hence always empty.
+    let result = match oldset {
+        0 => Ok(()),
+        _ => state.copy_bytes_out(oldset, &[0; size_of::<u64>()]),
+    }
+    .map(|_| 0)
+    .unwrap_or_else(syscall_error);
+
+    state.set_reg(Register::A0, result);
+    debug!(
+        "rt_sigprocmask({},{:#x},{:#x}) -> {}",
+        how, set, oldset, result as i64
+    );
+}
+
+// System calls reporting consumed processor time are backed by the
+// instruction count of resource accounting (see `instructions_per_tick`
+// in the configuration), keeping self-measuring programs deterministic.
+
+fn syscall_times(state: &mut EmulatorState) {
+    let buffer = state.get_reg(Register::A0);
+
+    // Fill `struct tms` with user time only, the buffer is optional.
+    let ticks = state.user_time_micros() / (1_000_000 / CLOCK_TICKS_PER_SECOND);
+    let result = if buffer == 0 {
+        ticks
+    } else {
+        match state.copy_words_out(buffer, &[ticks, 0, 0, 0]) {
+            Ok(()) => ticks,
+            Err(errno) => syscall_error(errno),
+        }
+    };
+
+    state.set_reg(Register::A0, result);
+    debug!("times({:#x}) -> {}", buffer, result);
+}
+
+// The guest is the only thread there is, yielding always succeeds at once.
+fn syscall_sched_yield(state: &mut EmulatorState) {
+    state.set_reg(Register::A0, 0);
+    debug!("sched_yield() -> 0");
+}
+
+// Sleeping is instantaneous, instead the requested duration is added to
+// the synthetic wall-clock time as reported by `clock_gettime`. Sleeps
+// are never interrupted, hence the remaining time is always zero.
+fn syscall_nanosleep(state: &mut EmulatorState) {
+    let request = state.get_reg(Register::A0);
+    let remain = state.get_reg(Register::A1);
+
+    let result = sleep(state, request, false, remain).map_or_else(syscall_error, |_| 0);
+
+    state.set_reg(Register::A0, result);
+    debug!(
+        "nanosleep({:#x},{:#x}) -> {}",
+        request, remain, result as i64
+    );
+}
+
+fn syscall_clock_nanosleep(state: &mut EmulatorState) {
+    let clock = state.get_reg(Register::A0);
+    let flags = state.get_reg(Register::A1);
+    let request = state.get_reg(Register::A2);
+    let remain = state.get_reg(Register::A3);
+
+    let absolute = flags & TIMER_ABSTIME != 0;
+    let result = if clock > CLOCK_BOOTTIME {
+        syscall_error(Errno::EINVAL)
+    } else {
+        sleep(state, request, absolute, remain).map_or_else(syscall_error, |_| 0)
+    };
+
+    state.set_reg(Register::A0, result);
+    debug!(
+        "clock_nanosleep({},{:#x},{:#x},{:#x}) -> {}",
+        clock, flags, request, remain, result as i64
+    );
+}
+
+fn sleep(
+    state: &mut EmulatorState,
+    request: EmulatorValue,
+    absolute: bool,
+    remain: EmulatorValue,
+) -> Result<(), Errno> {
+    let timespec = state.copy_words_in(request, 2)?;
+    let (seconds, nanos) = (timespec[0], timespec[1]);
+    if (seconds as i64) < 0 || nanos >= NANOS_PER_SECOND {
+        return Err(Errno::EINVAL);
+    }
+    let duration = seconds
+        .saturating_mul(NANOS_PER_SECOND)
+        .saturating_add(nanos);
+    if absolute {
+        let now = state.elapsed_nanos();
+        state.slept_nanos += duration.saturating_sub(now);
+    } else {
+        state.slept_nanos = state.slept_nanos.saturating_add(duration);
+        if remain != 0 {
+            state.copy_words_out(remain, &[0, 0])?;
+        }
+    }
+    Ok(())
+}
+
+// All clocks start at zero when the run starts, the CPU-time clocks only
+// account for user time while all other clocks also include sleeps.
+fn syscall_clock_gettime(state: &mut EmulatorState) {
+    let clock = state.get_reg(Register::A0);
+    let buffer = state.get_reg(Register::A1);
+
+    let nanos = match clock {
+        CLOCK_PROCESS_CPUTIME_ID | CLOCK_THREAD_CPUTIME_ID => state.user_time_micros() * 1_000,
+        _ => state.elapsed_nanos(),
+    };
+    let timespec = [nanos / NANOS_PER_SECOND, nanos % NANOS_PER_SECOND];
+    let result = if clock > CLOCK_BOOTTIME {
+        syscall_error(Errno::EINVAL)
+    } else {
+        state
+            .copy_words_out(buffer, &timespec)
+            .map_or_else(syscall_error, |_| 0)
+    };
+
+    state.set_reg(Register::A0, result);
+    debug!(
+        "clock_gettime({},{:#x}) -> {}",
+        clock, buffer, result as i64
+    );
+}
+
+// Reports the memory of the emulator as the RAM of the system, with the
+// space between program break and stack pointer being free. There is no
+// swap, uptime is the synthetic wall-clock time of the current run.
+fn syscall_sysinfo(state: &mut EmulatorState) {
+    let buffer = state.get_reg(Register::A0);
+
+    let mut words = vec![0; SYSINFO_SIZE / riscu::WORD_SIZE];
+    words[0] = state.elapsed_nanos() / NANOS_PER_SECOND; // uptime
+    words[4] = state.memory.len() as u64; // totalram
+    words[5] = state
+        .get_reg(Register::Sp)
+        .saturating_sub(state.program_break); // freeram
+    words[10] = 1; // procs
+    words[13] = 1; // mem_unit
+    let result = match state.copy_words_out(buffer, &words) {
+        Ok(()) => 0,
+        Err(errno) => syscall_error(errno),
+    };
+
+    state.set_reg(Register::A0, result);
+    debug!("sysinfo({:#x}) -> {}", buffer, result as i64);
+}
+
+// Fills `struct utsname` with the configured system identity (see
+// `system_identity` in the configuration). The structure consists of six
+// fields of `UTSNAME_FIELD_SIZE` (i.e. 65) bytes each, in this order and
+// at these offsets: sysname (0), nodename (65), release (130), version
+// (195), machine (260) and domainname (325). Every field is a string
+// padded with zero bytes, long strings are cut short to keep the last
+// byte of each field zero.
+fn syscall_uname(state: &mut EmulatorState) {
+    let buffer = state.get_reg(Register::A0);
+
+    let identity = &state.config.system_identity;
+    let fields = [
+        &identity.sysname,
+        &identity.nodename,
+        &identity.release,
+        &identity.version,
+        &identity.machine,
+        &identity.domainname,
+    ];
+    let mut utsname = vec![0; fields.len() * UTSNAME_FIELD_SIZE];
+    for (field, value) in utsname.chunks_exact_mut(UTSNAME_FIELD_SIZE).zip(fields) {
+        let len = min(value.len(), UTSNAME_FIELD_SIZE - 1);
+        field[..len].copy_from_slice(&value.as_bytes()[..len]);
+    }
+    let result = state
+        .copy_bytes_out(buffer, &utsname)
+        .map_or_else(syscall_error, |_| 0);
+
+    state.set_reg(Register::A0, result);
+    debug!("uname({:#x}) -> {}", buffer, result as i64);
+}
+
+// Resource limits reflect the memory of the emulator: the address space
+// is limited by the memory size, heap and stack share the space above the
+// start of the heap (hence both report that space). All other resources
+// are unlimited. Limits can not be changed.
+fn syscall_getrlimit(state: &mut EmulatorState) {
+    let resource = state.get_reg(Register::A0);
+    let buffer = state.get_reg(Register::A1);
+
+    let result = get_resource_limit(state, resource, buffer).map_or_else(syscall_error, |_| 0);
+
+    state.set_reg(Register::A0, result);
+    debug!("getrlimit({},{:#x}) -> {}", resource, buffer, result as i64);
+}
+
+fn syscall_prlimit64(state: &mut EmulatorState) {
+    let pid = state.get_reg(Register::A0);
+    let resource = state.get_reg(Register::A1);
+    let new_limit = state.get_reg(Register::A2);
+    let old_limit = state.get_reg(Register::A3);
+
+    let result = if pid != 0 && pid != FAKE_PID {
+        syscall_error(Errno::ESRCH)
+    } else if new_limit != 0 {
+        syscall_error(Errno::EPERM)
+    } else if old_limit == 0 {
+        0
+    } else {
+        get_resource_limit(state, resource, old_limit).map_or_else(syscall_error, |_| 0)
+    };
+
+    state.set_reg(Register::A0, result);
+    debug!(
+        "prlimit64({},{},{:#x},{:#x}) -> {}",
+        pid, resource, new_limit, old_limit, result as i64
+    );
+}
+
+fn get_resource_limit(
+    state: &mut EmulatorState,
+    resource: u64,
+    buffer: EmulatorValue,
+) -> Result<(), Errno> {
+    let limit = match resource {
+        RLIMIT_AS => state.memory.len() as u64,
+        RLIMIT_DATA | RLIMIT_STACK => (state.memory.len() as u64).saturating_sub(state.heap_start),
+        _ => RLIM_INFINITY,
+    };
+    state.copy_words_out(buffer, &[limit, limit])
+}
+
+fn syscall_getrusage(state: &mut EmulatorState) {
+    let who = state.get_reg(Register::A0);
+    let buffer = state.get_reg(Register::A1);
+
+    // Fill `struct rusage` with user time only, all other fields (i.e.
+    // system time and the various counters) are reported as zero.
+    let micros = state.user_time_micros();
+    let mut words = vec![0; RUSAGE_SIZE / riscu::WORD_SIZE];
+    words[0] = micros / 1_000_000;
+    words[1] = micros % 1_000_000;
+    let result = match state.copy_words_out(buffer, &words) {
+        Ok(()) => 0,
+        Err(errno) => syscall_error(errno),
+    };
+
+    state.set_reg(Register::A0, result);
+    debug!(
+        "getrusage({},{:#x}) -> {}",
+        who as i64, buffer, result as i64
+    );
+}
+
+fn trace_btype(state: &EmulatorState, mne: &str, btype: BType, condition: bool) {
+    if !state.tracing {
+        return;
+    }
+    trace!(
+        "pc={}: {} {:?},{:?},{}: {:?}={:#x}, {:?}={:#x} |- {}",
+        state.pc_label(state.program_counter),
+        mne,
+        btype.rs1(),
+        btype.rs2(),
+        btype.imm(),
+        btype.rs1(),
+        state.get_reg(btype.rs1()),
+        btype.rs2(),
+        state.get_reg(btype.rs2()),
+        condition
+    );
+}
+
+fn trace_itype(state: &EmulatorState, mne: &str, itype: IType, rd_value: EmulatorValue) {
+    if !state.tracing {
+        return;
+    }
+    trace!(
+        "pc={}: {} {:?},{:?},{}: {:?}={:#x} |- {:?}={:#x} -> {:?}={:#x}",
+        state.pc_label(state.program_counter),
+        mne,
+        itype.rd(),
+        itype.rs1(),
+        itype.imm(),
+        itype.rs1(),
+        state.get_reg(itype.rs1()),
+        itype.rd(),
+        state.get_reg(itype.rd()),
+        itype.rd(),
+        rd_value
+    );
+}
+
+fn trace_jtype(state: &EmulatorState, mne: &str, jtype: JType, rd_value: EmulatorValue) {
+    if !state.tracing {
+        return;
+    }
+    trace!(
+        "pc={}: {} {:?},{}: |- {:?}={:#x} -> {:?}={:#x}",
+        state.pc_label(state.program_counter),
+        mne,
+        jtype.rd(),
+        jtype.imm(),
+        jtype.rd(),
+        state.get_reg(jtype.rd()),
+        jtype.rd(),
+        rd_value
+    );
+}
+
+fn trace_rtype(state: &EmulatorState, mne: &str, rtype: RType, rd_value: EmulatorValue) {
+    if !state.tracing {
+        return;
+    }
+    trace!(
+        "pc={}: {} {:?},{:?},{:?}: {:?}={:#x}, {:?}={:#x} |- {:?}={:#x} -> {:?}={:#x}",
+        state.pc_label(state.program_counter),
+        mne,
+        rtype.rd(),
+        rtype.rs1(),
+        rtype.rs2(),
+        rtype.rs1(),
+        state.get_reg(rtype.rs1()),
+        rtype.rs2(),
+        state.get_reg(rtype.rs2()),
+        rtype.rd(),
+        state.get_reg(rtype.rd()),
+        rtype.rd(),
+        rd_value
+    );
+}
+
+fn trace_stype(state: &EmulatorState, mne: &str, stype: SType, address: EmulatorValue) {
+    if !state.tracing {
+        return;
+    }
+    trace!(
+        "pc={}: {} {:?},{}({:?}): {:?}={:#x}, {:?}={:#x} |- mem[{:#x}]=? -> mem[{:#x}]=?",
+        state.pc_label(state.program_counter),
+        mne,
+        stype.rs2(),
+        stype.imm(),
+        stype.rs1(),
+        stype.rs1(),
+        state.get_reg(stype.rs1()),
+        stype.rs2(),
+        state.get_reg(stype.rs2()),
+        address & !WORD_SIZE_MASK,
+        address & !WORD_SIZE_MASK,
+    );
+}
+
+fn trace_utype(state: &EmulatorState, mne: &str, utype: UType, rd_value: EmulatorValue) {
+    if !state.tracing {
+        return;
+    }
+    trace!(
+        "pc={}: {} {:?},{:#x}: |- {:?}={:#x} -> {:?}={:#x}",
+        state.pc_label(state.program_counter),
+        mne,
+        utype.rd(),
+        utype.imm(),
+        utype.rd(),
+        state.get_reg(utype.rd()),
+        utype.rd(),
+        rd_value
+    );
+}
+
+trait MyLittleEndian: Default {
+    fn write(bytes: &mut [u8], value: Self);
+    fn read(bytes: &[u8]) -> Self;
+}
+
+impl MyLittleEndian for i8 {
+    fn write(bytes: &mut [u8], value: Self) {
+        bytes[0] = value as u8;
+    }
+    fn read(bytes: &[u8]) -> Self {
+        bytes[0] as i8
+    }
+}
+
+impl MyLittleEndian for u8 {
+    fn write(bytes: &mut [u8], value: Self) {
+        bytes[0] = value;
+    }
+    fn read(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+}
+
+impl MyLittleEndian for i16 {
+    fn write(bytes: &mut [u8], value: Self) {
+        LittleEndian::write_i16(bytes, value);
+    }
+    fn read(bytes: &[u8]) -> Self {
+        LittleEndian::read_i16(bytes)
+    }
+}
+
+impl MyLittleEndian for u16 {
+    fn write(bytes: &mut [u8], value: Self) {
+        LittleEndian::write_u16(bytes, value);
+    }
+    fn read(bytes: &[u8]) -> Self {
+        LittleEndian::read_u16(bytes)
+    }
+}
+
+impl MyLittleEndian for i32 {
+    fn write(bytes: &mut [u8], value: Self) {
+        LittleEndian::write_i32(bytes, value);
+    }
+    fn read(bytes: &[u8]) -> Self {
+        LittleEndian::read_i32(bytes)
+    }
+}
+
+impl MyLittleEndian for u32 {
+    fn write(bytes: &mut [u8], value: Self) {
+        LittleEndian::write_u32(bytes, value);
+    }
+    fn read(bytes: &[u8]) -> Self {
+        LittleEndian::read_u32(bytes)
+    }
+}
+
+impl MyLittleEndian for u64 {
+    fn write(bytes: &mut [u8], value: Self) {
+        LittleEndian::write_u64(bytes, value);
+    }
+    fn read(bytes: &[u8]) -> Self {
+        LittleEndian::read_u64(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use riscu::ProgramSegment;
+
+    const MEMORY_SIZE: usize = 1024 * 1024;
+    const CODE_START: u64 = 0x10000;
+    const DATA_START: u64 = 0x20000;
+
+    fn program(code: &[Instruction]) -> Program {
+        let content: Vec<u8> = code
+            .iter()
+            .flat_map(|instr| u32::from(*instr).to_le_bytes())
+            .collect();
+        let code_end = CODE_START + content.len() as u64;
+        Program {
+            code: ProgramSegment {
+                address: CODE_START,
+                content,
+            },
+            data: ProgramSegment {
+                address: DATA_START,
+                content: vec![],
+            },
+            instruction_range: CODE_START..code_end,
+            is64: true,
+        }
+    }
+
+    // Assembles a test program from the given instructions, with code at
+    // `CODE_START` and an empty data segment at `DATA_START`, e.g.:
+    //   program![li(Register::A0, 5), ecall()]
+    macro_rules! program {
+        ($($instr:expr),* $(,)?) => {
+            program(&[$($instr),*])
+        };
+    }
+
+    // Executes the single raw instruction `word` with `a1` and `a2` holding
+    // the given operands and returns the resulting value of `a0`. This
+    // checks the semantics of individual instructions (e.g. how immediates
+    // are sign-extended) in isolation, without assembling a program.
+    fn exec_word(word: u32, rs1_value: u64, rs2_value: u64) -> u64 {
+        let instr = decode_one(word).expect("implemented instruction");
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state.set_reg(Register::A1, rs1_value);
+        state.set_reg(Register::A2, rs2_value);
+        state.pc_set(CODE_START);
+        execute(&mut state, instr);
+        state.get_reg(Register::A0)
+    }
+
+    // Raw I-type encoding with `rd = a0` and `rs1 = a1`.
+    fn itype_word(opcode: u32, funct3: u32, imm: u32) -> u32 {
+        (imm & 0xfff) << 20 | 11 << 15 | funct3 << 12 | 10 << 7 | opcode
+    }
+
+    fn run(code: &[Instruction]) -> EmulatorState {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(&program(code), &[String::from("test")])
+            .expect("bootstrap success");
+        state.run().expect("run success");
+        state
+    }
+
+    fn li(reg: Register, imm: i32) -> Instruction {
+        Instruction::new_addi(reg, Register::Zero, imm)
+    }
+
+    fn addi(rd: Register, rs1: Register, imm: i32) -> Instruction {
+        Instruction::new_addi(rd, rs1, imm)
+    }
+
+    fn ecall() -> Instruction {
+        Instruction::new_ecall()
+    }
+
+    // Host stream capturing everything written to it.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn read_from_closed_descriptor_fails() {
+        let state = run(&[
+            li(Register::A0, 0),
+            li(Register::A7, SyscallId::Close as i32),
+            Instruction::new_ecall(),
+            Instruction::new_add(Register::S0, Register::A0, Register::Zero),
+            li(Register::A0, 0),
+            Instruction::new_addi(Register::A1, Register::Sp, -8),
+            li(Register::A2, 8),
+            li(Register::A7, SyscallId::Read as i32),
+            Instruction::new_ecall(),
+            li(Register::A7, SyscallId::Exit as i32),
+            Instruction::new_ecall(),
+        ]);
+        assert_eq!(state.get_reg(Register::S0), 0);
+        assert_eq!(state.get_reg(Register::A0), syscall_error(Errno::EBADF));
+    }
+
+    #[test]
+    fn fingerprint_is_stable_and_detects_divergence() {
+        let mut state1 = EmulatorState::new(MEMORY_SIZE);
+        let mut state2 = EmulatorState::new(MEMORY_SIZE);
+        assert_eq!(state1.state_fingerprint(), 0xb20a_3b9d_2c8d_f865);
+        assert_eq!(state1.state_fingerprint(), state2.state_fingerprint());
+        state1.set_mem(0x1000, 42);
+        assert_ne!(state1.state_fingerprint(), state2.state_fingerprint());
+        state2.set_mem(0x1000, 42);
+        assert_eq!(state1.state_fingerprint(), state2.state_fingerprint());
+    }
+
+    #[test]
+    fn program_break_grows_and_shrinks() {
+        let state = run(&[
+            li(Register::A0, 0),
+            li(Register::A7, SyscallId::Brk as i32),
+            Instruction::new_ecall(),
+            Instruction::new_addi(Register::S0, Register::A0, 0),
+            Instruction::new_addi(Register::A0, Register::S0, 1024),
+            Instruction::new_ecall(),
+            Instruction::new_addi(Register::S1, Register::A0, 0),
+            Instruction::new_addi(Register::A0, Register::S0, 0),
+            Instruction::new_ecall(),
+            Instruction::new_addi(Register::S2, Register::A0, 0),
+            Instruction::new_addi(Register::A0, Register::S0, -8),
+            Instruction::new_ecall(),
+            li(Register::A7, SyscallId::Exit as i32),
+            Instruction::new_ecall(),
+        ]);
+        let heap_start = state.get_reg(Register::S0);
+        assert_eq!(heap_start, DATA_START);
+        assert_eq!(state.get_reg(Register::S1), heap_start + 1024);
+        assert_eq!(state.get_reg(Register::S2), heap_start);
+        assert_eq!(state.get_reg(Register::A0), heap_start);
+        assert_eq!(state.get_program_break(), heap_start);
+    }
+
+    #[test]
+    fn register_watch_stops_at_matching_write() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program(&[
+                    li(Register::A0, 0x41),
+                    li(Register::A0, 0x42),
+                    li(Register::A0, 0x43),
+                ]),
+                &[],
+            )
+            .expect("bootstrap success");
+        state.add_register_watch(Register::A0, |value| value == 0x42);
+        state.run().expect("run success");
+        let expected = StopReason::RegisterWatch {
+            register: Register::A0,
+            value: 0x42,
+            pc: CODE_START + 4,
+        };
+        assert_eq!(state.stop_reason(), Some(expected));
+        assert_eq!(state.get_reg(Register::A0), 0x42);
+    }
+
+    #[test]
+    fn watchpoint_stops_at_matching_store() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program(&[
+                    Instruction::new_lui(Register::A0, DATA_START as i32 >> 12),
+                    li(Register::A1, 0x0f),
+                    Instruction::new_sd(Register::A0, Register::A1, 0),
+                    li(Register::A1, 0xff),
+                    Instruction::new_sd(Register::A0, Register::A1, 8),
+                    Instruction::new_sd(Register::A0, Register::A1, 0),
+                    Instruction::new_sd(Register::A0, Register::Zero, 0),
+                ]),
+                &[],
+            )
+            .expect("bootstrap success");
+        state.add_watchpoint(DATA_START, |value| value == 0xff);
+        state.run().expect("run success");
+        let expected = StopReason::MemoryWatch {
+            address: DATA_START,
+            value: 0xff,
+            pc: CODE_START + 20,
+        };
+        assert_eq!(state.stop_reason(), Some(expected));
+        assert_eq!(state.get_mem(DATA_START), 0xff);
+    }
+
+    #[test]
+    fn oversized_arguments_are_rejected() {
+        let mut state = EmulatorState::new(DATA_START as usize + 4096);
+        let argv = [String::from("test"), "x".repeat(4096)];
+        let result = state.bootstrap(&program(&[]), &argv);
+        assert!(matches!(
+            result,
+            Err(EmulatorError::StackSetupOverflow {
+                available: 4096,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn step_back_restores_registers_and_memory() {
+        let config = EmulatorConfig {
+            reverse_depth: 2,
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(
+                &program(&[
+                    li(Register::A0, 7),
+                    Instruction::new_sd(Register::Sp, Register::A0, -8),
+                    li(Register::A0, 8),
+                ]),
+                &[],
+            )
+            .expect("bootstrap success");
+        let sp = state.get_reg(Register::Sp);
+        for _ in 0..3 {
+            state.step().expect("step success");
+        }
+        assert_eq!(state.get_reg(Register::A0), 8);
+        assert!(state.step_back());
+        assert_eq!(state.get_reg(Register::A0), 7);
+        assert_eq!(state.get_mem(sp - 8), 7);
+        assert!(state.step_back());
+        assert_eq!(state.get_mem(sp - 8), 0);
+        assert_eq!(state.get_program_counter(), CODE_START + 4);
+        assert!(!state.step_back());
+    }
+
+    #[test]
+    fn emulations_run_on_separate_threads() {
+        let handles: Vec<_> = (1..=4)
+            .map(|code| {
+                let mut state = EmulatorState::new(MEMORY_SIZE);
+                state
+                    .bootstrap(&program(&[li(Register::A0, code)]), &[])
+                    .expect("bootstrap success");
+                std::thread::spawn(move || {
+                    state.step().expect("step success");
+                    state.get_reg(Register::A0)
+                })
+            })
+            .collect();
+        for (code, handle) in (1..=4).zip(handles) {
+            assert_eq!(handle.join().expect("thread success"), code as u64);
+        }
+    }
+
+    #[test]
+    fn patched_instruction_is_executed() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(&program(&[li(Register::A0, 1)]), &[])
+            .expect("bootstrap success");
+        state
+            .patch_instruction(CODE_START, li(Register::A0, 2))
+            .expect("patch success");
+        state.step().expect("step success");
+        assert_eq!(state.get_reg(Register::A0), 2);
+    }
+
+    #[test]
+    fn patch_outside_of_code_is_rejected() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(&program(&[li(Register::A0, 1)]), &[])
+            .expect("bootstrap success");
+        for address in [CODE_START + 2, CODE_START + 4, DATA_START, u64::MAX - 1] {
+            assert!(matches!(
+                state.patch_instruction(address, li(Register::A0, 2)),
+                Err(EmulatorError::PatchOutOfCode { address: a }) if a == address
+            ));
+        }
+        state.step().expect("step success");
+        assert_eq!(state.get_reg(Register::A0), 1);
+    }
+
+    #[test]
+    fn clone_reports_threading_unsupported() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program(&[
+                    li(Register::A7, SyscallId::Clone as i32),
+                    Instruction::new_ecall(),
+                ]),
+                &[],
+            )
+            .expect("bootstrap success");
+        assert!(matches!(
+            state.run(),
+            Err(EmulatorError::ThreadingUnsupported {
+                syscall: SyscallId::Clone,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn execution_yields_retired_instructions() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        assert_eq!(state.execution().take(2).count(), 2);
+        let pcs: Vec<_> = state
+            .execution()
+            .map(|retired| retired.expect("step success").pc)
+            .collect();
+        assert_eq!(pcs, vec![CODE_START + 8]);
+    }
+
+    #[test]
+    fn overlapping_segments_are_rejected() {
+        let mut overlapping = program(&[li(Register::A0, 0)]);
+        overlapping.data = ProgramSegment {
+            address: CODE_START,
+            content: vec![0; 8],
+        };
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        assert!(matches!(
+            state.bootstrap(&overlapping, &[]),
+            Err(EmulatorError::SegmentOverlap { .. })
+        ));
+    }
+
+    #[test]
+    fn reported_user_time_grows() {
+        let config = EmulatorConfig {
+            instructions_per_tick: 1,
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(
+                &program(&[
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Times as i32),
+                    Instruction::new_ecall(),
+                    Instruction::new_addi(Register::T0, Register::A0, 0),
+                    li(Register::A0, 0),
+                    Instruction::new_ecall(),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    Instruction::new_ecall(),
+                ]),
+                &[],
+            )
+            .expect("bootstrap success");
+        let report = state.run().expect("run success");
+        assert_eq!(state.get_reg(Register::T0), 2);
+        assert_eq!(report.exit_code, Some(5));
+        assert_eq!(report.host_exit_code(), Some(5));
+        assert_eq!(report.instructions_retired, 8);
+        assert_eq!(report.final_pc, CODE_START + 32);
+    }
+
+    #[test]
+    fn find_in_memory_crosses_word_boundaries() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state.set_mem(DATA_START, 0xdead_beef_0000_0000);
+        state.set_mem(DATA_START + 8, 0x0000_0000_0000_beef);
+        assert_eq!(
+            state.find_in_memory(&[0xef, 0xbe, 0xad, 0xde]),
+            vec![DATA_START + 4]
+        );
+        assert_eq!(
+            state.find_in_memory(&[0xde, 0xef, 0xbe]),
+            vec![DATA_START + 7]
+        );
+        assert!(state.find_in_memory(&[]).is_empty());
+    }
+
+    #[test]
+    fn symbols_label_nearest_preceding_address() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state.set_symbols(vec![
+            (CODE_START + 0x20, String::from("main")),
+            (CODE_START, String::from("_start")),
+        ]);
+        assert_eq!(state.symbol_at(CODE_START - 4), None);
+        assert_eq!(state.symbol_at(CODE_START + 4), Some(("_start", 4)));
+        assert_eq!(
+            state.pc_label(CODE_START + 0x30).to_string(),
+            "0x10030 <main+0x10>"
+        );
+    }
+
+    #[test]
+    fn path_witness_records_branch_outcomes() {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program(&[
+                    Instruction::new_beq(Register::Zero, Register::Zero, 8),
+                    li(Register::A0, 1),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    Instruction::new_ecall(),
+                ]),
+                &[],
+            )
+            .expect("bootstrap success");
+        let recorded = events.clone();
+        state.set_path_witness(move |event| recorded.lock().unwrap().push(event));
+        let report = state.run().expect("run success");
+        assert_eq!(report.exit_code, Some(0));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![WitnessEvent::Branch {
+                pc: CODE_START,
+                taken: true
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_memory_reports_differing_words() {
+        let mut old = EmulatorState::new(MEMORY_SIZE);
+        let mut new = EmulatorState::new(MEMORY_SIZE + 16);
+        old.set_mem(DATA_START, 1);
+        new.set_mem(DATA_START, 2);
+        new.set_mem(MEMORY_SIZE as u64 + 8, 3);
+        assert_eq!(
+            old.diff_memory(&new),
+            vec![
+                MemoryDiff {
+                    address: DATA_START,
+                    old_word: 1,
+                    new_word: 2
+                },
+                MemoryDiff {
+                    address: MEMORY_SIZE as u64 + 8,
+                    old_word: 0,
+                    new_word: 3
+                },
+            ]
+        );
+        assert!(old.diff_memory(&old).is_empty());
+    }
+
+    #[test]
+    fn zero_register_always_reads_zero() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state.registers[Register::Zero as usize] = 42;
+        assert_eq!(state.get_reg(Register::Zero), 0);
+    }
+
+    #[test]
+    fn guest_writes_to_zero_register_are_discarded() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, 6),
+                    li(Register::A1, 7),
+                    Instruction::new_add(Register::Zero, Register::A0, Register::A1),
+                    Instruction::new_mul(Register::Zero, Register::A0, Register::A1),
+                    Instruction::new_divu(Register::Zero, Register::A0, Register::A1),
+                    Instruction::new_sltu(Register::Zero, Register::A0, Register::A1),
+                    Instruction::new_lui(Register::Zero, 1),
+                    Instruction::new_ld(Register::Zero, Register::Sp, 0),
+                    Instruction::new_add(Register::A2, Register::Zero, Register::A1),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.run().expect("run success");
+        assert_eq!(state.get_reg(Register::Zero), 0);
+        assert_eq!(state.get_reg(Register::A2), 7);
+    }
+
+    #[test]
+    fn break_hook_reports_actual_changes() {
+        let changes = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program(&[
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Brk as i32),
+                    Instruction::new_ecall(),
+                    Instruction::new_addi(Register::S0, Register::A0, 0),
+                    Instruction::new_ecall(),
+                    Instruction::new_addi(Register::A0, Register::S0, 1024),
+                    Instruction::new_ecall(),
+                    Instruction::new_addi(Register::A0, Register::S0, 0),
+                    Instruction::new_ecall(),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    Instruction::new_ecall(),
+                ]),
+                &[],
+            )
+            .expect("bootstrap success");
+        let recorded = changes.clone();
+        state.on_break_change(move |old, new| recorded.lock().unwrap().push((old, new)));
+        state.run().expect("run success");
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![
+                (DATA_START, DATA_START + 1024),
+                (DATA_START + 1024, DATA_START)
+            ]
+        );
+    }
+
+    #[test]
+    fn dup3_redirects_stdout() {
+        let captured = SharedBuffer::default();
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program(&[
+                    li(Register::T0, 0x41),
+                    Instruction::new_sd(Register::Sp, Register::T0, -8),
+                    li(Register::A0, 2),
+                    li(Register::A1, 1),
+                    li(Register::A2, 0),
+                    li(Register::A7, SyscallId::Dup3 as i32),
+                    Instruction::new_ecall(),
+                    Instruction::new_addi(Register::A1, Register::Sp, -8),
+                    li(Register::A2, 1),
+                    li(Register::A7, SyscallId::Write as i32),
+                    Instruction::new_ecall(),
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    Instruction::new_ecall(),
+                ]),
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), captured.clone());
+        state.run().expect("run success");
+        assert_eq!(*captured.0.lock().unwrap(), b"A");
+    }
+
+    #[test]
+    fn ioctl_reports_fixed_window_size_on_stdout() {
+        let state = run(&[
+            li(Register::A0, 1),
+            Instruction::new_lui(Register::A1, (TIOCGWINSZ >> 12) as i32),
+            addi(Register::A1, Register::A1, (TIOCGWINSZ & 0xfff) as i32),
+            addi(Register::A2, Register::Sp, -8),
+            li(Register::A7, SyscallId::Ioctl as i32),
+            ecall(),
+            addi(Register::S1, Register::A0, 0),
+            li(Register::A0, 1),
+            li(Register::A1, 0x5402),
+            ecall(),
+            addi(Register::S2, Register::A0, 0),
+            li(Register::A0, 0),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ]);
+        let winsize = state.get_mem(state.get_reg(Register::Sp) - 8);
+        assert_eq!(state.get_reg(Register::S1), 0);
+        assert_eq!(winsize, 24 | 80 << 16);
+        assert_eq!(state.get_reg(Register::S2), syscall_error(Errno::ENOTTY));
+    }
+
+    #[test]
+    fn file_mapping_is_readable_but_not_writable() {
+        let mut data = tempfile::NamedTempFile::new().expect("temporary file");
+        data.write_all(b"mappedbytes-0123").expect("write success");
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, 0),
+                    li(Register::A1, 16),
+                    li(Register::A2, 1),
+                    li(Register::A3, 2),
+                    li(Register::A4, 3),
+                    li(Register::A5, 0),
+                    li(Register::A7, SyscallId::Mmap as i32),
+                    ecall(),
+                    addi(Register::S1, Register::A0, 0),
+                    Instruction::new_ld(Register::S2, Register::S1, 8),
+                    Instruction::new_sd(Register::S1, Register::S2, 0),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        let file = File::open(data.path()).expect("open success");
+        assert_eq!(state.fd_new(Descriptor::File(file)), 3);
+        let mapping = state.get_program_break();
+        assert!(matches!(
+            state.run(),
+            Err(EmulatorError::ReadOnlyWrite { address, .. }) if address == mapping
+        ));
+        assert_eq!(state.get_reg(Register::S1), mapping);
+        assert_eq!(
+            state.get_reg(Register::S2),
+            u64::from_le_bytes(*b"tes-0123")
+        );
+        assert_eq!(state.get_program_break(), mapping + PAGE_SIZE as u64);
+    }
+
+    #[test]
+    fn division_by_zero_follows_riscv_semantics() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A1, 7),
+                    Instruction::new_divu(Register::A3, Register::A1, Register::Zero),
+                    Instruction::new_remu(Register::A4, Register::A1, Register::Zero),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.run().expect("run success");
+        assert_eq!(state.get_reg(Register::A3), u64::MAX);
+        assert_eq!(state.get_reg(Register::A4), 7);
+    }
+
+    #[test]
+    fn signed_division_overflow_traps_only_if_enabled() {
+        // Encoding of `div a0,a1,a2` dividing `i64::MIN` by minus one.
+        let div = decode_one(0x02c5_c533).expect("implemented instruction");
+        let code = program![div, li(Register::A7, SyscallId::Exit as i32), ecall()];
+        let run_division = |config| {
+            let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+            state.bootstrap(&code, &[]).expect("bootstrap success");
+            state.set_reg(Register::A1, i64::MIN as u64);
+            state.set_reg(Register::A2, -1_i64 as u64);
+            let result = state.run();
+            (result, state.get_reg(Register::A0))
+        };
+
+        let (result, quotient) = run_division(EmulatorConfig::default());
+        assert_eq!(
+            result.expect("run success").exit_code,
+            Some(i64::MIN as u64)
+        );
+        assert_eq!(quotient, i64::MIN as u64);
+
+        let (result, quotient) = run_division(EmulatorConfig {
+            trap_signed_division_overflow: true,
+            ..EmulatorConfig::default()
+        });
+        match result {
+            Err(EmulatorError::SignedDivisionOverflow { context }) => {
+                assert_eq!(context.pc, CODE_START);
+                assert_eq!(
+                    context.operands,
+                    vec![(Register::A1, i64::MIN as u64), (Register::A2, u64::MAX)]
+                );
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(quotient, i64::MIN as u64);
+    }
+
+    #[test]
+    fn determinism_bundle_reproduces_random_bytes_and_input() {
+        let code = program![
+            addi(Register::A0, Register::Sp, -16),
+            li(Register::A1, 12),
+            li(Register::A2, 0),
+            li(Register::A7, SyscallId::Getrandom as i32),
+            ecall(),
+            li(Register::A0, 0),
+            addi(Register::A1, Register::Sp, -32),
+            li(Register::A2, 8),
+            li(Register::A7, SyscallId::Read as i32),
+            ecall(),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ];
+        let bundle = DeterminismConfig {
+            random_seed: 42,
+            stdin: ScriptedInput::new().then(5, b"in").then(0, b"put"),
+            ..DeterminismConfig::default()
+        };
+        let file = tempfile::NamedTempFile::new().expect("temporary file");
+        bundle.save(file.path()).expect("save success");
+        let loaded = DeterminismConfig::load(file.path()).expect("load success");
+        assert_eq!(loaded.random_seed, 42);
+        assert_eq!(loaded.instructions_per_tick, bundle.instructions_per_tick);
+
+        let reproduce = |bundle: &DeterminismConfig| {
+            let mut state = EmulatorState::new(MEMORY_SIZE);
+            state.bootstrap(&code, &[]).expect("bootstrap success");
+            state.set_determinism(bundle);
+            state.run().expect("run success");
+            let sp = state.get_reg(Register::Sp);
+            (
+                state.get_mem(sp - 16),
+                state.get_mem(sp - 8),
+                state.get_mem(sp - 32),
+            )
+        };
+        let (random, tail, input) = reproduce(&bundle);
+        assert_eq!((random, tail, input), reproduce(&loaded));
+        assert_ne!(random, 0);
+        assert_eq!(tail >> 32, 0);
+        assert_eq!(input, u64::from_le_bytes(*b"in\0\0\0\0\0\0"));
+    }
+
+    #[test]
+    fn coverage_bitmap_counts_loop_edges() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::T0, 3),
+                    addi(Register::T0, Register::T0, -1),
+                    Instruction::new_beq(Register::T0, Register::Zero, 8),
+                    Instruction::new_jal(Register::Zero, -8),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        assert!(state.coverage_bitmap().is_empty());
+        state.record_coverage_bitmap(1 << 16);
+        state.run().expect("run success");
+        let hits: u64 = state.coverage_bitmap().iter().map(|c| *c as u64).sum();
+        assert_eq!(state.coverage_bitmap().len(), 1 << 16);
+        assert_eq!(hits, 5);
+        state.reset_coverage();
+        assert!(state.coverage_bitmap().iter().all(|c| *c == 0));
+    }
+
+    #[test]
+    fn into_parts_hands_out_final_state() {
+        let state = run(&[
+            li(Register::A0, 7),
+            Instruction::new_sd(Register::Sp, Register::A0, -8),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ]);
+        let sp = state.get_reg(Register::Sp);
+        let pc = state.get_program_counter();
+        let (registers, memory, program_counter) = state.into_parts();
+        assert_eq!(registers[Register::A0 as usize], 7);
+        assert_eq!(memory.len(), MEMORY_SIZE);
+        assert_eq!(LittleEndian::read_u64(&memory[sp as usize - 8..]), 7);
+        assert_eq!(program_counter, pc);
+    }
+
+    #[test]
+    fn sleeping_advances_clock_without_waiting() {
+        let started = std::time::Instant::now();
+        let state = run(&[
+            // Sleep for 1000 seconds, with the request in place of `rem`.
+            li(Register::T0, 1000),
+            Instruction::new_sd(Register::Sp, Register::T0, -16),
+            Instruction::new_sd(Register::Sp, Register::Zero, -8),
+            addi(Register::A0, Register::Sp, -16),
+            addi(Register::A1, Register::Sp, -16),
+            li(Register::A7, SyscallId::Nanosleep as i32),
+            ecall(),
+            addi(Register::S1, Register::A0, 0),
+            li(Register::A0, 1),
+            addi(Register::A1, Register::Sp, -32),
+            li(Register::A7, SyscallId::ClockGettime as i32),
+            ecall(),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ]);
+        let sp = state.get_reg(Register::Sp);
+        assert_eq!(state.get_reg(Register::S1), 0);
+        assert_eq!(state.get_mem(sp - 16), 0);
+        assert_eq!(state.get_mem(sp - 32), 1000);
+        assert!(started.elapsed().as_secs() < 10);
+    }
+
+    #[test]
+    fn writev_gathers_buffers_to_stdout() {
+        let captured = SharedBuffer::default();
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, 1),
+                    addi(Register::A1, Register::Sp, -56),
+                    li(Register::A2, 3),
+                    li(Register::A7, SyscallId::Writev as i32),
+                    ecall(),
+                    addi(Register::S1, Register::A0, 0),
+                    li(Register::A0, 1),
+                    li(Register::A2, 0),
+                    ecall(),
+                    addi(Register::S2, Register::A0, 0),
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        let sp = state.get_reg(Register::Sp);
+        state.set_mem(sp - 8, u64::from_le_bytes(*b"ab--cd\0\0"));
+        let iovec = [sp - 8, 2, sp - 6, 0, sp - 4, 2];
+        for (i, word) in iovec.iter().enumerate() {
+            state.set_mem(sp - 56 + i as u64 * 8, *word);
+        }
+        state.set_stdio(io::empty(), captured.clone(), io::sink());
+        state.run().expect("run success");
+        assert_eq!(state.get_reg(Register::S1), 4);
+        assert_eq!(state.get_reg(Register::S2), 0);
+        assert_eq!(*captured.0.lock().unwrap(), b"abcd");
+    }
+
+    #[test]
+    fn layout_seed_moves_stack_reproducibly() {
+        let initial_sp = |layout_seed| {
+            let config = EmulatorConfig {
+                layout_seed,
+                ..EmulatorConfig::default()
+            };
+            let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+            state
+                .bootstrap(&program![ecall()], &[])
+                .expect("bootstrap success");
+            state.get_reg(Register::Sp) + unix_stack_size::<String>(&[])
+        };
+        assert_eq!(initial_sp(None), MEMORY_SIZE as u64);
+        assert_eq!(initial_sp(Some(1)), initial_sp(Some(1)));
+        assert_ne!(initial_sp(Some(1)), initial_sp(Some(2)));
+        for seed in 0..64 {
+            let sp = initial_sp(Some(seed));
+            assert_eq!(sp % 16, 0);
+            assert!(sp <= MEMORY_SIZE as u64 && sp > MEMORY_SIZE as u64 - MAX_STACK_OFFSET);
+        }
+    }
+
+    #[test]
+    fn memory_regions_follow_segments_and_break() {
+        let mut code = program![ecall()];
+        code.data.content = vec![0; 16];
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state.bootstrap(&code, &[]).expect("bootstrap success");
+        let program_break = state.get_program_break() + 1024;
+        state.set_program_break(program_break);
+        assert_eq!(state.memory_region(0), MemoryRegion::Unmapped);
+        assert_eq!(state.memory_region(CODE_START), MemoryRegion::Code);
+        assert_eq!(state.memory_region(CODE_START + 4), MemoryRegion::Unmapped);
+        assert_eq!(state.memory_region(DATA_START + 8), MemoryRegion::Global);
+        assert_eq!(state.memory_region(DATA_START + 16), MemoryRegion::Heap);
+        assert_eq!(state.memory_region(program_break - 8), MemoryRegion::Heap);
+        assert_eq!(state.memory_region(program_break), MemoryRegion::Stack);
+        assert_eq!(
+            state.memory_region(state.get_reg(Register::Sp)),
+            MemoryRegion::Stack
+        );
+        assert_eq!(
+            state.memory_region(MEMORY_SIZE as u64),
+            MemoryRegion::Unmapped
+        );
+    }
+
+    #[test]
+    fn run_until_instruction_stops_after_exactly_n() {
+        let code = program![
+            li(Register::A0, 1),
+            addi(Register::A0, Register::A0, 1),
+            addi(Register::A0, Register::A0, 1),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ];
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state.bootstrap(&code, &[]).expect("bootstrap success");
+        let report = state.run_until_instruction(2).expect("run success");
+        assert_eq!(
+            report.stop_reason,
+            StopReason::InstructionLimit {
+                instructions_retired: 2
+            }
+        );
+        assert_eq!(report.final_pc, CODE_START + 8);
+        assert_eq!(state.get_reg(Register::A0), 2);
+
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state.bootstrap(&code, &[]).expect("bootstrap success");
+        let report = state.run_until_instruction(100).expect("run success");
+        assert_eq!(report.stop_reason, StopReason::Exit { exit_code: 3 });
+    }
+
+    #[test]
+    fn calling_convention_check_flags_violations() {
+        let code = program![
+            Instruction::new_jal(Register::Ra, 12),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+            // Function calling without saving `ra`, then leaving to exit.
+            Instruction::new_jal(Register::Ra, 8),
+            Instruction::new_jal(Register::Zero, -12),
+            // Function saving `ra`, but returning through `t1`.
+            addi(Register::T1, Register::Ra, 0),
+            Instruction::new_jalr(Register::Zero, Register::T1, 0),
+        ];
+        let run_checked = |calling_convention| {
+            let config = EmulatorConfig {
+                calling_convention,
+                ..EmulatorConfig::default()
+            };
+            let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+            state.bootstrap(&code, &[]).expect("bootstrap success");
+            let result = state.run().map(|_| ());
+            (result, state.calling_convention_violations().to_vec())
+        };
+
+        let (result, violations) = run_checked(CallingConventionCheck::Warn);
+        assert!(result.is_ok());
+        assert_eq!(
+            violations,
+            vec![
+                ConventionViolation::LinkClobbered {
+                    pc: CODE_START + 12
+                },
+                ConventionViolation::ReturnThroughNonLink {
+                    pc: CODE_START + 24,
+                    register: Register::T1
+                },
+            ]
+        );
+
+        let (result, _) = run_checked(CallingConventionCheck::Abort);
+        assert!(matches!(
+            result,
+            Err(EmulatorError::CallingConventionViolation {
+                violation: ConventionViolation::LinkClobbered { .. }
+            })
+        ));
+
+        let (result, violations) = run_checked(CallingConventionCheck::Off);
+        assert!(result.is_ok() && violations.is_empty());
+    }
+
+    #[test]
+    fn sysinfo_and_getrlimit_report_memory_size() {
+        let state = run(&[
+            addi(Register::A0, Register::Sp, -112),
+            li(Register::A7, SyscallId::Sysinfo as i32),
+            ecall(),
+            li(Register::A0, 9),
+            addi(Register::A1, Register::Sp, -128),
+            li(Register::A7, SyscallId::Getrlimit as i32),
+            ecall(),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ]);
+        let sp = state.get_reg(Register::Sp);
+        assert_eq!(state.get_mem(sp - 112 + 32), MEMORY_SIZE as u64);
+        assert_eq!(state.get_mem(sp - 128), MEMORY_SIZE as u64);
+        assert_eq!(state.get_mem(sp - 120), MEMORY_SIZE as u64);
+    }
+
+    #[test]
+    fn execution_starts_at_entry_point() {
+        let code = program![
+            addi(Register::A0, Register::A0, 1),
+            addi(Register::A0, Register::A0, 1),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ];
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap_at(&code, &[], CODE_START + 4)
+            .expect("bootstrap success");
+        let report = state.run().expect("run success");
+        assert_eq!(report.exit_code, Some(1));
+
+        let mut header = [0; 32];
+        header[..5].copy_from_slice(b"\x7fELF\x02");
+        header[24..].copy_from_slice(&(CODE_START + 4).to_le_bytes());
+        let mut elf = tempfile::NamedTempFile::new().expect("temporary file");
+        elf.write_all(&header).expect("write success");
+        let entry = elf_entry_point(elf.path()).expect("valid header");
+        assert_eq!(entry, CODE_START + 4);
+    }
+
+    #[test]
+    fn trace_filter_restricts_traced_instructions() {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, 5),
+                    Instruction::new_sd(Register::Sp, Register::A0, -8),
+                    Instruction::new_ld(Register::A1, Register::Sp, -8),
+                    addi(Register::A1, Register::A1, 1),
+                    Instruction::new_ld(Register::A2, Register::Sp, -8),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        let recorded = events.clone();
+        state.on_trace(move |event| recorded.lock().unwrap().push((event.pc, event.result)));
+        state.set_trace_filter(TraceFilter {
+            pc_range: Some(CODE_START..CODE_START + 12),
+            classes: Some(vec![InstructionClass::Load, InstructionClass::Store]),
+        });
+        state.run().expect("run success");
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                (CODE_START + 4, None),
+                (CODE_START + 8, Some((Register::A1, 5))),
+            ]
+        );
+    }
+
+    #[test]
+    fn pipe2_transfers_bytes_from_write_end_to_read_end() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    addi(Register::A0, Register::Sp, -16),
+                    li(Register::A1, 0),
+                    li(Register::A7, SyscallId::Pipe2 as i32),
+                    ecall(),
+                    li(Register::A0, 4),
+                    addi(Register::A1, Register::Sp, -8),
+                    li(Register::A2, 3),
+                    li(Register::A7, SyscallId::Write as i32),
+                    ecall(),
+                    li(Register::A0, 3),
+                    addi(Register::A1, Register::Sp, -32),
+                    li(Register::A2, 8),
+                    li(Register::A7, SyscallId::Read as i32),
+                    ecall(),
+                    addi(Register::S1, Register::A0, 0),
+                    li(Register::A0, 4),
+                    li(Register::A7, SyscallId::Close as i32),
+                    ecall(),
+                    li(Register::A0, 3),
+                    li(Register::A7, SyscallId::Read as i32),
+                    ecall(),
+                    addi(Register::S2, Register::A0, 0),
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        let sp = state.get_reg(Register::Sp);
+        state.set_mem(sp - 8, u64::from_le_bytes(*b"hi!\0\0\0\0\0"));
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.run().expect("run success");
+        assert_eq!(state.get_mem(sp - 16), 3 | 4 << 32);
+        assert_eq!(state.get_reg(Register::S1), 3);
+        assert_eq!(
+            state.get_mem(sp - 32),
+            u64::from_le_bytes(*b"hi!\0\0\0\0\0")
+        );
+        assert_eq!(state.get_reg(Register::S2), 0); // end-of-file
+    }
+
+    #[test]
+    fn reading_empty_pipe_held_by_guest_reports_deadlock() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    addi(Register::A0, Register::Sp, -16),
+                    li(Register::A1, 0),
+                    li(Register::A7, SyscallId::Pipe2 as i32),
+                    ecall(),
+                    li(Register::A0, 3),
+                    addi(Register::A1, Register::Sp, -32),
+                    li(Register::A2, 8),
+                    li(Register::A7, SyscallId::Read as i32),
+                    ecall(),
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        match state.run() {
+            Err(EmulatorError::PipeDeadlock { fd: 3, context }) => {
+                assert_eq!(context.pc, CODE_START + 32);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    fn misaligned_lw(
+        trap_unaligned_access: bool,
+    ) -> (EmulatorState, Result<RunReport, EmulatorError>) {
+        let config = EmulatorConfig {
+            trap_unaligned_access,
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(
+                &program![
+                    Instruction::new_lui(Register::A0, DATA_START as i32 >> 12),
+                    decode_one(0x0025_2583).expect("lw a1,2(a0)"),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_mem(DATA_START, 0x0807_0605_0403_0201);
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        let result = state.run();
+        (state, result)
+    }
+
+    #[test]
+    fn misaligned_load_is_permitted_by_default() {
+        let (state, result) = misaligned_lw(false);
+        result.expect("run success");
+        assert_eq!(state.get_reg(Register::A1), 0x0605_0403);
+    }
+
+    #[test]
+    fn misaligned_load_fails_when_trapping() {
+        let (state, result) = misaligned_lw(true);
+        assert!(matches!(
+            result,
+            Err(EmulatorError::UnalignedAccess {
+                address,
+                width: 4,
+                ..
+            }) if address == DATA_START + 2
+        ));
+        assert_eq!(state.get_reg(Register::A1), 0);
+    }
+
+    #[test]
+    fn region_callbacks_time_function_in_instructions() {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    Instruction::new_jal(Register::Ra, 16),
+                    Instruction::new_jal(Register::Ra, 12),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                    addi(Register::A0, Register::A0, 1),
+                    addi(Register::A0, Register::A0, 1),
+                    Instruction::new_jalr(Register::Zero, Register::Ra, 0),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        let (entered, left) = (events.clone(), events.clone());
+        state.register_region(
+            "inc2",
+            CODE_START + 16,
+            CODE_START + 28,
+            RegionCallbacks::new(
+                move |event| {
+                    entered
+                        .lock()
+                        .unwrap()
+                        .push(("enter", event.instructions_retired))
+                },
+                move |event| {
+                    left.lock()
+                        .unwrap()
+                        .push(("leave", event.instructions_retired))
+                },
+            ),
+        );
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.run().expect("run success");
+        assert_eq!(state.get_reg(Register::A0), 4);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![("enter", 1), ("leave", 4), ("enter", 5), ("leave", 8)]
+        );
+    }
+
+    #[test]
+    fn alu_operations_agree_with_reference_semantics() {
+        fn sext32(value: u64) -> u64 {
+            value as u32 as i32 as i64 as u64
+        }
+        fn signed(value: u64) -> i128 {
+            value as i64 as i128
+        }
+        type Reference = fn(u64, u64) -> u64;
+        let references: &[(&str, Reference)] = &[
+            ("add", |a, b| (a as u128 + b as u128) as u64),
+            ("addw", |a, b| sext32((a as u128 + b as u128) as u64)),
+            ("sub", |a, b| (a as i128 - b as i128) as u64),
+            ("subw", |a, b| sext32((a as i128 - b as i128) as u64)),
+            ("sll", |a, b| a << (b & 63)),
+            ("sllw", |a, b| sext32(a << (b & 31))),
+            ("srl", |a, b| a >> (b & 63)),
+            ("sra", |a, b| ((a as i64) >> (b & 63)) as u64),
+            ("slt", |a, b| (signed(a) < signed(b)) as u64),
+            ("sltu", |a, b| (a < b) as u64),
+            ("or", |a, b| a | b),
+            ("and", |a, b| a & b),
+            ("mul", |a, b| (a as u128 * b as u128) as u64),
+            ("mulw", |a, b| sext32((a as u128 * b as u128) as u64)),
+            ("div", |a, b| match b {
+                0 => u64::MAX,
+                _ => (signed(a) / signed(b)) as u64,
+            }),
+            ("divw", |a, b| match sext32(b) {
+                0 => u64::MAX,
+                _ => sext32((sext32(a) as i64 as i128 / sext32(b) as i64 as i128) as u64),
+            }),
+            ("divu", |a, b| a.checked_div(b).unwrap_or(u64::MAX)),
+            ("rem", |a, b| match b {
+                0 => a,
+                _ => (signed(a) % signed(b)) as u64,
+            }),
+            ("remw", |a, b| match sext32(b) {
+                0 => sext32(a),
+                _ => sext32((sext32(a) as i64 as i128 % sext32(b) as i64 as i128) as u64),
+            }),
+            ("remu", |a, b| a.checked_rem(b).unwrap_or(a)),
+            ("addi", |a, b| (a as u128 + b as u128) as u64),
+            ("addiw", |a, b| sext32((a as u128 + b as u128) as u64)),
+            ("sltiu", |a, b| (a < b) as u64),
+            ("xori", |a, b| a ^ b),
+            ("ori", |a, b| a | b),
+            ("andi", |a, b| a & b),
+            ("slli", |a, b| a << (b & 63)),
+            ("slliw", |a, b| sext32(a << (b & 31))),
+            ("srli", |a, b| a >> (b & 63)),
+            ("srliw", |a, b| sext32((a as u32 >> (b & 31)) as u64)),
+            ("srai", |a, b| ((a as i64) >> (b & 63)) as u64),
+            ("sraiw", |a, b| ((a as i32) >> (b & 31)) as u64),
+        ];
+        let edge_cases = [
+            0,
+            1,
+            2,
+            31,
+            32,
+            63,
+            64,
+            u64::MAX,
+            i64::MIN as u64,
+            i64::MAX as u64,
+        ];
+        let mut seed = 0x5eed;
+        let mut operands: Vec<(u64, u64)> = (0..10_000)
+            .map(|_| (splitmix64(&mut seed), splitmix64(&mut seed)))
+            .collect();
+        for &a in &edge_cases {
+            operands.extend(edge_cases.iter().map(|&b| (a, b)));
+        }
+        for (mnemonic, reference) in references {
+            let operation = alu_operation(mnemonic).expect("known operation");
+            for &(a, b) in &operands {
+                let actual = match operation {
+                    AluOperation::Register(op) => op(a, b),
+                    AluOperation::Immediate(op) => op(a, b as i64),
+                };
+                assert_eq!(actual, reference(a, b), "{} {:#x}, {:#x}", mnemonic, a, b);
+            }
+        }
+        assert_eq!(ALU_OPERATIONS.len(), references.len());
+        assert!(alu_operation("beq").is_none());
+    }
+
+    #[test]
+    fn host_exit_code_keeps_low_eight_bits() {
+        let exit_with = |exit_code: i32| {
+            let mut state = EmulatorState::new(MEMORY_SIZE);
+            state
+                .bootstrap(
+                    &program![
+                        li(Register::A0, exit_code),
+                        li(Register::A7, SyscallId::Exit as i32),
+                        ecall(),
+                    ],
+                    &[],
+                )
+                .expect("bootstrap success");
+            state.set_stdio(io::empty(), io::sink(), io::sink());
+            state.run().expect("run success").host_exit_code()
+        };
+        assert_eq!(exit_with(42), Some(42));
+        assert_eq!(exit_with(257), Some(1));
+        assert_eq!(exit_with(-1), Some(255));
+    }
+
+    #[test]
+    fn run_rejects_empty_program() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(&program![], &[])
+            .expect("bootstrap success");
+        assert!(matches!(state.run(), Err(EmulatorError::EmptyProgram)));
+    }
+
+    #[test]
+    fn run_rejects_entry_outside_of_code() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap_at(&program![ecall()], &[], CODE_START + 4)
+            .expect("bootstrap success");
+        assert!(matches!(
+            state.run(),
+            Err(EmulatorError::EntryOutOfText { pc, .. }) if pc == CODE_START + 4
+        ));
+    }
+
+    #[test]
+    fn peek_decodes_next_instruction_without_executing_it() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(&program![li(Register::A0, 7), ecall()], &[])
+            .expect("bootstrap success");
+        let peeked = state.peek().expect("pc in text");
+        assert_eq!(InstructionText(peeked).to_string(), "addi A0,Zero,7");
+        assert_eq!(state.get_program_counter(), CODE_START);
+        assert_eq!(state.get_reg(Register::A0), 0);
+        state.pc_set(CODE_START + 8);
+        assert!(state.peek().is_none());
+    }
+
+    #[test]
+    fn store_into_protected_read_only_data_fails() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    Instruction::new_lui(Register::A0, DATA_START as i32 >> 12),
+                    li(Register::A1, 42),
+                    Instruction::new_sd(Register::A0, Register::A1, 8),
+                    Instruction::new_sd(Register::A0, Register::A1, 16),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.protect_read_only(DATA_START + 16..DATA_START + 32);
+        assert!(matches!(
+            state.run(),
+            Err(EmulatorError::ReadOnlyWrite { address, .. }) if address == DATA_START + 16
+        ));
+        assert_eq!(state.get_mem(DATA_START + 8), 42);
+        assert_eq!(state.get_mem(DATA_START + 16), 0);
+    }
+
+    #[test]
+    fn elf_read_only_data_finds_rodata_sections() {
+        let section = |kind: u32, flags: u64, address: u64, size: u64| {
+            let mut header = [0; 64];
+            header[4..8].copy_from_slice(&kind.to_le_bytes());
+            header[8..16].copy_from_slice(&flags.to_le_bytes());
+            header[16..24].copy_from_slice(&address.to_le_bytes());
+            header[32..40].copy_from_slice(&size.to_le_bytes());
+            header
+        };
+        let mut contents = vec![0; 64];
+        contents[..5].copy_from_slice(b"\x7fELF\x02");
+        contents[0x28..0x30].copy_from_slice(&64_u64.to_le_bytes());
+        contents[0x3a..0x3c].copy_from_slice(&64_u16.to_le_bytes());
+        contents[0x3c..0x3e].copy_from_slice(&4_u16.to_le_bytes());
+        contents.extend_from_slice(&section(0, 0, 0, 0));
+        contents.extend_from_slice(&section(1, 0x6, CODE_START, 0x100)); // .text
+        contents.extend_from_slice(&section(1, 0x2, DATA_START, 0x20)); // .rodata
+        contents.extend_from_slice(&section(1, 0x3, DATA_START + 0x20, 0x40)); // .data
+        let mut elf = tempfile::NamedTempFile::new().expect("temporary file");
+        elf.write_all(&contents).expect("write success");
+        let ranges = elf_read_only_data(elf.path()).expect("valid sections");
+        assert_eq!(ranges, vec![DATA_START..DATA_START + 0x20]);
+    }
+
+    #[test]
+    fn injected_fault_fails_third_allocation() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A7, SyscallId::Brk as i32),
+                    li(Register::A0, 0),
+                    ecall(),
+                    addi(Register::S1, Register::A0, 0),
+                    addi(Register::A0, Register::S1, 8),
+                    ecall(),
+                    addi(Register::S2, Register::A0, 0),
+                    addi(Register::A0, Register::S1, 16),
+                    ecall(),
+                    addi(Register::S3, Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.inject_fault(FaultSite::Brk, 3);
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.run().expect("run success");
+        let initial_break = state.get_reg(Register::S1);
+        assert_eq!(state.get_reg(Register::S2), initial_break + 8);
+        assert_eq!(state.get_reg(Register::S3), initial_break + 8);
+        assert_eq!(state.get_program_break(), initial_break + 8);
+    }
+
+    #[test]
+    fn injected_fault_fails_second_load() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    Instruction::new_ld(Register::A1, Register::Sp, 0),
+                    Instruction::new_ld(Register::A2, Register::Sp, 8),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.inject_fault(FaultSite::Load, 2);
+        let sp = state.get_reg(Register::Sp);
+        assert!(matches!(
+            state.run(),
+            Err(EmulatorError::InjectedFault { site: FaultSite::Load, ordinal: 2, address, .. })
+                if address == sp + 8
+        ));
+    }
+
+    #[test]
+    fn run_with_io_returns_transcript() {
+        let program = program![
+            li(Register::A0, 0),
+            addi(Register::A1, Register::Sp, -16),
+            li(Register::A2, 8),
+            li(Register::A7, SyscallId::Read as i32),
+            ecall(),
+            addi(Register::A2, Register::A0, 0),
+            li(Register::A0, 2),
+            li(Register::A7, SyscallId::Write as i32),
+            ecall(),
+            li(Register::A0, 3),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ];
+        let transcript = EmulatorState::new(MEMORY_SIZE)
+            .run_with_io(&program, &[], b"echo")
+            .expect("run success");
+        assert_eq!(
+            transcript,
+            IoResult {
+                stdout: Vec::new(),
+                stderr: b"echo".to_vec(),
+                exit_code: Some(3),
+                instructions: 12,
+            }
+        );
+    }
+
+    #[test]
+    fn bootstrap_places_multiple_data_segments() {
+        let code = program![ecall()].code;
+        let segment = |address, value: u64| ProgramSegment {
+            address,
+            content: value.to_le_bytes().to_vec(),
+        };
+        let data = [
+            segment(DATA_START, 0x11),
+            segment(DATA_START + 0x3000, 0x22),
+        ];
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap_segments(&code, &data, &[], CODE_START)
+            .expect("bootstrap success");
+        assert_eq!(state.get_mem(DATA_START), 0x11);
+        assert_eq!(state.get_mem(DATA_START + 0x3000), 0x22);
+        assert_eq!(state.data_segment(), DATA_START..DATA_START + 0x3008);
+        assert_eq!(state.get_program_break(), DATA_START + 0x4000);
+
+        let overlapping = [segment(DATA_START, 0x11), segment(DATA_START + 4, 0x22)];
+        assert!(matches!(
+            state.bootstrap_segments(&code, &overlapping, &[], CODE_START),
+            Err(EmulatorError::DataSegmentOverlap { .. })
+        ));
+    }
+
+    fn recursive_countdown() -> Program {
+        program![
+            li(Register::A0, 3),
+            Instruction::new_jal(Register::Ra, 12),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+            Instruction::new_beq(Register::A0, Register::Zero, 28),
+            addi(Register::Sp, Register::Sp, -16),
+            Instruction::new_sd(Register::Sp, Register::Ra, 0),
+            addi(Register::A0, Register::A0, -1),
+            Instruction::new_jal(Register::Ra, -16),
+            Instruction::new_ld(Register::Ra, Register::Sp, 0),
+            addi(Register::Sp, Register::Sp, 16),
+            Instruction::new_jalr(Register::Zero, Register::Ra, 0),
+        ]
+    }
+
+    #[test]
+    fn step_over_runs_through_calls() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(&recursive_countdown(), &[])
+            .expect("bootstrap success");
+        assert_eq!(state.step_over(1000).expect("step success"), None);
+        assert_eq!(state.get_program_counter(), CODE_START + 4);
+        assert_eq!(state.step_over(1000).expect("step success"), None);
+        assert_eq!(state.get_program_counter(), CODE_START + 8);
+        assert_eq!(state.get_reg(Register::A0), 0);
+    }
+
+    #[test]
+    fn step_over_recursive_call_returns_to_same_frame() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(&recursive_countdown(), &[])
+            .expect("bootstrap success");
+        while state.get_program_counter() != CODE_START + 32 {
+            state.step().expect("step success");
+        }
+        let sp = state.get_reg(Register::Sp);
+        assert_eq!(state.step_over(1000).expect("step success"), None);
+        assert_eq!(state.get_program_counter(), CODE_START + 36);
+        assert_eq!(state.get_reg(Register::Sp), sp);
+        assert_eq!(state.get_reg(Register::A0), 0);
+    }
+
+    #[test]
+    fn step_over_runs_through_calls_linking_through_t0() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        let program = program![
+            Instruction::new_jal(Register::T0, 12),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+            li(Register::A0, 7),
+            Instruction::new_jalr(Register::Zero, Register::T0, 0),
+        ];
+        state.bootstrap(&program, &[]).expect("bootstrap success");
+        assert_eq!(state.step_over(1000).expect("step success"), None);
+        assert_eq!(state.get_program_counter(), CODE_START + 4);
+        assert_eq!(state.get_reg(Register::A0), 7);
+    }
+
+    #[test]
+    fn step_over_bounds_non_returning_calls() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(&recursive_countdown(), &[])
+            .expect("bootstrap success");
+        state.step().expect("step success");
+        let expected = StopReason::InstructionLimit {
+            instructions_retired: 6,
+        };
+        assert_eq!(state.step_over(5).expect("step success"), Some(expected));
+    }
+
+    #[test]
+    fn core_dump_captures_state_on_error() {
+        let core = tempfile::NamedTempFile::new().expect("temporary file");
+        let config = EmulatorConfig {
+            core_dump: Some(core.path().to_path_buf()),
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, 42),
+                    li(Register::A7, SyscallId::Clone as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        let sp = state.get_reg(Register::Sp);
+        state.set_mem(sp - 8, 0x1234);
+        let error = state.run().expect_err("threading unsupported");
+
+        let mut restored = EmulatorState::new(4096);
+        let note = restored.load_snapshot(core.path()).expect("valid core");
+        assert_eq!(note, error.to_string());
+        assert_eq!(restored.get_program_counter(), CODE_START + 8);
+        assert_eq!(restored.get_reg(Register::A0), 42);
+        assert_eq!(restored.get_reg(Register::Sp), sp);
+        assert_eq!(restored.get_mem(sp - 8), 0x1234);
+        assert_eq!(restored.get_program_break(), state.get_program_break());
+    }
+
+    #[test]
+    fn getcwd_reports_configured_directory() {
+        let config = EmulatorConfig {
+            working_directory: String::from("/home/guest"),
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(
+                &program![
+                    addi(Register::A0, Register::Sp, -16),
+                    li(Register::A1, 16),
+                    li(Register::A7, SyscallId::Getcwd as i32),
+                    ecall(),
+                    addi(Register::S1, Register::A0, 0),
+                    addi(Register::A0, Register::Sp, -32),
+                    li(Register::A1, 8),
+                    ecall(),
+                    addi(Register::S2, Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.run().expect("run success");
+        let sp = state.get_reg(Register::Sp);
+        assert_eq!(state.get_reg(Register::S1), sp - 16);
+        assert_eq!(state.get_mem(sp - 16), u64::from_le_bytes(*b"/home/gu"));
+        assert_eq!(state.get_mem(sp - 8), u64::from_le_bytes(*b"est\0\0\0\0\0"));
+        assert_eq!(state.get_reg(Register::S2) as i64, -(Errno::ERANGE as i64));
+    }
+
+    #[test]
+    fn encode_round_trips_decoded_instructions() {
+        let words = [
+            0x0000_0537, // lui a0,0x0
+            0xfff5_0513, // addi a0,a0,-1
+            0x4035_5593, // srai a1,a0,3
+            0x02c5_c533, // div a0,a1,a2
+            0x40b5_053b, // subw a0,a0,a1
+            0xfe0a_ece3, // bltu s5,zero,-8
+            0xfea1_3c23, // sd a0,-8(sp)
+            0xff81_3583, // ld a1,-8(sp)
+            0x8000_00ef, // jal ra,-1048576
+            0x0000_8067, // jalr zero,0(ra)
+            0x0000_0073, // ecall
+        ];
+        for &word in &words {
+            let instr = decode_one(word).expect("implemented instruction");
+            assert_eq!(encode(instr), Some(word), "{:#010x}", word);
+        }
+    }
+
+    #[test]
+    fn verify_decode_mode_runs_well_formed_code() {
+        let config = EmulatorConfig {
+            verify_decode: true,
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(&recursive_countdown(), &[])
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        assert_eq!(state.run().expect("run success").exit_code, Some(0));
+    }
+
+    #[test]
+    fn decoded_program_from_bytes_decodes_and_pads() {
+        let code = [0x13, 0x05, 0x70, 0x00, 0x73, 0x00, 0x00, 0x00]; // addi, ecall
+        let data = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09];
+        let decoded = decoded_program_from_bytes(CODE_START, &code, DATA_START, &data)
+            .expect("supported instructions");
+        assert_eq!(decoded.code.address, CODE_START);
+        assert!(matches!(
+            decoded.code.content[..],
+            [Instruction::Addi(_), Instruction::Ecall(_)]
+        ));
+        assert_eq!(decoded.data.address, DATA_START);
+        assert_eq!(decoded.data.content, vec![0x0807_0605_0403_0201, 0x09]);
+    }
+
+    #[test]
+    fn decoded_program_from_bytes_rejects_misaligned_data() {
+        let code = [0x73, 0x00, 0x00, 0x00]; // ecall
+        let result = decoded_program_from_bytes(CODE_START, &code, DATA_START + 3, &[1]);
+        assert!(matches!(
+            result,
+            Err(EmulatorError::MisalignedDataSegment { address }) if address == DATA_START + 3
+        ));
+    }
+
+    #[test]
+    fn misaligned_data_segment_is_loaded_byte_exact() {
+        let mut code = program![ecall()];
+        code.data.address = DATA_START + 3;
+        code.data.content = (1..=10).collect();
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state.bootstrap(&code, &[]).expect("bootstrap success");
+        assert_eq!(state.data_segment(), DATA_START + 3..DATA_START + 13);
+        assert_eq!(state.get_mem(DATA_START), 0x0504_0302_0100_0000);
+        assert_eq!(state.get_mem(DATA_START + 8), 0x0000_000a_0908_0706);
+        assert_eq!(state.get_mem(DATA_START + 16), 0);
+    }
+
+    #[test]
+    fn decoded_program_from_bytes_rejects_unsupported_words() {
+        let code = [0x13, 0x05, 0x70, 0x00, 0xff, 0xff, 0xff, 0xff];
+        let result = decoded_program_from_bytes(CODE_START, &code, DATA_START, &[]);
+        assert!(matches!(
+            result,
+            Err(EmulatorError::UnsupportedInstruction { address, word: 0xffff_ffff })
+                if address == CODE_START + 4
+        ));
+    }
+
+    #[test]
+    fn stack_peak_covers_deepest_frame() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    addi(Register::Sp, Register::Sp, -64),
+                    addi(Register::Sp, Register::Sp, 48),
+                    addi(Register::Sp, Register::Sp, 16),
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        let initial_depth = state.resource_usage().stack_peak;
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        let report = state.run().expect("run success");
+        assert_eq!(report.usage.stack_peak, initial_depth + 64);
+    }
+
+    #[test]
+    fn invalid_stack_pointer_fails_at_corrupting_write() {
+        let config = EmulatorConfig {
+            check_stack_pointer: true,
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(
+                &program![
+                    addi(Register::Sp, Register::Sp, -16),
+                    Instruction::new_add(Register::Sp, Register::Zero, Register::Zero),
+                    Instruction::new_sd(Register::Sp, Register::Zero, 0),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        assert!(matches!(
+            state.run(),
+            Err(EmulatorError::InvalidStackPointer { value: 0, context })
+                if context.pc == CODE_START + 4
+        ));
+    }
+
+    #[test]
+    fn static_reachable_skips_jumped_over_code() {
+        let program = program![
+            Instruction::new_beq(Register::A0, Register::Zero, 12),
+            Instruction::new_jal(Register::Zero, 16),
+            li(Register::A0, 1),
+            Instruction::new_jalr(Register::Zero, Register::Ra, 0),
+            li(Register::A0, 2),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ];
+        let decoded = program.decode().expect("valid program");
+        let reachable = static_reachable(&decoded, CODE_START);
+        let expected: BTreeSet<u64> = [0, 4, 12, 20, 24]
+            .iter()
+            .map(|offset| CODE_START + offset)
+            .collect();
+        assert_eq!(reachable, expected);
+        let from_dead_code = static_reachable(&decoded, CODE_START + 8);
+        assert_eq!(
+            from_dead_code.into_iter().collect::<Vec<_>>(),
+            vec![CODE_START + 8, CODE_START + 12]
+        );
+    }
+
+    #[test]
+    fn tee_output_duplicates_into_log() {
+        let stdout = SharedBuffer::default();
+        let log = SharedBuffer::default();
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::T0, 0x41),
+                    Instruction::new_sd(Register::Sp, Register::T0, -8),
+                    li(Register::A0, 1),
+                    addi(Register::A1, Register::Sp, -8),
+                    li(Register::A2, 1),
+                    li(Register::A7, SyscallId::Write as i32),
+                    ecall(),
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), stdout.clone(), io::sink());
+        state.tee_output(log.clone());
+        state.run().expect("run success");
+        assert_eq!(*stdout.0.lock().unwrap(), b"A");
+        assert_eq!(*log.0.lock().unwrap(), b"A");
+    }
+
+    #[test]
+    fn faccessat_checks_host_files() {
+        let file = tempfile::NamedTempFile::new().expect("temporary file");
+        let directory = file.path().parent().expect("temporary directory");
+        let name = file.path().file_name().expect("file name");
+        let config = EmulatorConfig {
+            working_directory: directory.to_string_lossy().into_owned(),
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, AT_FDCWD as i32),
+                    addi(Register::A1, Register::Sp, -256),
+                    li(Register::A2, R_OK as i32),
+                    li(Register::A7, SyscallId::Faccessat as i32),
+                    ecall(),
+                    addi(Register::S1, Register::A0, 0),
+                    li(Register::A0, AT_FDCWD as i32),
+                    addi(Register::A1, Register::Sp, -128),
+                    ecall(),
+                    addi(Register::S2, Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        let sp = state.get_reg(Register::Sp);
+        let mut existing = name.to_string_lossy().into_owned().into_bytes();
+        existing.push(0);
+        state.copy_mem(sp - 256, &existing);
+        state.copy_mem(sp - 128, b"no-such-file\0");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.run().expect("run success");
+        assert_eq!(state.get_reg(Register::S1), 0);
+        assert_eq!(state.get_reg(Register::S2) as i64, -(Errno::ENOENT as i64));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_trace_writes_one_line_per_instruction() {
+        let trace = SharedBuffer::default();
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, 7),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.write_json_trace(trace.clone());
+        state.run().expect("run success");
+        let text = String::from_utf8(trace.0.lock().unwrap().clone()).expect("valid UTF-8");
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            r#"{"pc":65536,"mnemonic":"addi","operands":"A0,Zero,7","rd":"A0","result":7}"#
+        );
+        assert!(lines[2].contains(r#""mnemonic":"ecall""#));
+        assert_eq!(json_string("a\"b\\\n"), r#""a\"b\\\u000a""#);
+    }
+
+    #[test]
+    fn argv_from_file_feeds_raw_arguments() {
+        let mut file = tempfile::NamedTempFile::new().expect("temporary file");
+        let mut contents = 2u32.to_le_bytes().to_vec();
+        for arg in [&b"prog"[..], &[0xff, b' ', b'"']] {
+            contents.extend_from_slice(&(arg.len() as u32).to_le_bytes());
+            contents.extend_from_slice(arg);
+        }
+        file.write_all(&contents).expect("write success");
+        let argv = argv_from_file(file.path()).expect("valid argument file");
+        assert_eq!(argv, vec![b"prog".to_vec(), vec![0xff, b' ', b'"']]);
+
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap_bytes(&program![ecall()], &argv)
+            .expect("bootstrap success");
+        let sp = state.get_reg(Register::Sp);
+        assert_eq!(state.get_mem(sp), 2);
+        let arg1 = state.get_mem(sp + 16);
+        assert_eq!(
+            state.get_mem(arg1),
+            u64::from_le_bytes(*b"\xff \"\0\0\0\0\0")
+        );
+
+        file.write_all(&[0]).expect("write success");
+        assert!(matches!(
+            argv_from_file(file.path()),
+            Err(EmulatorError::MalformedArgvFile)
+        ));
+    }
+
+    #[test]
+    fn runaway_recursion_exceeds_call_depth() {
+        let config = EmulatorConfig {
+            max_call_depth: Some(3),
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(&program![Instruction::new_jal(Register::Ra, 0)], &[])
+            .expect("bootstrap success");
+        assert!(matches!(
+            state.run(),
+            Err(EmulatorError::RecursionLimitExceeded {
+                depth: 3,
+                pc: CODE_START
+            })
+        ));
+        assert_eq!(state.call_depth(), 4);
+    }
+
+    #[test]
+    fn call_depth_drops_on_return() {
+        let config = EmulatorConfig {
+            max_call_depth: Some(1),
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(&recursive_countdown(), &[])
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        assert!(matches!(
+            state.run(),
+            Err(EmulatorError::RecursionLimitExceeded { depth: 1, .. })
+        ));
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(&recursive_countdown(), &[])
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.run().expect("run success");
+        assert_eq!(state.call_depth(), 0);
+    }
+
+    #[test]
+    fn installing_signal_handler_succeeds() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::T0, 0x123),
+                    Instruction::new_sd(Register::Sp, Register::T0, -32),
+                    li(Register::A0, 2),
+                    addi(Register::A1, Register::Sp, -32),
+                    li(Register::A2, 0),
+                    li(Register::A7, SyscallId::RtSigaction as i32),
+                    ecall(),
+                    addi(Register::S1, Register::A0, 0),
+                    li(Register::A0, 0),
+                    li(Register::A1, 0),
+                    addi(Register::A2, Register::Sp, -8),
+                    li(Register::A7, SyscallId::RtSigprocmask as i32),
+                    ecall(),
+                    addi(Register::S2, Register::A0, 0),
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        let report = state.run().expect("run success");
+        assert_eq!(report.exit_code, Some(0));
+        assert_eq!(state.get_reg(Register::S1), 0);
+        assert_eq!(state.get_reg(Register::S2), 0);
+        assert_eq!(state.signal_handlers().get(&2), Some(&0x123));
+    }
+
+    #[test]
+    fn overridden_syscalls_skip_real_handler() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Brk as i32),
+                    ecall(),
+                    addi(Register::S1, Register::A0, 0),
+                    li(Register::A0, 0),
+                    ecall(),
+                    addi(Register::S2, Register::A0, 0),
+                    li(Register::A0, 0),
+                    ecall(),
+                    addi(Register::S3, Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.override_syscall(SyscallId::Brk, -(Errno::ENOMEM as i64));
+        state.override_syscall(SyscallId::Brk, 3);
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.run().expect("run success");
+        assert_eq!(state.get_reg(Register::S1), syscall_error(Errno::ENOMEM));
+        assert_eq!(state.get_reg(Register::S2), 3);
+        assert_eq!(state.get_reg(Register::S3), state.get_program_break());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn read_from_directory_fails_with_eisdir() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, AT_FDCWD as i32),
+                    addi(Register::A1, Register::Sp, -256),
+                    li(Register::A2, 0),
+                    li(Register::A3, 0),
+                    li(Register::A7, SyscallId::Openat as i32),
+                    ecall(),
+                    addi(Register::S1, Register::A0, 0),
+                    addi(Register::A1, Register::Sp, -64),
+                    li(Register::A2, 8),
+                    li(Register::A7, SyscallId::Read as i32),
+                    ecall(),
+                    addi(Register::S2, Register::A0, 0),
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        let sp = state.get_reg(Register::Sp);
+        let mut directory = std::env::temp_dir()
+            .to_string_lossy()
+            .into_owned()
+            .into_bytes();
+        directory.push(0);
+        state.copy_mem(sp - 256, &directory);
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.run().expect("run success");
+        assert_eq!(state.get_reg(Register::S1), 3);
+        assert_eq!(state.get_reg(Register::S2), syscall_error(Errno::EISDIR));
+    }
+
+    // Host stream whose reader went away.
+    struct BrokenPipe;
+
+    impl Write for BrokenPipe {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::from(ErrorKind::BrokenPipe))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn failing_host_writes_are_reported_to_the_guest() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, 1),
+                    addi(Register::A1, Register::Sp, -8),
+                    li(Register::A2, 8),
+                    li(Register::A7, SyscallId::Write as i32),
+                    ecall(),
+                    addi(Register::S1, Register::A0, 0),
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), BrokenPipe, io::sink());
+        state.run().expect("run success");
+        assert_eq!(state.get_reg(Register::S1), syscall_error(Errno::EPIPE));
+    }
+
+    #[test]
+    fn instruction_kinds_have_unique_stable_ids() {
+        let mut ids = BTreeMap::new();
+        for opcode in 0..0x80 {
+            for funct3 in 0..8 {
+                for funct7 in [0x00, 0x01, 0x20] {
+                    let word = funct7 << 25 | funct3 << 12 | opcode;
+                    if let Some(instr) = decode_one(word) {
+                        let kind = instruction_kind(instr).expect("implemented");
+                        assert!((kind.id() as usize) < InstructionKind::COUNT);
+                        let name = ids.entry(kind.id()).or_insert(kind.mnemonic());
+                        assert_eq!(*name, kind.mnemonic());
+                    }
+                }
+            }
+        }
+        assert_eq!(ids.len(), InstructionKind::COUNT);
+        assert_eq!(InstructionKind::COUNT, supported_instructions().len());
+        assert_eq!(InstructionKind::Lui.id(), 0);
+        assert_eq!(InstructionKind::Addi.id(), 20);
+        assert_eq!(InstructionKind::Ecall.id(), 52);
+        assert_eq!(InstructionKind::Divu.mnemonic(), "divu");
+    }
+
+    #[test]
+    fn run_batch_reports_each_input() {
+        // Exits with the first byte of input, crashes on empty input by
+        // loading from the null page, and loops forever on input 'L'.
+        let code = program![
+            li(Register::A0, 0),
+            addi(Register::A1, Register::Sp, -8),
+            li(Register::A2, 1),
+            li(Register::A7, SyscallId::Read as i32),
+            ecall(),
+            Instruction::new_beq(Register::A0, Register::Zero, 28),
+            Instruction::new_ld(Register::A0, Register::Sp, -8),
+            li(Register::T0, 0x4c),
+            Instruction::new_beq(Register::A0, Register::T0, 0),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+            Instruction::new_jal(Register::Zero, 0),
+            Instruction::new_ld(Register::A0, Register::Zero, 0),
+        ];
+        let input = |stdin: &[u8]| BatchInput {
+            argv: vec![String::from("prog")],
+            stdin: stdin.to_vec(),
+        };
+        let config = EmulatorConfig {
+            null_guard_size: Some(PAGE_SIZE as u64),
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state.record_coverage_bitmap(1024);
+        let outcomes = state.run_batch(
+            &code,
+            &[input(b"\x07"), input(b""), input(b"L"), input(b"\x07")],
+            1000,
+        );
+        assert_eq!(outcomes[0].exit_code(), Some(7));
+        assert!(outcomes[0].new_coverage > 0);
+        assert!(outcomes[1].crashed());
+        assert!(matches!(
+            outcomes[2].result,
+            Ok(RunReport {
+                instructions_retired: 1000,
+                ..
+            })
+        ));
+        assert_eq!(outcomes[3].exit_code(), Some(7));
+        assert_eq!(outcomes[3].new_coverage, 0);
+    }
+
+    #[test]
+    fn run_batch_keeps_harness_state() {
+        // Exits with the result of reading one byte of input.
+        let code = program![
+            li(Register::A0, 0),
+            addi(Register::A1, Register::Sp, -8),
+            li(Register::A2, 1),
+            li(Register::A7, SyscallId::Read as i32),
+            ecall(),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ];
+        let input = BatchInput {
+            argv: vec![String::from("prog")],
+            stdin: b"x".to_vec(),
+        };
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        let traced = Arc::new(Mutex::new(0));
+        let counter = traced.clone();
+        state.on_trace(move |_| *counter.lock().unwrap() += 1);
+        state.override_syscall(SyscallId::Read, 9);
+        state.stop_handle().stop();
+        let outcomes = state.run_batch(&code, &[input.clone(), input.clone(), input], 1000);
+        assert!(matches!(
+            outcomes[0].result,
+            Ok(RunReport {
+                stop_reason: StopReason::Interrupted,
+                ..
+            })
+        ));
+        assert_eq!(outcomes[1].exit_code(), Some(9));
+        assert_eq!(outcomes[2].exit_code(), Some(9));
+        assert_eq!(*traced.lock().unwrap(), 14);
+    }
+
+    #[test]
+    fn uname_reports_configured_machine() {
+        let config = EmulatorConfig {
+            system_identity: SystemIdentity {
+                machine: String::from("rv64gc"),
+                ..SystemIdentity::default()
+            },
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(
+                &program![
+                    addi(Register::A0, Register::Sp, -400),
+                    li(Register::A7, SyscallId::Uname as i32),
+                    ecall(),
+                    addi(Register::S1, Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.run().expect("run success");
+        let utsname = state.get_reg(Register::Sp) - 400;
+        let field = |offset: u64| {
+            let mut bytes = Vec::new();
+            for adr in (utsname + offset..).step_by(riscu::WORD_SIZE).take(8) {
+                bytes.extend_from_slice(&state.get_mem_unaligned::<u64>(adr).to_le_bytes());
+            }
+            bytes.truncate(bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len()));
+            String::from_utf8(bytes).expect("valid UTF-8")
+        };
+        assert_eq!(state.get_reg(Register::S1), 0);
+        assert_eq!(field(0), "Linux");
+        assert_eq!(field(4 * UTSNAME_FIELD_SIZE as u64), "rv64gc");
+    }
+
+    #[test]
+    fn disassemble_bytes_flags_bad_words_inline() {
+        let mut code = Vec::new();
+        for word in [0x0070_0513, 0xffff_ffff, 0x0010_0073, 0x0000_0073] {
+            code.extend_from_slice(&u32::to_le_bytes(word)); // addi, ?, ebreak, ecall
+        }
+        let listing = disassemble_bytes(CODE_START, &code);
+        let addresses: Vec<_> = listing.iter().map(|(address, _)| *address).collect();
+        assert_eq!(
+            addresses,
+            vec![CODE_START, CODE_START + 4, CODE_START + 8, CODE_START + 12]
+        );
+        assert!(matches!(listing[0].1, Ok(Instruction::Addi(_))));
+        assert_eq!(
+            listing[1].1.unwrap_err(),
+            DecodeError::Invalid { word: 0xffff_ffff }
+        );
+        assert_eq!(
+            listing[2].1.unwrap_err(),
+            DecodeError::Unsupported { word: 0x0010_0073 }
+        );
+        assert!(matches!(listing[3].1, Ok(Instruction::Ecall(_))));
+    }
+
+    #[test]
+    fn cache_model_counts_hits_and_misses() {
+        // Loads two words sharing a line twice each, then a word 64 bytes
+        // further which maps to the same set of a direct-mapped cache.
+        let code = program![
+            Instruction::new_lui(Register::T1, DATA_START as i32 >> 12),
+            Instruction::new_ld(Register::T0, Register::T1, 0),
+            Instruction::new_ld(Register::T0, Register::T1, 8),
+            Instruction::new_ld(Register::T0, Register::T1, 0),
+            Instruction::new_ld(Register::T0, Register::T1, 8),
+            Instruction::new_ld(Register::T0, Register::T1, 64),
+            Instruction::new_ld(Register::T0, Register::T1, 0),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ];
+        let run_with = |cache: SetAssociativeCache| {
+            let mut state = EmulatorState::new(MEMORY_SIZE);
+            state.bootstrap(&code, &[]).expect("bootstrap success");
+            state.attach_cache(cache);
+            state.set_stdio(io::empty(), io::sink(), io::sink());
+            state.run().expect("run success");
+            state.cache_stats().expect("cache attached")
+        };
+        let direct = run_with(SetAssociativeCache::direct_mapped(64, 64));
+        assert_eq!(direct, CacheStats { hits: 3, misses: 3 });
+        assert_eq!(direct.miss_rate(), 0.5);
+        let associative = run_with(SetAssociativeCache::default());
+        assert_eq!(associative, CacheStats { hits: 4, misses: 2 });
+    }
+
+    #[test]
+    fn virtual_devices_are_emulated_without_the_host() {
+        let code = program![
+            li(Register::A0, AT_FDCWD as i32),
+            addi(Register::A1, Register::Sp, -64),
+            li(Register::A2, 0),
+            li(Register::A3, 0),
+            li(Register::A7, SyscallId::Openat as i32),
+            ecall(),
+            addi(Register::S1, Register::A0, 0),
+            addi(Register::A1, Register::Sp, -32),
+            li(Register::A2, 16),
+            li(Register::A7, SyscallId::Read as i32),
+            ecall(),
+            addi(Register::S2, Register::A0, 0),
+            addi(Register::A0, Register::S1, 0),
+            addi(Register::A1, Register::Sp, -32),
+            li(Register::A7, SyscallId::Write as i32),
+            ecall(),
+            addi(Register::S3, Register::A0, 0),
+            li(Register::A7, SyscallId::SchedYield as i32),
+            ecall(),
+            addi(Register::S4, Register::A0, 0),
+            li(Register::A0, 0),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ];
+        let read_device = |path: &[u8], seed| {
+            let mut state = EmulatorState::with_config(
+                MEMORY_SIZE,
+                EmulatorConfig {
+                    random_seed: seed,
+                    ..EmulatorConfig::default()
+                },
+            );
+            state.bootstrap(&code, &[]).expect("bootstrap success");
+            let sp = state.get_reg(Register::Sp);
+            state.copy_mem(sp - 64, path);
+            state.copy_mem(sp - 32, &[0xff; 16]);
+            state.run().expect("run success");
+            assert_eq!(state.get_reg(Register::S1), 3);
+            assert_eq!(state.get_reg(Register::S3), 16);
+            assert_eq!(state.get_reg(Register::S4), 0);
+            let bytes = [state.get_mem(sp - 32), state.get_mem(sp - 24)];
+            (state.get_reg(Register::S2), bytes)
+        };
+
+        assert_eq!(read_device(b"/dev/null\0", 0), (0, [u64::MAX, u64::MAX]));
+        assert_eq!(read_device(b"/dev/zero\0", 0), (16, [0, 0]));
+        let (read, random) = read_device(b"/dev/urandom\0", 7);
+        assert_eq!(read, 16);
+        assert_ne!(random, [0, 0]);
+        assert_ne!(random, [u64::MAX, u64::MAX]);
+        assert_eq!(read_device(b"/dev/urandom\0", 7), (16, random));
+        assert_ne!(read_device(b"/dev/urandom\0", 8), (16, random));
+    }
+
+    fn exit_with_answer() -> (EmulatorState, RunReport) {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A1, 1),
+                    li(Register::A0, 42),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        let report = state.run().expect("run success");
+        (state, report)
+    }
+
+    #[test]
+    fn test_support_assertions_accept_matching_values() {
+        let (state, report) = exit_with_answer();
+        test_support::assert_exit_code(&report, 42);
+        test_support::assert_register(&state, Register::A0, 42);
+        test_support::assert_register(&state, Register::A1, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "exit code mismatch: expected 0x7, actual 0x2a")]
+    fn test_support_reports_exit_code_mismatch() {
+        let (_, report) = exit_with_answer();
+        test_support::assert_exit_code(&report, 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "register A1 mismatch: expected 0x2, actual 0x1")]
+    fn test_support_reports_register_mismatch() {
+        let (state, _) = exit_with_answer();
+        test_support::assert_register(&state, Register::A1, 2);
+    }
+
+    #[test]
+    fn test_support_disassembles_near_pc() {
+        let (mut state, _) = exit_with_answer();
+        let text = test_support::disassembly_near_pc(&state);
+        assert!(text.contains("   0x1000c: ecall\n"), "{}", text);
+        assert!(text.contains("pc outside of code segment"), "{}", text);
+
+        state.pc_set(CODE_START + 4);
+        let text = test_support::disassembly_near_pc(&state);
+        assert!(text.contains("   0x10000: addi A1,Zero,1\n"), "{}", text);
+        assert!(text.contains("=> 0x10004: addi A0,Zero,42\n"), "{}", text);
+        assert!(!text.contains("outside"), "{}", text);
+    }
+
+    #[test]
+    fn summary_line_write_failure_is_reported() {
+        let mut state = EmulatorState::with_config(
+            MEMORY_SIZE,
+            EmulatorConfig {
+                summary_line: true,
+                ..EmulatorConfig::default()
+            },
+        );
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), BrokenPipe);
+        assert!(matches!(state.run(), Err(EmulatorError::IoError(_))));
+    }
+
+    #[test]
+    fn summary_line_reports_usage_after_run() {
+        let mut state = EmulatorState::with_config(
+            MEMORY_SIZE,
+            EmulatorConfig {
+                summary_line: true,
+                ..EmulatorConfig::default()
+            },
+        );
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Brk as i32),
+                    ecall(),
+                    Instruction::new_lui(Register::T0, 2),
+                    Instruction::new_add(Register::A0, Register::A0, Register::T0),
+                    ecall(),
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        let stderr = SharedBuffer::default();
+        state.set_stdio(io::empty(), io::sink(), stderr.clone());
+        let report = state.run().expect("run success");
+        assert_eq!(report.usage.syscalls, 3);
+        assert_eq!(report.usage.heap_peak, 8 * 1024);
+        let expected = format!(
+            "9 insns, 3 syscalls, heap 8KiB, stack {}, exit 0\n",
+            compact_size(report.usage.stack_peak)
+        );
+        assert_eq!(*stderr.0.lock().unwrap(), expected.as_bytes());
+    }
+
+    #[test]
+    fn summary_abbreviates_counts_and_sizes() {
+        assert_eq!(compact_count(999), "999");
+        assert_eq!(compact_count(1_500), "1.5K");
+        assert_eq!(compact_count(42_100_000), "42.1M");
+        assert_eq!(compact_count(3_000_000_000), "3.0G");
+        assert_eq!(compact_size(512), "512B");
+        assert_eq!(compact_size(8 * 1024), "8KiB");
+        assert_eq!(compact_size(3 << 19), "1.5MiB");
+        let report = RunReport {
+            stop_reason: StopReason::Interrupted,
+            exit_code: None,
+            instructions_retired: 12,
+            final_pc: CODE_START,
+            usage: ResourceUsage::default(),
+        };
+        assert_eq!(
+            report.summary(),
+            "12 insns, 0 syscalls, heap 0B, stack 0B, stopped Interrupted"
+        );
+    }
+
+    // Invokes custom system call 500 with a guest callback in `a0` (which
+    // adds its two arguments), the callback clobbers `s2` before returning.
+    fn callback_program(callback: &[Instruction]) -> Program {
+        let mut code = vec![
+            Instruction::new_lui(Register::A0, (CODE_START >> 12) as i32),
+            addi(Register::A0, Register::A0, 40),
+            li(Register::A1, 3),
+            li(Register::A2, 4),
+            li(Register::S2, 77),
+            li(Register::A7, 500),
+            ecall(),
+            addi(Register::S1, Register::A0, 0),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ];
+        code.extend_from_slice(callback);
+        program(&code)
+    }
+
+    fn run_with_callback_handler(
+        code: &Program,
+    ) -> (EmulatorState, Result<RunReport, EmulatorError>) {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state.bootstrap(code, &[]).expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.set_syscall_handler(500, |state| {
+            let function = state.get_reg(Register::A0);
+            let args = [state.get_reg(Register::A1), state.get_reg(Register::A2)];
+            let result = state.call_function(function, &args);
+            state.set_reg(Register::A0, result.wrapping_mul(2));
+        });
+        let result = state.run();
+        (state, result)
+    }
+
+    #[test]
+    fn syscall_handler_calls_back_into_guest() {
+        let code = callback_program(&[
+            Instruction::new_add(Register::A0, Register::A0, Register::A1),
+            li(Register::S2, 1),
+            Instruction::new_jalr(Register::Zero, Register::Ra, 0),
+        ]);
+        let (state, result) = run_with_callback_handler(&code);
+        assert_eq!(result.expect("run success").exit_code, Some(14));
+        assert_eq!(state.get_reg(Register::S1), 14);
+        assert_eq!(state.get_reg(Register::S2), 77);
+        assert_eq!(state.get_reg(Register::A1), 3);
+    }
+
+    #[test]
+    fn syscall_handler_is_not_reentered_by_callback() {
+        let code = callback_program(&[
+            li(Register::A7, 500),
+            ecall(),
+            Instruction::new_jalr(Register::Zero, Register::Ra, 0),
+        ]);
+        let (state, result) = run_with_callback_handler(&code);
+        // The nested `ecall` is unknown and hence returns -1 by default.
+        assert_eq!(result.expect("run success").exit_code, Some(-2_i64 as u64));
+        assert_eq!(state.get_reg(Register::S2), 77);
+    }
+
+    #[test]
+    fn exit_within_callback_stops_run() {
+        let code = callback_program(&[
+            li(Register::A0, 5),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ]);
+        let (state, result) = run_with_callback_handler(&code);
+        assert_eq!(result.expect("run success").exit_code, Some(5));
+        assert_eq!(state.get_reg(Register::S1), 0);
+    }
+
+    #[test]
+    fn syscall_storm_is_detected_per_window() {
+        // Loops 30 times over `getpid`, one system call in 5 instructions.
+        let code = program![
+            li(Register::S1, 30),
+            li(Register::A7, SyscallId::Getpid as i32),
+            ecall(),
+            addi(Register::S1, Register::S1, -1),
+            Instruction::new_beq(Register::S1, Register::Zero, 8),
+            Instruction::new_jal(Register::Zero, -16),
+            li(Register::A0, 0),
+            li(Register::A7, SyscallId::Exit as i32),
+            ecall(),
+        ];
+        let run_limited = |max_syscalls, stop| {
+            let mut state = EmulatorState::with_config(
+                MEMORY_SIZE,
+                EmulatorConfig {
+                    syscall_rate_limit: Some(SyscallRateLimit {
+                        max_syscalls,
+                        window: 100,
+                        stop,
+                    }),
+                    ..EmulatorConfig::default()
+                },
+            );
+            state.bootstrap(&code, &[]).expect("bootstrap success");
+            state.set_stdio(io::empty(), io::sink(), io::sink());
+            state.run().expect("run success").stop_reason
+        };
+        assert_eq!(
+            run_limited(10, true),
+            StopReason::SyscallStorm {
+                syscalls: 11,
+                window: 100,
+                pc: CODE_START + 8,
+            }
+        );
+        assert_eq!(run_limited(10, false), StopReason::Exit { exit_code: 0 });
+        assert_eq!(run_limited(21, true), StopReason::Exit { exit_code: 0 });
+    }
+
+    #[test]
+    fn read_cstring_spans_word_boundaries() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state.copy_mem(DATA_START + 5, b"hello, world\0");
+        assert_eq!(
+            state.read_cstring(DATA_START + 5).expect("terminated"),
+            b"hello, world"
+        );
+        assert_eq!(
+            state.read_cstring(DATA_START + 12).expect("terminated"),
+            b"world"
+        );
+        assert_eq!(
+            state.read_cstring(DATA_START + 17).expect("terminated"),
+            b""
+        );
+        assert_eq!(state.read_cstring(DATA_START).expect("terminated"), b"");
+    }
+
+    #[test]
+    fn read_cstring_is_bounded() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state.copy_mem(DATA_START, &[b'x'; MAX_CSTRING_LENGTH]);
+        assert_eq!(
+            state.read_cstring(DATA_START).expect("terminated").len(),
+            MAX_CSTRING_LENGTH
+        );
+        state.copy_mem(DATA_START, &[b'x'; MAX_CSTRING_LENGTH + 1]);
+        assert!(matches!(
+            state.read_cstring(DATA_START),
+            Err(EmulatorError::StringTooLong { address, limit: MAX_CSTRING_LENGTH })
+                if address == DATA_START
+        ));
+
+        let end = MEMORY_SIZE as u64;
+        state.copy_mem(end - 3, b"abc");
+        assert!(matches!(
+            state.read_cstring(end - 3),
+            Err(EmulatorError::UnterminatedString { address }) if address == end - 3
+        ));
+        for address in [end, end + 8, u64::MAX] {
+            assert!(matches!(
+                state.read_cstring(address),
+                Err(EmulatorError::StringOutOfBounds { address: a }) if a == address
+            ));
+        }
+    }
+
+    #[test]
+    fn openat_reports_fault_for_paths_outside_of_memory() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, AT_FDCWD as i32),
+                    li(Register::A1, -8),
+                    li(Register::A2, 0),
+                    li(Register::A3, 0),
+                    li(Register::A7, SyscallId::Openat as i32),
+                    ecall(),
+                    addi(Register::S1, Register::A0, 0),
+                    li(Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.run().expect("run success");
+        assert_eq!(state.get_reg(Register::S1) as i64, -(Errno::EFAULT as i64));
+    }
+
+    #[test]
+    fn memory_image_is_zero_padded_to_word_boundary() {
+        let mut image = tempfile::NamedTempFile::new().expect("temporary file");
+        image.write_all(b"abcdefghij").expect("write success");
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state.set_mem(DATA_START + 8, u64::MAX);
+        state
+            .load_memory_image(DATA_START, image.path())
+            .expect("image fits");
+        assert_eq!(state.get_mem(DATA_START), u64::from_le_bytes(*b"abcdefgh"));
+        assert_eq!(
+            state.get_mem(DATA_START + 8),
+            u64::from(u16::from_le_bytes(*b"ij"))
+        );
+    }
+
+    #[test]
+    fn memory_image_out_of_bounds_is_rejected() {
+        let mut image = tempfile::NamedTempFile::new().expect("temporary file");
+        image.write_all(b"abcdefghij").expect("write success");
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        let end = MEMORY_SIZE as u64;
+        for address in [end - 8, end - 9, end, u64::MAX - 4] {
+            assert!(
+                matches!(
+                    state.load_memory_image(address, image.path()),
+                    Err(EmulatorError::ImageOutOfBounds { address: a, .. }) if a == address
+                ),
+                "image at {:#x}",
+                address
+            );
+        }
+        // Padding up to the word boundary still fits.
+        assert!(state.load_memory_image(end - 16, image.path()).is_ok());
+    }
+
+    #[test]
+    fn output_stops_at_configured_cap() {
+        let captured = SharedBuffer::default();
+        let config = EmulatorConfig {
+            max_output_bytes: Some(3),
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(
+                &program(&[
+                    li(Register::T0, 0x41),
+                    Instruction::new_sd(Register::Sp, Register::T0, -8),
+                    li(Register::A0, 1),
+                    Instruction::new_addi(Register::A1, Register::Sp, -8),
+                    li(Register::A2, 8),
+                    li(Register::A7, SyscallId::Write as i32),
+                    Instruction::new_ecall(),
+                    Instruction::new_addi(Register::T1, Register::A0, 0),
+                    li(Register::A0, 1),
+                    Instruction::new_ecall(),
+                    Instruction::new_addi(Register::T2, Register::A0, 0),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    Instruction::new_ecall(),
+                ]),
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), captured.clone(), io::sink());
+        state.run().expect("run success");
+        assert_eq!(state.get_reg(Register::T1), 3);
+        assert_eq!(state.get_reg(Register::T2), syscall_error(Errno::ENOSPC));
+        assert_eq!(*captured.0.lock().unwrap(), b"A\0\0");
+    }
+
+    #[test]
+    fn null_pointer_access_is_detected() {
+        let config = EmulatorConfig {
+            null_guard_size: Some(PAGE_SIZE as u64),
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(
+                &program(&[Instruction::new_ld(Register::A0, Register::Zero, 8)]),
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_mem(8, 0xdead_beef);
+        state.set_reg(Register::A0, 42);
+        match state.step() {
+            Err(EmulatorError::NullPointerAccess { address, context }) => {
+                assert_eq!(address, 8);
+                assert_eq!(context.pc, CODE_START);
+                assert_eq!(context.operands, vec![(Register::Zero, 0)]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(state.get_reg(Register::A0), 42);
+    }
+
+    #[test]
+    fn supported_lists_match_dispatch() {
+        // Enumerate opcode and function fields of all instruction formats,
+        // which reaches every implemented instruction at least once.
+        let mut reached = BTreeSet::new();
+        for opcode in 0..0x80 {
+            for funct3 in 0..8 {
+                for funct7 in [0x00, 0x01, 0x20] {
+                    let word = funct7 << 25 | funct3 << 12 | opcode;
+                    if let Some(instr) = decode_one(word) {
+                        let name = mnemonic(instr).expect("implemented");
+                        assert!(supported_instructions().contains(&name));
+                        reached.insert(name);
+                    }
+                }
+            }
+        }
+        assert_eq!(reached.len(), supported_instructions().len());
+        for id in supported_syscalls() {
+            assert!(syscall_handler(id as u64).is_some());
+        }
+    }
+
+    #[test]
+    fn unknown_syscall_returns_enosys() {
+        let config = EmulatorConfig {
+            unknown_syscall: UnknownSyscallPolicy::Enosys,
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A7, 1000),
+                    ecall(),
+                    addi(Register::A0, Register::A0, Errno::ENOSYS as i32),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        let report = state.run().expect("run success");
+        assert_eq!(report.exit_code, Some(0));
+    }
+
+    #[test]
+    fn call_graph_counts_calls() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program(&[
+                    Instruction::new_jal(Register::Ra, 16),
+                    Instruction::new_jal(Register::Ra, 12),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    Instruction::new_ecall(),
+                    Instruction::new_jalr(Register::Zero, Register::Ra, 0),
+                ]),
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.record_call_graph();
+        state.run().expect("run success");
+        let call_graph = state.call_graph().expect("recorded");
+        let edges: Vec<_> = call_graph.edges().iter().collect();
+        assert_eq!(edges, vec![(&(CODE_START, CODE_START + 16), &2)]);
+        assert!(call_graph
+            .to_dot()
+            .contains("\"0x10000\" -> \"0x10010\" [label=\"2\"]"));
+    }
+
+    #[test]
+    fn stop_handle_interrupts_run() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(&program(&[Instruction::new_jal(Register::Zero, 0)]), &[])
+            .expect("bootstrap success");
+        let handle = state.stop_handle();
+        let thread = std::thread::spawn(move || state.run().expect("run success"));
+        handle.stop();
+        let report = thread.join().expect("thread success");
+        assert_eq!(report.stop_reason, StopReason::Interrupted);
+    }
+
+    #[test]
+    fn syscall_costs_exhaust_gas_faster() {
+        let run_loop = |syscall_costs: BTreeMap<EmulatorValue, u64>| {
+            let config = EmulatorConfig {
+                gas_budget: Some(100),
+                syscall_costs,
+                ..EmulatorConfig::default()
+            };
+            let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+            state
+                .bootstrap(
+                    &program![
+                        li(Register::A0, 1),
+                        li(Register::A2, 0),
+                        li(Register::A7, SyscallId::Write as i32),
+                        ecall(),
+                        Instruction::new_jal(Register::Zero, -12),
+                    ],
+                    &[],
+                )
+                .expect("bootstrap success");
+            state.set_stdio(io::empty(), io::sink(), io::sink());
+            state.run().expect("run success")
+        };
+        let free = run_loop(BTreeMap::new());
+        let costly = run_loop(vec![(SyscallId::Write as u64, 10)].into_iter().collect());
+        assert_eq!(free.stop_reason, StopReason::OutOfGas { gas_used: 100 });
+        assert_eq!(free.instructions_retired, 100);
+        assert_eq!(costly.stop_reason, StopReason::OutOfGas { gas_used: 100 });
+        assert_eq!(costly.instructions_retired, 30);
+    }
+
+    #[test]
+    fn gas_budget_stops_run() {
+        let config = EmulatorConfig {
+            gas_budget: Some(10),
+            cost_model: CostModel {
+                divide: 4,
+                ..CostModel::default()
+            },
+            ..EmulatorConfig::default()
+        };
+        let mut state = EmulatorState::with_config(MEMORY_SIZE, config);
+        state
+            .bootstrap(
+                &program(&[
+                    li(Register::A0, 1),
+                    Instruction::new_divu(Register::A0, Register::A0, Register::A0),
+                    Instruction::new_jal(Register::Zero, -4),
+                ]),
+                &[],
+            )
+            .expect("bootstrap success");
+        let report = state.run().expect("run success");
+        assert_eq!(report.stop_reason, StopReason::OutOfGas { gas_used: 10 });
+        assert_eq!(report.instructions_retired, 4);
+    }
+
+    #[test]
+    fn memory_access_hook_reports_effective_address() {
+        let accesses = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    Instruction::new_sd(Register::Sp, Register::Zero, -16),
+                    Instruction::new_ld(Register::A0, Register::Sp, -16),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        let recorded = accesses.clone();
+        state.on_memory_access(move |access| recorded.lock().unwrap().push(access));
+        state.step().expect("step success");
+        state.step().expect("step success");
+        let sp = state.get_reg(Register::Sp);
+        assert_eq!(
+            *accesses.lock().unwrap(),
+            vec![
+                MemoryAccess {
+                    pc: CODE_START,
+                    kind: AccessKind::Store,
+                    effective_address: sp - 16,
+                    size: 8,
+                    region: MemoryRegion::Stack,
+                },
+                MemoryAccess {
+                    pc: CODE_START + 4,
+                    kind: AccessKind::Load,
+                    effective_address: sp - 16,
+                    size: 8,
+                    region: MemoryRegion::Stack,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn immediates_are_sign_extended() {
+        assert_eq!(exec_word(itype_word(0x13, 0, 0xfff), 0, 0), u64::MAX); // addi
+        assert_eq!(exec_word(itype_word(0x13, 3, 0xfff), 5, 0), 1); // sltiu
+        assert_eq!(
+            exec_word(itype_word(0x1b, 0, 1), 0x7fff_ffff, 0), // addiw
+            0xffff_ffff_8000_0000
+        );
+        assert_eq!(
+            exec_word(itype_word(0x13, 5, 0x404), 1 << 63, 0), // srai
+            0xf800_0000_0000_0000
+        );
+        assert_eq!(
+            exec_word(0x8000_0537, 0, 0), // lui a0,0x80000
+            0xffff_ffff_8000_0000
+        );
+    }
+
+    #[test]
+    fn scripted_input_blocks_until_available() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state
+            .bootstrap(
+                &program![
+                    li(Register::A0, 0),
+                    addi(Register::A1, Register::Sp, -8),
+                    li(Register::A2, 8),
+                    li(Register::A7, SyscallId::Read as i32),
+                    ecall(),
+                    Instruction::new_ld(Register::A0, Register::Sp, -8),
+                    li(Register::A7, SyscallId::Exit as i32),
+                    ecall(),
+                ],
+                &[],
+            )
+            .expect("bootstrap success");
+        state.set_stdio(io::empty(), io::sink(), io::sink());
+        state.set_scripted_input(ScriptedInput::new().then(100, b"*"));
+        let report = state.run().expect("run success");
+        assert_eq!(report.exit_code, Some(u64::from(b'*')));
+        assert_eq!(report.instructions_retired, 104);
+    }
+
+    #[test]
+    fn checksum_region_handles_unaligned_ranges() {
+        let mut state = EmulatorState::new(MEMORY_SIZE);
+        state.copy_mem(DATA_START + 7, b"abc");
+        assert_eq!(
+            state.checksum_region(DATA_START, 0),
+            Some(0xcbf2_9ce4_8422_2325)
+        );
+        assert_eq!(
+            state.checksum_region(DATA_START + 7, 1),
+            Some(0xaf63_dc4c_8601_ec8c)
+        );
+        assert_eq!(
+            state.checksum_region(DATA_START + 7, 3),
+            Some(0xe71f_a219_0541_574b)
+        );
+        assert_ne!(
+            state.checksum_region(DATA_START + 7, 3),
+            state.checksum_region(DATA_START + 8, 3)
+        );
+    }
+
+    #[test]
+    fn checksum_region_outside_of_memory_is_rejected() {
+        let state = EmulatorState::new(MEMORY_SIZE);
+        let end = MEMORY_SIZE as u64;
+        assert!(state.checksum_region(end - 8, 8).is_some());
+        assert!(state.checksum_region(end, 0).is_some());
+        assert_eq!(state.checksum_region(end - 8, 9), None);
+        assert_eq!(state.checksum_region(end + 1, 0), None);
+        assert_eq!(state.checksum_region(u64::MAX, 2), None);
+    }
+}
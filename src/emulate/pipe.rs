@@ -0,0 +1,116 @@
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// In-memory pipe created by `pipe2`, shared by all descriptors referring
+// to either of its ends. The number of open ends is tracked so that
+// readers see end-of-file once all write ends are closed.
+#[derive(Debug, Default)]
+struct Pipe {
+    buffer: VecDeque<u8>,
+    readers: usize,
+    writers: usize,
+}
+
+// One end of a pipe, closing the last descriptor referring to it (i.e.
+// dropping it) updates the count of open ends.
+#[derive(Debug)]
+pub(super) struct PipeEnd {
+    pipe: Arc<Mutex<Pipe>>,
+    pub(super) write: bool,
+    pub(super) nonblocking: bool,
+}
+
+impl PipeEnd {
+    pub(super) fn pair(nonblocking: bool) -> (PipeEnd, PipeEnd) {
+        let pipe = Arc::new(Mutex::new(Pipe {
+            buffer: VecDeque::new(),
+            readers: 1,
+            writers: 1,
+        }));
+        let read_end = PipeEnd {
+            pipe: pipe.clone(),
+            write: false,
+            nonblocking,
+        };
+        let write_end = PipeEnd {
+            pipe,
+            write: true,
+            nonblocking,
+        };
+        (read_end, write_end)
+    }
+
+    pub(super) fn duplicate(&self) -> PipeEnd {
+        let mut pipe = self.lock();
+        if self.write {
+            pipe.writers += 1;
+        } else {
+            pipe.readers += 1;
+        }
+        PipeEnd {
+            pipe: self.pipe.clone(),
+            write: self.write,
+            nonblocking: self.nonblocking,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Pipe> {
+        self.pipe.lock().expect("pipe not poisoned")
+    }
+
+    // Whether reading has to wait for a writer, which is the case for an
+    // empty pipe with write ends still open.
+    pub(super) fn is_starved(&self) -> bool {
+        let pipe = self.lock();
+        pipe.buffer.is_empty() && pipe.writers > 0
+    }
+
+    pub(super) fn is_broken(&self) -> bool {
+        self.lock().readers == 0
+    }
+
+    // Number of write ends of the pipe that are still open.
+    pub(super) fn writers(&self) -> usize {
+        self.lock().writers
+    }
+
+    // Whether both ends belong to the same pipe.
+    pub(super) fn same_pipe(&self, other: &PipeEnd) -> bool {
+        Arc::ptr_eq(&self.pipe, &other.pipe)
+    }
+}
+
+impl Drop for PipeEnd {
+    fn drop(&mut self) {
+        let mut pipe = self.lock();
+        if self.write {
+            pipe.writers -= 1;
+        } else {
+            pipe.readers -= 1;
+        }
+    }
+}
+
+impl Read for PipeEnd {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut pipe = self.lock();
+        let len = min(buf.len(), pipe.buffer.len());
+        for (byte, value) in buf.iter_mut().zip(pipe.buffer.drain(..len)) {
+            *byte = value;
+        }
+        Ok(len)
+    }
+}
+
+impl Write for PipeEnd {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().buffer.extend(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
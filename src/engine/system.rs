@@ -4,8 +4,10 @@ use std::mem::size_of;
 pub const PAGE_SIZE: usize = 4 * 1024;
 pub const NUMBER_OF_REGISTERS: usize = 32;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SyscallId {
     Exit = 93,
+    ExitGroup = 94,
     Read = 63,
     Write = 64,
     Open = 1024,
@@ -13,23 +15,81 @@ pub enum SyscallId {
     Brk = 214,
     Close = 57,
     Newfstat = 80,
+    SetTidAddress = 96,
+    Getpid = 172,
+    Gettid = 178,
+    Dup = 23,
+    Dup3 = 24,
+    Futex = 98,
+    Times = 153,
+    Getrusage = 165,
+    Clone = 220,
+    Clone3 = 435,
+    Ioctl = 29,
+    Mmap = 222,
+    Getrandom = 278,
+    Nanosleep = 101,
+    ClockGettime = 113,
+    ClockNanosleep = 115,
+    Readv = 65,
+    Writev = 66,
+    Sysinfo = 179,
+    Getrlimit = 163,
+    Prlimit64 = 261,
+    Pipe2 = 59,
+    Getcwd = 17,
+    Access = 1033,
+    Faccessat = 48,
+    RtSigaction = 134,
+    RtSigprocmask = 135,
+    Uname = 160,
+    SchedYield = 124,
+}
+
+// Error numbers as used by Linux, system calls report failure to the
+// guest by returning the negated error number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Errno {
+    EPERM = 1,
+    ENOENT = 2,
+    ESRCH = 3,
+    EIO = 5,
+    EBADF = 9,
+    EAGAIN = 11,
+    ENOMEM = 12,
+    EACCES = 13,
+    EFAULT = 14,
+    EEXIST = 17,
+    ENODEV = 19,
+    EISDIR = 21,
+    EINVAL = 22,
+    EMFILE = 24,
+    ENOTTY = 25,
+    ENOSPC = 28,
+    ESPIPE = 29,
+    EPIPE = 32,
+    ERANGE = 34,
+    ENAMETOOLONG = 36,
+    ENOSYS = 38,
 }
 
 // Prepares arguments on the stack like a UNIX system. Note that we
 // pass an empty environment and that all strings will be properly
-// zero-terminated and word-aligned:
+// zero-terminated and word-aligned. Arguments are arbitrary bytes, an
+// argument containing a zero byte hence appears cut short to the guest:
 //
 // | argc | argv[0] | ... | argv[n] | 0 | env[0] | ... | env[m] | 0 |
 //
-pub fn prepare_unix_stack(argv: &[String], sp: u64) -> Vec<u64> {
+pub fn prepare_unix_stack<A: AsRef<[u8]>>(argv: &[A], sp: u64) -> Vec<u64> {
     let mut stack = vec![];
     let argc = argv.len() as u64;
     let argv_ptrs: Vec<u64> = argv
         .iter()
         .rev()
         .map(|arg| {
-            let c_string = arg.to_owned() + "\0\0\0\0\0\0\0\0";
-            for chunk in c_string.as_bytes().chunks_exact(size_of::<u64>()).rev() {
+            let mut c_string = arg.as_ref().to_vec();
+            c_string.extend_from_slice(&[0; size_of::<u64>()]);
+            for chunk in c_string.chunks_exact(size_of::<u64>()).rev() {
                 stack.push(LittleEndian::read_u64(chunk));
             }
             sp - (stack.len() * size_of::<u64>()) as u64
@@ -44,6 +104,17 @@ pub fn prepare_unix_stack(argv: &[String], sp: u64) -> Vec<u64> {
     stack
 }
 
+// Computes the number of bytes `prepare_unix_stack` will push for the
+// given arguments `argv`, without actually preparing the stack.
+pub fn unix_stack_size<A: AsRef<[u8]>>(argv: &[A]) -> u64 {
+    let strings: usize = argv
+        .iter()
+        .map(|arg| (arg.as_ref().len() + size_of::<u64>()) / size_of::<u64>())
+        .sum();
+    let words = strings + argv.len() + 3; // argc, argv table, two terminators
+    (words * size_of::<u64>()) as u64
+}
+
 pub fn prepare_unix_stack32bit(argv: &[String], sp: u32) -> Vec<u64> {
     let mut stack32 = vec![];
     let argc32 = argv.len() as u32;
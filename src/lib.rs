@@ -4,6 +4,8 @@ pub mod util;
 pub mod disassemble;
 pub mod emulate;
 pub mod engine;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 
 use riscu::{load_object_file, Program};
 use std::path::Path;
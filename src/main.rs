@@ -24,7 +24,9 @@ use crate::unicorn::unroller::{prune_model, renumber_model, unroll_model};
 use crate::unicorn::write_model;
 
 use ::unicorn::disassemble::disassemble;
-use ::unicorn::emulate::EmulatorState;
+use ::unicorn::emulate::{
+    elf_entry_point, elf_read_only_data, program_argv, EmulatorConfig, EmulatorState,
+};
 use anyhow::{Context, Result};
 use bytesize::ByteSize;
 use cli::{collect_arg_values, expect_arg, expect_optional_arg, LogLevel, SatType, SmtType};
@@ -60,16 +62,40 @@ fn main() -> Result<()> {
         Some(("emulate", args)) => {
             let input = expect_arg::<PathBuf>(args, "input-file")?;
             let memory_size = ByteSize::mib(*args.get_one("memory").unwrap()).as_u64();
-            let arg0 = expect_arg::<String>(args, "input-file")?;
+            let arg0 = expect_optional_arg::<String>(args, "argv0")?;
             let extras = collect_arg_values(args, "extras");
-
-            let argv = [vec![arg0], extras].concat();
-            let program = load_object_file(input)?;
-            let mut emulator = EmulatorState::new(memory_size as usize);
-            emulator.bootstrap(&program, &argv);
-            emulator.run();
-
-            Ok(())
+            let protect_rodata = args.get_flag("protect-rodata");
+            let output_log = expect_optional_arg::<String>(args, "output-log")?;
+            let summary_line = args.get_flag("summary");
+
+            let argv = program_argv(&input, arg0.as_deref(), &extras);
+            let program = load_object_file(&input)?;
+            let entry = elf_entry_point(&input)?;
+            let config = EmulatorConfig {
+                summary_line,
+                ..EmulatorConfig::default()
+            };
+            let mut emulator = EmulatorState::with_config(memory_size as usize, config);
+            emulator.bootstrap_at(&program, &argv, entry)?;
+            if protect_rodata {
+                for range in elf_read_only_data(&input)? {
+                    emulator.protect_read_only(range);
+                }
+            }
+            if let Some(path) = output_log {
+                emulator.tee_output(File::create(path)?);
+            }
+            let report = emulator.run()?;
+
+            // Propagate the exit code of the guest to the host process.
+            match report.host_exit_code() {
+                Some(exit_code) if exit_code != 0 => {
+                    drop(emulator);
+                    stdout().flush()?;
+                    std::process::exit(i32::from(exit_code))
+                }
+                _ => Ok(()),
+            }
         }
         Some(("beator", args)) | Some(("qubot", args)) => {
             let is_beator = matches.subcommand().unwrap().0 == "beator";
@@ -176,17 +202,15 @@ fn main() -> Result<()> {
                 assert!(!input_is_dimacs, "cannot compile arbitrary DIMACS");
                 assert!(!discretize, "cannot compile with discretized memory");
 
-                // TODO: Just a workaround to get `argv` again.
-                let arg0 = expect_arg::<String>(args, "input-file")?;
                 let extras = collect_arg_values(args, "extras");
-                let argv = [vec![arg0], extras].concat();
+                let argv = program_argv(&input, None, &extras);
 
                 let program = load_object_file(&input)?;
                 let mut emulator = EmulatorState::new(memory_size as usize);
                 // TODO: Eventually patch original program first, then bootstrap.
-                emulator.bootstrap(&program, &argv); // bootstrap original program
+                emulator.bootstrap(&program, &argv)?; // bootstrap original program
                 compile_model_into_program(&mut emulator, &model.unwrap(), &program);
-                emulator.run();
+                emulator.run()?;
                 return Ok(());
             }
 
@@ -199,7 +223,7 @@ fn main() -> Result<()> {
                 let mut emulator = EmulatorState::new(memory_size as usize);
                 emulator.prepare(&program); // only loads the code
                 load_model_into_emulator(&mut emulator, &model.unwrap());
-                emulator.run();
+                emulator.run()?;
                 return Ok(());
             }
 
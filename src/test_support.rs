@@ -0,0 +1,82 @@
+//! # Assertions for tests running emulated programs
+//!
+//! Failing assertions report the actual value in hexadecimal, together
+//! with where emulation stopped, instead of just two decimal numbers.
+//! Only compiled for unit tests of this crate, or with the `test-support`
+//! feature enabled (as the integration tests do via a dev-dependency).
+
+use crate::disassemble::InstructionText;
+use crate::emulate::{EmulatorState, EmulatorValue, RunReport};
+use riscu::Register;
+use std::fmt::Write;
+
+// Number of instructions shown before and after the program counter.
+const CONTEXT_INSTRUCTIONS: u64 = 3;
+
+// Size of a single (uncompressed) instruction in bytes.
+const INSTRUCTION_SIZE: u64 = 4;
+
+// Asserts that the run described by `outcome` exited with the `expected`
+// exit code. A report does not hold the emulated memory, the failure
+// message hence shows the stop reason and final program counter only (see
+// `assert_register` for a report including disassembly).
+#[track_caller]
+pub fn assert_exit_code(outcome: &RunReport, expected: EmulatorValue) {
+    match outcome.exit_code {
+        Some(actual) if actual == expected => {}
+        Some(actual) => panic!(
+            "exit code mismatch: expected {:#x}, actual {:#x} ({})\nstopped at pc={:#x} ({:?})",
+            expected, actual, actual as i64, outcome.final_pc, outcome.stop_reason
+        ),
+        None => panic!(
+            "exit code mismatch: expected {:#x}, but guest did not exit\nstopped at pc={:#x} ({:?})",
+            expected, outcome.final_pc, outcome.stop_reason
+        ),
+    }
+}
+
+// Asserts that register `reg` of `emu` holds the `expected` value. The
+// failure message shows the disassembly surrounding the program counter.
+#[track_caller]
+pub fn assert_register(emu: &EmulatorState, reg: Register, expected: EmulatorValue) {
+    let actual = emu.get_reg(reg);
+    if actual != expected {
+        panic!(
+            "register {:?} mismatch: expected {:#x}, actual {:#x} ({})\n{}",
+            reg,
+            expected,
+            actual,
+            actual as i64,
+            disassembly_near_pc(emu)
+        );
+    }
+}
+
+// Renders the instructions surrounding the program counter, marking the
+// one at the program counter itself. Addresses outside of the code segment
+// are skipped, unimplemented instructions are shown as such.
+pub fn disassembly_near_pc(emu: &EmulatorState) -> String {
+    let pc = emu.get_program_counter();
+    let code = emu.code_segment();
+    let context = CONTEXT_INSTRUCTIONS * INSTRUCTION_SIZE;
+    let first = pc.saturating_sub(context).max(code.start);
+    let last = pc.saturating_add(context + INSTRUCTION_SIZE).min(code.end);
+    let mut text = format!("disassembly near pc={:#x}:\n", pc);
+    for address in (first..last).step_by(INSTRUCTION_SIZE as usize) {
+        let marker = if address == pc { "=>" } else { "  " };
+        let _ = match emu.instruction_at(address) {
+            Some(instr) => writeln!(
+                text,
+                "{} {:#x}: {}",
+                marker,
+                address,
+                InstructionText(instr)
+            ),
+            None => writeln!(text, "{} {:#x}: <unknown>", marker, address),
+        };
+    }
+    if !code.contains(&pc) {
+        let _ = writeln!(text, "   (pc outside of code segment {:#x?})", code);
+    }
+    text
+}
@@ -0,0 +1,28 @@
+use std::path::Path;
+use unicorn::emulate::{elf_entry_point, program_argv, EmulatorState};
+use unicorn::load_elf;
+
+// Statically linked musl binary built from `fixtures/hello.c` by running
+// `make -C tests/fixtures` (needs `riscv64-linux-musl-gcc` in the path),
+// which the Linux CI jobs do before testing. It is not part of the
+// repository, tests using it are skipped in case it is missing.
+const HELLO_MUSL: &str = "tests/fixtures/hello-musl";
+
+#[test]
+fn can_emulate_musl_hello_world() {
+    if !Path::new(HELLO_MUSL).exists() {
+        eprintln!("skipping, fixture {} not found", HELLO_MUSL);
+        return;
+    }
+
+    let program = load_elf(HELLO_MUSL).expect("can load fixture");
+    let entry = elf_entry_point(HELLO_MUSL).expect("can read entry point");
+    let argv = program_argv(HELLO_MUSL, None, &[]);
+    let mut emulator = EmulatorState::new(128 * 1024 * 1024);
+    let result = emulator
+        .run_with_io_at(&program, &argv, entry, b"")
+        .expect("can run to completion");
+
+    assert_eq!(result.exit_code, Some(0));
+    assert_eq!(result.stdout, b"hello, world\n");
+}